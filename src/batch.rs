@@ -0,0 +1,285 @@
+//! Decoding of archives with one hex encoded frame per line.
+//!
+//! Lines may carry other fields, such as the time a frame was received and
+//! its VIN, which a [`LineFormat`] tells apart. [`decode_files`] decodes
+//! whole files, on all cores with the `rayon` feature, into a [`Report`]
+//! with [`Stats`] and the lines that failed; [`decode_text_with`] also
+//! hands out the packets.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::packet::error::Error;
+use crate::packet::{parser, Packet};
+
+/// Kind of the failures of lines without the field of the frame.
+pub const MISSING_FRAME: &str = "missing_frame";
+
+/// Lines decoded at once by [`decode_text_with`].
+const CHUNK: usize = 4096;
+
+/// Fields of a line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineFormat {
+    /// Separator of the fields; runs of whitespace if `None`.
+    pub separator: Option<char>,
+    /// Index of the field with the frame, from 0; the last field if `None`,
+    /// which suits lines with and without a prefix alike.
+    pub frame: Option<usize>,
+    /// Index of the field with the VIN, counted for lines that fail to
+    /// decode.
+    pub vin: Option<usize>,
+}
+
+impl LineFormat {
+    /// Fields of the frame and the VIN in `line`, if any.
+    pub fn fields<'a>(&self, line: &'a str) -> (Option<&'a str>, Option<&'a str>) {
+        let fields: Vec<&str> = match self.separator {
+            Some(separator) => line.split(separator).map(str::trim).collect(),
+            None => line.split_whitespace().collect(),
+        };
+        let frame = match self.frame {
+            Some(n) => fields.get(n),
+            None => fields.last(),
+        };
+        let vin = self.vin.and_then(|n| fields.get(n));
+        (frame.copied(), vin.copied())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub format: LineFormat,
+    pub parser: parser::Options,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Lines that are not blank.
+    pub lines: u64,
+    /// Frames decoded.
+    pub frames: u64,
+    /// Frames decoded per command, such as `VehicleLogin`.
+    pub commands: BTreeMap<String, u64>,
+    /// Failed lines per kind of error, see [`Error::kind`] and
+    /// [`MISSING_FRAME`].
+    pub errors: BTreeMap<&'static str, u64>,
+    /// Lines per VIN, taken from the frame or, for lines that fail, from
+    /// the field of the VIN.
+    pub vins: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    pub fn merge(&mut self, other: Stats) {
+        self.lines += other.lines;
+        self.frames += other.frames;
+        for (command, n) in other.commands {
+            *self.commands.entry(command).or_default() += n;
+        }
+        for (kind, n) in other.errors {
+            *self.errors.entry(kind).or_default() += n;
+        }
+        for (vin, n) in other.vins {
+            *self.vins.entry(vin).or_default() += n;
+        }
+    }
+
+    /// Lines that failed.
+    pub fn failed(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+/// Line that failed to decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub file: PathBuf,
+    /// Number of the line, from 1.
+    pub line: usize,
+    pub kind: &'static str,
+    pub error: String,
+    /// The line as it was.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub stats: Stats,
+    /// Failed lines in the order of the files and lines.
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    /// Counts `line`, decoded from `text` on line `number` of `file`.
+    pub fn add(&mut self, file: &Path, number: usize, text: &str, line: &Line) {
+        let stats = &mut self.stats;
+        match line {
+            Line::Blank => return,
+            Line::Decoded(packet) => {
+                stats.frames += 1;
+                let command = format!("{:?}", packet.command);
+                *stats.commands.entry(command).or_default() += 1;
+                *stats.vins.entry(packet.vin.as_str().into()).or_default() += 1;
+            }
+            Line::Failed { vin, kind, error } => {
+                *stats.errors.entry(kind).or_default() += 1;
+                if let Some(vin) = vin {
+                    *stats.vins.entry(vin.clone()).or_default() += 1;
+                }
+                self.failures.push(Failure {
+                    file: file.to_path_buf(),
+                    line: number,
+                    kind,
+                    error: error.clone(),
+                    text: text.into(),
+                });
+            }
+        }
+        stats.lines += 1;
+    }
+
+    /// Writes the failures to `path` as tab separated file, line number,
+    /// error kind, error and line.
+    pub fn write_failures(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        for failure in &self.failures {
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                failure.file.display(),
+                failure.line,
+                failure.kind,
+                failure.error,
+                failure.text
+            )?;
+        }
+        out.flush()
+    }
+
+    /// Adds `other`, whose failures come after those of `self`.
+    pub fn merge(&mut self, other: Report) {
+        self.stats.merge(other.stats);
+        self.failures.extend(other.failures);
+    }
+}
+
+/// A line decoded by [`decode_line`].
+#[derive(Debug)]
+pub enum Line {
+    /// Nothing but whitespace, which is not counted.
+    Blank,
+    Decoded(Packet),
+    Failed {
+        /// VIN from the field of the VIN, if any.
+        vin: Option<String>,
+        kind: &'static str,
+        error: String,
+    },
+}
+
+/// Decodes one line.
+pub fn decode_line(line: &str, options: &Options) -> Line {
+    if line.trim().is_empty() {
+        return Line::Blank;
+    }
+    let (frame, vin) = options.format.fields(line);
+    let vin = vin.map(String::from);
+    let frame = match frame {
+        Some(frame) => frame,
+        None => {
+            return Line::Failed {
+                vin,
+                kind: MISSING_FRAME,
+                error: "no field with a frame".into(),
+            }
+        }
+    };
+    let packet = hex::decode(frame)
+        .map_err(Error::from)
+        .and_then(|data| parser::parse_bytes_with(&data, &options.parser));
+    match packet {
+        Ok(packet) => Line::Decoded(packet),
+        Err(err) => Line::Failed {
+            vin,
+            kind: err.kind(),
+            error: err.to_string(),
+        },
+    }
+}
+
+/// Maps `items` in order, in parallel with the `rayon` feature.
+#[cfg(feature = "rayon")]
+fn map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync + Send) -> Vec<U> {
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map<T, U>(items: &[T], f: impl Fn(&T) -> U) -> Vec<U> {
+    items.iter().map(f).collect()
+}
+
+/// Decodes the lines of `text`, read from `file`.
+pub fn decode_text(file: &Path, text: &str, options: &Options) -> Report {
+    let mut report = Report::default();
+    let result = decode_text_with(
+        file,
+        text,
+        options,
+        &mut report,
+        |_| Ok::<_, Infallible>(()),
+    );
+    match result {
+        Ok(()) => report,
+        Err(never) => match never {},
+    }
+}
+
+/// Decodes the lines of `text`, read from `file`, into `report`, handing
+/// the packets to `f` in the order of the lines and stopping at its first
+/// error.
+pub fn decode_text_with<F, E>(
+    file: &Path,
+    text: &str,
+    options: &Options,
+    report: &mut Report,
+    mut f: F,
+) -> Result<(), E>
+where
+    F: FnMut(Packet) -> Result<(), E>,
+{
+    let lines: Vec<&str> = text.lines().collect();
+    // bounds the packets held at once
+    for (chunk, lines) in lines.chunks(CHUNK).enumerate() {
+        let decoded = map(lines, |line| decode_line(line, options));
+        for (n, (text, line)) in lines.iter().zip(decoded).enumerate() {
+            report.add(file, chunk * CHUNK + n + 1, text, &line);
+            if let Line::Decoded(packet) = line {
+                f(packet)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the files at `paths`, failing if one cannot be read. Bytes that
+/// are not UTF-8 fail the lines they are on.
+pub fn decode_files<P: AsRef<Path> + Sync>(paths: &[P], options: &Options) -> io::Result<Report> {
+    let reports: Vec<io::Result<Report>> = map(paths, |path| {
+        let path = path.as_ref();
+        let data = fs::read(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+        Ok(decode_text(path, &String::from_utf8_lossy(&data), options))
+    });
+    let mut report = Report::default();
+    for other in reports {
+        report.merge(other?);
+    }
+    Ok(report)
+}
@@ -0,0 +1,193 @@
+//! Connection handling shared by the servers in `src/bin`: one thread per
+//! connection runs a session over the frames read, answers on the
+//! connection and hands emitted packets to a callback.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use vin::packet::error::Error;
+use vin::packet::platform::{Action, Close, PlatformSession};
+use vin::packet::relay::Upstream;
+use vin::packet::stream::{Chunk, Framer};
+use vin::packet::{parser, Packet};
+
+/// Sessions answering the packets of one connection.
+pub trait Session: Send + 'static {
+    fn handle(&mut self, packet: Packet, now: Duration) -> Vec<Action>;
+    fn poll(&mut self, now: Duration) -> Vec<Action>;
+    fn deadline(&self) -> Option<Duration>;
+}
+
+impl Session for PlatformSession {
+    fn handle(&mut self, packet: Packet, now: Duration) -> Vec<Action> {
+        PlatformSession::handle(self, packet, now)
+    }
+
+    fn poll(&mut self, now: Duration) -> Vec<Action> {
+        PlatformSession::poll(self, now)
+    }
+
+    fn deadline(&self) -> Option<Duration> {
+        PlatformSession::deadline(self)
+    }
+}
+
+impl Session for Upstream {
+    fn handle(&mut self, packet: Packet, now: Duration) -> Vec<Action> {
+        Upstream::handle(self, packet, now)
+    }
+
+    fn poll(&mut self, now: Duration) -> Vec<Action> {
+        Upstream::poll(self, now)
+    }
+
+    fn deadline(&self) -> Option<Duration> {
+        Upstream::deadline(self)
+    }
+}
+
+/// Receives emitted packets with the peer and the frame they came in.
+pub type Emit = Arc<dyn Fn(&str, &[u8], Packet) + Send + Sync>;
+
+pub fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Accepts connections forever, running a session from `session` on each.
+pub fn serve<S, F>(listener: TcpListener, session: F, options: parser::Options, emit: Emit)
+where
+    S: Session,
+    F: Fn() -> S,
+{
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("accept: {}", err);
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|_| "unknown".into());
+        let connection = Connection {
+            stream,
+            peer,
+            options: options.clone(),
+            emit: Arc::clone(&emit),
+        };
+        let session = session();
+        thread::spawn(move || connection.run(session));
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    peer: String,
+    options: parser::Options,
+    emit: Emit,
+}
+
+impl Connection {
+    fn run<S: Session>(mut self, mut session: S) {
+        eprintln!("{}: connected", self.peer);
+        let mut framer = Framer::new();
+        let mut buf = [0; 4096];
+        loop {
+            let wait = session
+                .deadline()
+                .map(|deadline| deadline.saturating_sub(now()))
+                .unwrap_or_default()
+                .max(Duration::from_millis(1));
+            if let Err(err) = self.stream.set_read_timeout(Some(wait)) {
+                eprintln!("{}: {}", self.peer, err);
+                return;
+            }
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    eprintln!("{}: closed by peer", self.peer);
+                    return;
+                }
+                Ok(n) => framer.extend(&buf[..n]),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    let actions = session.poll(now());
+                    if !self.carry_out(actions, &[]) {
+                        return;
+                    }
+                    continue;
+                }
+                Err(err) => {
+                    eprintln!("{}: {}", self.peer, err);
+                    return;
+                }
+            }
+            while let Some(chunk) = framer.next() {
+                let frame = match chunk {
+                    Chunk::Frame(frame) => frame,
+                    Chunk::Garbage(data) => {
+                        eprintln!("{}: skipped {} bytes", self.peer, data.len());
+                        continue;
+                    }
+                };
+                match parser::parse_bytes_with(&frame, &self.options) {
+                    Ok(packet) => {
+                        let actions = session.handle(packet, now());
+                        if !self.carry_out(actions, &frame) {
+                            return;
+                        }
+                    }
+                    Err(Error::Checksum { .. }) => framer.resync(frame),
+                    Err(err) => eprintln!(
+                        "{}: dropped frame: {}: {}",
+                        self.peer,
+                        err,
+                        hex::encode(&frame)
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Carries out `actions` for `frame`, returning whether the connection
+    /// stays open.
+    fn carry_out(&mut self, actions: Vec<Action>, frame: &[u8]) -> bool {
+        for action in actions {
+            match action {
+                Action::Send(packet) => {
+                    let sent = packet
+                        .encode()
+                        .map_err(|err| err.to_string())
+                        .and_then(|data| {
+                            self.stream.write_all(&data).map_err(|err| err.to_string())
+                        });
+                    if let Err(err) = sent {
+                        eprintln!("{}: cannot answer: {}", self.peer, err);
+                        return false;
+                    }
+                }
+                Action::Emit(packet) => (self.emit)(&self.peer, frame, packet),
+                Action::Close(close) => {
+                    let reason = match close {
+                        Close::Logout => "logged out".to_string(),
+                        Close::Idle => "idle".to_string(),
+                        Close::Violation(violation) => violation.to_string(),
+                    };
+                    eprintln!("{}: closing: {}", self.peer, reason);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
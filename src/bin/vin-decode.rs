@@ -0,0 +1,468 @@
+//! Decodes hex encoded frames and prints them as a tree, as JSON lines or
+//! as annotated hex dumps.
+//!
+//! ```text
+//! vin-decode 232301fe4c5a...5c
+//! vin-decode --file frames.txt --format json
+//! vin-decode --format dump 232301fe4c5a...5c
+//! tcpdump ... | vin-decode --stream
+//! vin-decode --pcap tbox.pcapng
+//! vin-decode --batch logs/2018-10-*.log --vin-field 2 --failed failed.tsv
+//! ```
+
+use std::fmt::Display;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use serde_json::json;
+use vin::batch::{self, LineFormat};
+use vin::packet::error::Error;
+use vin::packet::stream::{self, Framer};
+use vin::packet::{dump, json, parser, Packet, Strictness};
+use vin::pcap::{self, Frames};
+
+#[derive(Parser)]
+#[command(version, about = "Decode GB/T 32960 frames given in hex")]
+struct Args {
+    /// Frames in hex; read from `--file` or stdin when missing.
+    frames: Vec<String>,
+
+    /// File with one frame per line.
+    #[arg(short, long, conflicts_with = "frames")]
+    file: Option<PathBuf>,
+
+    /// Treat the input as one concatenated stream of frames instead of one
+    /// frame per line.
+    #[arg(short, long)]
+    stream: bool,
+
+    /// Decode the TCP traffic of a pcap or pcapng capture, always as JSON
+    /// lines.
+    #[arg(long, conflicts_with_all = ["frames", "file", "stream"])]
+    pcap: Option<PathBuf>,
+
+    /// Decode archive files of one frame per line, on all cores, and print
+    /// statistics instead of the packets.
+    #[arg(long, num_args = 1.., conflicts_with_all = ["frames", "file", "stream", "pcap"])]
+    batch: Vec<PathBuf>,
+
+    /// Separator of the fields of `--batch` lines; whitespace by default.
+    #[arg(long, requires = "batch")]
+    separator: Option<char>,
+
+    /// Field of `--batch` lines with the frame, from 1; the last by
+    /// default.
+    #[arg(long, requires = "batch", value_parser = clap::value_parser!(u16).range(1..))]
+    frame_field: Option<u16>,
+
+    /// Field of `--batch` lines with the VIN, from 1, to count lines that
+    /// fail by VIN.
+    #[arg(long, requires = "batch", value_parser = clap::value_parser!(u16).range(1..))]
+    vin_field: Option<u16>,
+
+    /// Write the `--batch` lines that fail to this file, as tab separated
+    /// file, line number, error kind, error and line.
+    #[arg(long, requires = "batch")]
+    failed: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = Format::Tree)]
+    format: Format,
+
+    /// Fail on VINs and ICCIDs that do not validate.
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Tree,
+    Json,
+    /// Annotated hex dump showing the bytes of every field.
+    Dump,
+}
+
+/// Piece of the input, located by its line and byte offset in the line (or
+/// in the whole input with `--stream`).
+enum Chunk {
+    Frame(Vec<u8>),
+    Tail(Vec<u8>, &'static str),
+}
+
+/// Splits `data` into frames with a [`Framer`], leaving bytes that cannot
+/// start or complete a frame as tails. A frame that fails its check byte and
+/// does not decode even with it fixed is taken for a false start marker.
+fn split(data: &[u8], options: &parser::Options) -> Vec<(usize, Chunk)> {
+    let mut framer = Framer::new();
+    framer.extend(data);
+    let mut chunks = Vec::new();
+    let mut pos = 0;
+    while let Some(chunk) = framer.next() {
+        let chunk = match chunk {
+            stream::Chunk::Frame(frame)
+                if parser::verify_checksum(&frame).is_err() && decode(&frame, options).is_err() =>
+            {
+                framer.resync(frame);
+                continue;
+            }
+            stream::Chunk::Frame(frame) => Chunk::Frame(frame),
+            stream::Chunk::Garbage(data) => Chunk::Tail(data, "no start marker"),
+        };
+        let len = match &chunk {
+            Chunk::Frame(data) | Chunk::Tail(data, _) => data.len(),
+        };
+        chunks.push((pos, chunk));
+        pos += len;
+    }
+    let rest = framer.pending();
+    if !rest.is_empty() {
+        let reason = if rest.starts_with(b"##") {
+            "truncated frame"
+        } else {
+            "no start marker"
+        };
+        chunks.push((pos, Chunk::Tail(rest.to_vec(), reason)));
+    }
+    chunks
+}
+
+/// Decodes `frame`; a frame whose only fault is its check byte is decoded
+/// anyway and returned along with the checksum error.
+fn decode(frame: &[u8], options: &parser::Options) -> Result<(Packet, Option<Error>), Error> {
+    match parser::parse_bytes_with(frame, options) {
+        Ok(packet) => Ok((packet, None)),
+        Err(err @ Error::Checksum { expected, actual }) => {
+            let mut fixed = frame.to_vec();
+            *fixed.last_mut().expect("frames are not empty") = expected;
+            let mut packet = parser::parse_bytes_with(&fixed, options)?;
+            packet.bcc = actual;
+            Ok((packet, Some(err)))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+struct Printer {
+    format: Format,
+    color: bool,
+    failed: bool,
+}
+
+impl Printer {
+    fn paint(&self, code: &str, text: impl Display) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn chunk(&mut self, line: usize, offset: usize, chunk: Chunk, options: &parser::Options) {
+        match chunk {
+            Chunk::Frame(frame) if self.format == Format::Dump => {
+                self.dump(line, offset, &frame, options)
+            }
+            Chunk::Frame(frame) => match decode(&frame, options) {
+                Ok((packet, checksum)) => self.packet(line, offset, &packet, checksum),
+                Err(err) => self.error(line, offset, &frame, &err),
+            },
+            Chunk::Tail(tail, reason) => self.tail(line, offset, &tail, reason),
+        }
+    }
+
+    fn packet(&mut self, line: usize, offset: usize, packet: &Packet, checksum: Option<Error>) {
+        let mut warnings: Vec<String> = Vec::new();
+        if let Some(err) = checksum {
+            self.failed = true;
+            warnings.push(err.to_string());
+        }
+        if let Some(issue) = packet.vin.issue() {
+            warnings.push(format!("VIN: {}", issue));
+        }
+        if self.format == Format::Json {
+            let value = json!({
+                "line": line,
+                "offset": offset,
+                "packet": json::to_value(packet),
+                "warnings": warnings,
+            });
+            println!("{}", value);
+            return;
+        }
+        println!(
+            "{}",
+            self.paint("1", format!("line {} offset {}", line, offset))
+        );
+        for warning in warnings {
+            println!("  {} {}", self.paint("33", "warning:"), warning);
+        }
+        println!("  command   {:?}", packet.command);
+        println!("  response  {:?}", packet.response);
+        println!("  vin       {}", packet.vin);
+        println!("  encrypt   {:?}", packet.encrypt);
+        println!("  body_len  {}", packet.body_len);
+        println!("  bcc       {:#04x}", packet.bcc);
+        let body = format!("{:#?}", packet.body);
+        println!("  body      {}", body.replace('\n', "\n  "));
+    }
+
+    fn dump(&mut self, line: usize, offset: usize, frame: &[u8], options: &parser::Options) {
+        let (mut spans, mut result) = parser::annotate(frame, options);
+        if let Err(Error::Checksum { expected, actual }) = result {
+            // annotate the body all the same, showing the check byte as sent
+            let mut fixed = frame.to_vec();
+            *fixed.last_mut().expect("frames are not empty") = expected;
+            let (fixed_spans, fixed_result) = parser::annotate(&fixed, options);
+            spans = fixed_spans;
+            if let Some(bcc) = spans.iter_mut().find(|span| span.path == "bcc") {
+                bcc.raw = vec![actual];
+                bcc.value = actual.to_string();
+            }
+            result = fixed_result.and(Err(Error::Checksum { expected, actual }));
+        }
+        println!(
+            "{}",
+            self.paint("1", format!("line {} offset {}", line, offset))
+        );
+        print!("{}", dump::render(frame, &spans));
+        if let Err(err) = result {
+            self.failed = true;
+            println!("{} {}", self.paint("31", "error:"), err);
+        }
+    }
+
+    fn error(&mut self, line: usize, offset: usize, frame: &[u8], err: &Error) {
+        self.failed = true;
+        if self.format == Format::Json {
+            let value = json!({
+                "line": line,
+                "offset": offset,
+                "error": err.to_string(),
+                "error_offset": err.offset(),
+                "path": err.path(),
+                "hex": hex::encode(frame),
+            });
+            println!("{}", value);
+            return;
+        }
+        println!(
+            "{}",
+            self.paint("1", format!("line {} offset {}", line, offset))
+        );
+        println!("  {} {}", self.paint("31", "error:"), err);
+        if let Some(at) = err.offset().filter(|&at| at < frame.len()) {
+            println!(
+                "  {}{}",
+                hex::encode(&frame[..at]),
+                self.paint("31", hex::encode(&frame[at..]))
+            );
+        } else {
+            println!("  {}", hex::encode(frame));
+        }
+    }
+
+    /// Prints a frame of a capture as a JSON line.
+    fn captured(&mut self, frame: &pcap::Frame, options: &parser::Options) {
+        let mut value = json!({
+            "time_ms": frame.at.as_millis() as u64,
+            "src": frame.src.to_string(),
+            "dst": frame.dst.to_string(),
+        });
+        match decode(&frame.data, options) {
+            Ok((packet, checksum)) => {
+                let mut warnings: Vec<String> = Vec::new();
+                if let Some(err) = checksum {
+                    self.failed = true;
+                    warnings.push(err.to_string());
+                }
+                if let Some(issue) = packet.vin.issue() {
+                    warnings.push(format!("VIN: {}", issue));
+                }
+                value["packet"] = json::to_value(&packet);
+                value["warnings"] = json!(warnings);
+            }
+            Err(err) => {
+                self.failed = true;
+                value["error"] = json!(err.to_string());
+                value["hex"] = json!(hex::encode(&frame.data));
+            }
+        }
+        println!("{}", value);
+    }
+
+    fn tail(&mut self, line: usize, offset: usize, tail: &[u8], reason: &str) {
+        self.failed = true;
+        if self.format == Format::Json {
+            let value = json!({
+                "line": line,
+                "offset": offset,
+                "error": format!("undecodable tail: {}", reason),
+                "hex": hex::encode(tail),
+            });
+            println!("{}", value);
+            return;
+        }
+        println!(
+            "{}",
+            self.paint("1", format!("line {} offset {}", line, offset))
+        );
+        println!(
+            "  {} undecodable tail of {} bytes: {}",
+            self.paint("31", "error:"),
+            tail.len(),
+            reason
+        );
+        println!("  {}", self.paint("31", hex::encode(tail)));
+    }
+}
+
+/// Prints the frames of the capture at `path`.
+fn capture(path: &Path, printer: &mut Printer, options: &parser::Options) -> ExitCode {
+    let frames = match Frames::open(path) {
+        Ok(frames) => frames,
+        Err(err) => {
+            eprintln!("vin-decode: {}: {}", path.display(), err);
+            return ExitCode::from(2);
+        }
+    };
+    for frame in frames {
+        match frame {
+            Ok(frame) => printer.captured(&frame, options),
+            Err(err) => {
+                // a capture cut off by the end of tcpdump is still worth its frames
+                printer.failed = true;
+                eprintln!("vin-decode: {}: {}", path.display(), err);
+            }
+        }
+    }
+    if printer.failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Decodes the archive files at `paths` and prints their statistics.
+fn batch(args: &Args, options: parser::Options) -> ExitCode {
+    let field = |n: Option<u16>| n.map(|n| n as usize - 1);
+    let options = batch::Options {
+        format: LineFormat {
+            separator: args.separator,
+            frame: field(args.frame_field),
+            vin: field(args.vin_field),
+        },
+        parser: options,
+    };
+    let report = match batch::decode_files(&args.batch, &options) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("vin-decode: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    if let Some(path) = &args.failed {
+        if let Err(err) = report.write_failures(path) {
+            eprintln!("vin-decode: {}: {}", path.display(), err);
+            return ExitCode::from(2);
+        }
+    }
+
+    let stats = &report.stats;
+    if args.format == Format::Json {
+        println!("{}", json!(stats));
+    } else {
+        println!("lines   {}", stats.lines);
+        println!("frames  {}", stats.frames);
+        println!("failed  {}", stats.failed());
+        for (title, counts) in [("commands", &stats.commands), ("vins", &stats.vins)] {
+            println!("{}", title);
+            for (name, n) in counts {
+                println!("  {:<20} {}", name, n);
+            }
+        }
+        println!("errors");
+        for (kind, n) in &stats.errors {
+            println!("  {:<20} {}", kind, n);
+        }
+    }
+    if report.failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let strictness = if args.strict {
+        Strictness::Reject
+    } else {
+        Strictness::Warn
+    };
+    let options = parser::Options {
+        vin: strictness,
+        iccid: strictness,
+    };
+    let mut printer = Printer {
+        format: args.format,
+        color: args.format != Format::Json && io::stdout().is_terminal(),
+        failed: false,
+    };
+    if let Some(path) = &args.pcap {
+        return capture(path, &mut printer, &options);
+    }
+    if !args.batch.is_empty() {
+        return batch(&args, options);
+    }
+
+    let input = if !args.frames.is_empty() {
+        args.frames.join("\n")
+    } else if let Some(path) = &args.file {
+        match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("vin-decode: {}: {}", path.display(), err);
+                return ExitCode::from(2);
+            }
+        }
+    } else {
+        let mut text = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut text) {
+            eprintln!("vin-decode: stdin: {}", err);
+            return ExitCode::from(2);
+        }
+        text
+    };
+
+    let lines: Vec<String> = if args.stream {
+        vec![input.split_whitespace().collect()]
+    } else {
+        input
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect()
+    };
+    for (i, text) in lines.iter().enumerate() {
+        if text.is_empty() {
+            continue;
+        }
+        let data = match hex::decode(text) {
+            Ok(data) => data,
+            Err(err) => {
+                printer.failed = true;
+                eprintln!("vin-decode: line {}: invalid hex: {}", i + 1, err);
+                continue;
+            }
+        };
+        for (offset, chunk) in split(&data, &options) {
+            printer.chunk(i + 1, offset, chunk, &options);
+        }
+    }
+
+    if printer.failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
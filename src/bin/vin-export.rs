@@ -0,0 +1,150 @@
+//! Flattens the real time and reissue reports of archive files of one hex
+//! frame per line into CSV or Parquet tables, see `vin::export`.
+//!
+//! ```text
+//! vin-export --out tables logs/2018-10-*.log
+//! vin-export --out tables --format parquet --separator , --frame-field 3 logs/*.csv
+//! vin-export --out tables --failed failed.tsv logs/2018-10-*.log
+//! vin-export --schema
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, ValueEnum};
+use vin::batch::{self, LineFormat, Report};
+use vin::export::{self, CsvWriter, Exporter, Table, Writer};
+use vin::packet::parser;
+
+#[derive(Parser)]
+#[command(version, about = "Export GB/T 32960 real time reports as tables")]
+struct Args {
+    /// Files with one frame per line.
+    #[arg(required_unless_present = "schema")]
+    files: Vec<PathBuf>,
+
+    /// Directory to write a file per table to.
+    #[arg(short, long, required_unless_present = "schema")]
+    out: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+
+    /// Separator of the fields of lines; whitespace by default.
+    #[arg(long)]
+    separator: Option<char>,
+
+    /// Field of lines with the frame, from 1; the last by default.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    frame_field: Option<u16>,
+
+    /// Write the lines that fail to this file, as tab separated file, line
+    /// number, error kind, error and line.
+    #[arg(long)]
+    failed: Option<PathBuf>,
+
+    /// Print the tables and their columns instead.
+    #[arg(long)]
+    schema: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    /// Snappy compressed Parquet, if built with the `parquet` feature.
+    Parquet,
+}
+
+fn print_schema() {
+    for table in Table::ALL {
+        println!("{}", table.name());
+        for column in table.columns() {
+            let ty = format!("{:?}", column.ty).to_lowercase();
+            println!("  {:<36} {:<6} {}", column.name, ty, column.doc);
+        }
+    }
+}
+
+/// Exports the reports in the files of `args` to `writer`.
+fn export<W: Writer>(args: &Args, writer: W) -> ExitCode {
+    let options = batch::Options {
+        format: LineFormat {
+            separator: args.separator,
+            frame: args.frame_field.map(|n| n as usize - 1),
+            vin: None,
+        },
+        parser: parser::Options::default(),
+    };
+    let mut exporter = Exporter::new(writer);
+    let mut report = Report::default();
+    let mut reports = 0;
+    for path in &args.files {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("vin-export: {}: {}", path.display(), err);
+                return ExitCode::from(2);
+            }
+        };
+        let text = String::from_utf8_lossy(&data);
+        let exported = batch::decode_text_with(path, &text, &options, &mut report, |packet| {
+            if exporter.push_packet(&packet)? {
+                reports += 1;
+            }
+            Ok::<_, export::Error>(())
+        });
+        if let Err(err) = exported {
+            eprintln!("vin-export: {}", err);
+            return ExitCode::from(2);
+        }
+    }
+    if let Err(err) = exporter.finish() {
+        eprintln!("vin-export: {}", err);
+        return ExitCode::from(2);
+    }
+    if let Some(path) = &args.failed {
+        if let Err(err) = report.write_failures(path) {
+            eprintln!("vin-export: {}: {}", path.display(), err);
+            return ExitCode::from(2);
+        }
+    }
+    eprintln!(
+        "vin-export: {} reports exported, {} lines failed to decode",
+        reports,
+        report.stats.failed()
+    );
+    for (kind, n) in &report.stats.errors {
+        eprintln!("  {:<20} {}", kind, n);
+    }
+    if report.failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if args.schema {
+        print_schema();
+        return ExitCode::SUCCESS;
+    }
+    let out = args.out.as_ref().expect("required without --schema");
+    let result = match args.format {
+        Format::Csv => CsvWriter::create(out).map(|writer| export(&args, writer)),
+        #[cfg(feature = "parquet")]
+        Format::Parquet => {
+            vin::export::parquet::ParquetWriter::create(out).map(|writer| export(&args, writer))
+        }
+        #[cfg(not(feature = "parquet"))]
+        Format::Parquet => {
+            eprintln!("vin-export: built without the parquet feature");
+            return ExitCode::from(2);
+        }
+    };
+    result.unwrap_or_else(|err| {
+        eprintln!("vin-export: {}: {}", out.display(), err);
+        ExitCode::from(2)
+    })
+}
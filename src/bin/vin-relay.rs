@@ -0,0 +1,326 @@
+//! Forwards the traffic of terminals to an upstream platform, such as the
+//! public platform, keeping a platform login and buffering while the
+//! upstream is unreachable. With `--stand-in` it is the upstream instead,
+//! accepting platform logins and printing the forwarded packets as JSON
+//! lines.
+//!
+//! ```text
+//! vin-relay --stand-in --listen 127.0.0.1:9000 --username relay --password secret
+//! vin-relay --listen 0.0.0.0:32960 --upstream 127.0.0.1:9000 \
+//!     --platform-id PLATFORM000000001 --username relay --password secret
+//! ```
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::Parser;
+use vin::packet::error::Error;
+use vin::packet::platform::{self, PlatformSession};
+use vin::packet::reissue::{FileStore, MemoryStore, ReissueStore};
+use vin::packet::relay::{self, Action, Relay, Upstream, UpstreamConfig};
+use vin::packet::stream::{Chunk, Framer};
+use vin::packet::{parser, Packet};
+use vin::sink::{JsonLines, Record, Sink};
+
+#[path = "common/serve.rs"]
+mod serve;
+
+#[derive(Parser)]
+#[command(version, about = "Forward GB/T 32960 traffic to an upstream platform")]
+struct Args {
+    /// Address terminals, or with `--stand-in` relays, connect to.
+    #[arg(short, long, default_value = "127.0.0.1:32960")]
+    listen: String,
+
+    /// Upstream platform to forward to.
+    #[arg(
+        short,
+        long,
+        value_name = "HOST:PORT",
+        required_unless_present = "stand_in"
+    )]
+    upstream: Option<String>,
+
+    /// Unique code of this platform, sent in place of a VIN.
+    #[arg(long, default_value = "")]
+    platform_id: String,
+
+    #[arg(long, default_value = "")]
+    username: String,
+
+    #[arg(long, default_value = "")]
+    password: String,
+
+    /// Forward frames as received instead of encoding them afresh.
+    #[arg(long)]
+    passthrough: bool,
+
+    /// Send frames again that the upstream did not acknowledge.
+    #[arg(long)]
+    await_acks: bool,
+
+    /// Most frames kept while the upstream is unreachable.
+    #[arg(long, default_value_t = 100_000)]
+    buffer: usize,
+
+    /// File keeping those frames across restarts, memory if missing.
+    #[arg(long)]
+    store: Option<PathBuf>,
+
+    /// Most kept frames sent a second once the upstream is back.
+    #[arg(long, default_value_t = 100)]
+    reissue_rate: u32,
+
+    /// Seconds between attempts to reach the upstream.
+    #[arg(long, default_value_t = 5)]
+    reconnect: u64,
+
+    /// Be the upstream: accept platform logins and print what relays send.
+    #[arg(long, conflicts_with = "upstream")]
+    stand_in: bool,
+}
+
+enum Event {
+    /// Packet of a vehicle and the frame it came in.
+    Vehicle(Vec<u8>, Packet),
+    /// Packet of the upstream connection with the given number.
+    Upstream(u64, Packet),
+    Closed(u64),
+}
+
+/// Reads the upstream connection `id` until it closes.
+fn read_upstream(mut stream: TcpStream, id: u64, events: Sender<Event>) {
+    let mut framer = Framer::new();
+    let mut buf = [0; 4096];
+    while let Ok(n @ 1..) = stream.read(&mut buf) {
+        framer.extend(&buf[..n]);
+        while let Some(chunk) = framer.next() {
+            if let Chunk::Frame(frame) = chunk {
+                match parser::parse_bytes(&frame) {
+                    Ok(packet) => {
+                        let _ = events.send(Event::Upstream(id, packet));
+                    }
+                    Err(Error::Checksum { .. }) => framer.resync(frame),
+                    Err(err) => eprintln!("upstream: dropped frame: {}", err),
+                }
+            }
+        }
+    }
+    let _ = events.send(Event::Closed(id));
+}
+
+struct Forwarder {
+    addr: String,
+    passthrough: bool,
+    reconnect: Duration,
+    relay: Relay,
+    upstream: Option<(u64, TcpStream)>,
+    next_connect: Duration,
+    connections: u64,
+    events: Sender<Event>,
+}
+
+impl Forwarder {
+    fn connect(&mut self) -> Vec<Action> {
+        let stream = TcpStream::connect(&self.addr).and_then(|stream| {
+            let reader = stream.try_clone()?;
+            Ok((stream, reader))
+        });
+        match stream {
+            Ok((stream, reader)) => {
+                eprintln!("upstream: connected to {}", self.addr);
+                self.connections += 1;
+                let (id, events) = (self.connections, self.events.clone());
+                thread::spawn(move || read_upstream(reader, id, events));
+                self.upstream = Some((id, stream));
+                self.relay.connected(serve::now())
+            }
+            Err(err) => {
+                eprintln!("upstream: {}: {}", self.addr, err);
+                self.next_connect = serve::now() + self.reconnect;
+                Vec::new()
+            }
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some((_, stream)) = self.upstream.take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        let now = serve::now();
+        for action in self.relay.disconnected(now) {
+            if let Action::Store(err) = action {
+                eprintln!("upstream: {}", err);
+            }
+        }
+        self.next_connect = now + self.reconnect;
+    }
+
+    fn event(&mut self, event: Event) -> Vec<Action> {
+        let now = serve::now();
+        let current = self.upstream.as_ref().map(|(id, _)| *id);
+        match event {
+            Event::Vehicle(frame, _) if self.passthrough => self.relay.forward_frame(frame, now),
+            Event::Vehicle(_, packet) => self.relay.forward(&packet, now).unwrap_or_else(|err| {
+                eprintln!("{}: cannot forward: {}", packet.vin, err);
+                Vec::new()
+            }),
+            Event::Upstream(id, packet) if Some(id) == current => {
+                let logged_in = self.relay.is_logged_in();
+                let actions = self.relay.handle(packet, now);
+                if !logged_in && self.relay.is_logged_in() {
+                    eprintln!("upstream: logged in");
+                }
+                actions
+            }
+            Event::Closed(id) if Some(id) == current => {
+                eprintln!("upstream: closed");
+                self.disconnect();
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn carry_out(&mut self, actions: Vec<Action>) {
+        for action in actions {
+            match action {
+                Action::Send(data) => {
+                    let sent = match &mut self.upstream {
+                        Some((_, stream)) => stream.write_all(&data),
+                        None => Err(io::ErrorKind::NotConnected.into()),
+                    };
+                    if let Err(err) = sent {
+                        eprintln!("upstream: {}", err);
+                        self.disconnect();
+                        return;
+                    }
+                }
+                Action::Disconnect => {
+                    self.disconnect();
+                    return;
+                }
+                Action::Store(err) => eprintln!("upstream: {}", err),
+            }
+        }
+    }
+
+    fn run(mut self, events: mpsc::Receiver<Event>) {
+        loop {
+            if self.upstream.is_none() && serve::now() >= self.next_connect {
+                let actions = self.connect();
+                self.carry_out(actions);
+            }
+            let mut deadline = self.relay.deadline();
+            if self.upstream.is_none() {
+                deadline = Some(deadline.map_or(self.next_connect, |d| d.min(self.next_connect)));
+            }
+            let wait = deadline
+                .map(|deadline| deadline.saturating_sub(serve::now()))
+                .unwrap_or(Duration::from_secs(1))
+                .max(Duration::from_millis(1));
+            let actions = match events.recv_timeout(wait) {
+                Ok(event) => self.event(event),
+                Err(RecvTimeoutError::Timeout) => self.relay.poll(serve::now()),
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            self.carry_out(actions);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let listener = match TcpListener::bind(&args.listen) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("vin-relay: {}: {}", args.listen, err);
+            return ExitCode::from(2);
+        }
+    };
+    match listener.local_addr() {
+        Ok(addr) => eprintln!("vin-relay: listening on {}", addr),
+        Err(err) => eprintln!("vin-relay: {}", err),
+    }
+    let options = parser::Options::default();
+
+    if args.stand_in {
+        let config = UpstreamConfig {
+            username: args.username,
+            password: args.password,
+            ..UpstreamConfig::default()
+        };
+        let sink = Mutex::new(JsonLines::new(io::stdout()));
+        let emit: serve::Emit = Arc::new(move |peer, _, packet| {
+            let record = Record {
+                received: serve::now(),
+                peer,
+                packet: &packet,
+            };
+            let mut sink = sink.lock().unwrap_or_else(|err| err.into_inner());
+            if let Err(err) = sink.write(&record) {
+                eprintln!("{}: cannot write packet: {}", peer, err);
+            }
+        });
+        let session = move || Upstream::new(config.clone(), serve::now());
+        serve::serve(listener, session, options, emit);
+        return ExitCode::SUCCESS;
+    }
+
+    let store: Box<dyn ReissueStore + Send> = match &args.store {
+        Some(path) => match FileStore::open(path) {
+            Ok(store) => Box::new(store),
+            Err(err) => {
+                eprintln!("vin-relay: {}: {}", path.display(), err);
+                return ExitCode::from(2);
+            }
+        },
+        None => Box::new(MemoryStore::new()),
+    };
+    let relay = match Relay::new(relay::Config {
+        platform_id: args.platform_id,
+        username: args.username,
+        password: args.password,
+        await_acks: args.await_acks,
+        buffer_capacity: args.buffer,
+        reissue_rate: args.reissue_rate,
+        ..relay::Config::default()
+    }) {
+        Ok(relay) => relay.with_store(store),
+        Err(err) => {
+            eprintln!("vin-relay: invalid platform login: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let (events, received) = mpsc::channel();
+    let vehicles = Mutex::new(events.clone());
+    let emit: serve::Emit = Arc::new(move |_, frame, packet| {
+        let vehicles = vehicles.lock().unwrap_or_else(|err| err.into_inner());
+        let _ = vehicles.send(Event::Vehicle(frame.to_vec(), packet));
+    });
+    let config = platform::Config::default();
+    thread::spawn(move || {
+        let session = move || PlatformSession::new(config, serve::now());
+        serve::serve(listener, session, options, emit)
+    });
+
+    let forwarder = Forwarder {
+        addr: args.upstream.unwrap_or_default(),
+        passthrough: args.passthrough,
+        reconnect: Duration::from_secs(args.reconnect),
+        relay,
+        upstream: None,
+        next_connect: serve::now(),
+        connections: 0,
+        events,
+    };
+    forwarder.run(received);
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,114 @@
+//! Minimal platform for bench testing terminals: accepts connections, runs
+//! a platform session on each, answers logins, heart beats and time
+//! requests, and writes the received packets as JSON lines.
+//!
+//! ```text
+//! vin-server --listen 0.0.0.0:32960
+//! vin-server --out packets.jsonl --max-bytes 104857600 --keep 20
+//! ```
+
+use std::io;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clap::Parser;
+use vin::packet::platform::{self, PlatformSession};
+use vin::packet::{parser, Strictness};
+use vin::sink::{JsonLines, Record, RotatingFiles, Sink};
+
+#[path = "common/serve.rs"]
+mod serve;
+
+#[derive(Parser)]
+#[command(version, about = "Receive GB/T 32960 traffic from terminals")]
+struct Args {
+    /// Address to listen on.
+    #[arg(short, long, default_value = "127.0.0.1:32960")]
+    listen: String,
+
+    /// File to write packets to, rotated by size; stdout if missing.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+
+    /// Size at which `--out` is rotated.
+    #[arg(long, default_value_t = 64 << 20)]
+    max_bytes: u64,
+
+    /// Rotated files to keep.
+    #[arg(long, default_value_t = 10)]
+    keep: usize,
+
+    /// Seconds of silence after which a connection is closed.
+    #[arg(long, default_value_t = 180)]
+    idle_timeout: u64,
+
+    /// Answer real time and reissue reports too.
+    #[arg(long)]
+    respond_reports: bool,
+
+    /// Drop frames with VINs and ICCIDs that do not validate.
+    #[arg(long)]
+    strict: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let sink: Box<dyn Sink> = match &args.out {
+        Some(path) => match RotatingFiles::open(path, args.max_bytes, args.keep) {
+            Ok(files) => Box::new(files),
+            Err(err) => {
+                eprintln!("vin-server: {}: {}", path.display(), err);
+                return ExitCode::from(2);
+            }
+        },
+        None => Box::new(JsonLines::new(io::stdout())),
+    };
+    let sink = Mutex::new(sink);
+
+    let listener = match TcpListener::bind(&args.listen) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("vin-server: {}: {}", args.listen, err);
+            return ExitCode::from(2);
+        }
+    };
+    match listener.local_addr() {
+        Ok(addr) => eprintln!("vin-server: listening on {}", addr),
+        Err(err) => eprintln!("vin-server: {}", err),
+    }
+
+    let strictness = if args.strict {
+        Strictness::Reject
+    } else {
+        Strictness::Warn
+    };
+    let options = parser::Options {
+        vin: strictness,
+        iccid: strictness,
+    };
+    let config = platform::Config {
+        idle_timeout: Duration::from_secs(args.idle_timeout),
+        respond_reports: args.respond_reports,
+    };
+    let emit: serve::Emit = Arc::new(move |peer, _, packet| {
+        let record = Record {
+            received: serve::now(),
+            peer,
+            packet: &packet,
+        };
+        let mut sink = sink.lock().unwrap_or_else(|err| err.into_inner());
+        if let Err(err) = sink.write(&record) {
+            eprintln!("{}: cannot write packet: {}", peer, err);
+        }
+    });
+    serve::serve(
+        listener,
+        move || PlatformSession::new(config, serve::now()),
+        options,
+        emit,
+    );
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,239 @@
+//! Simulates terminals sending GB/T 32960 traffic, written to a file or sent
+//! to a platform over TCP.
+//!
+//! ```text
+//! vin-sim --count 10 --out frames.bin
+//! vin-sim --vins vins.txt --profile highway --hex --out frames.txt
+//! vin-sim --count 100 --connect 127.0.0.1:32960 --realtime
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::{Parser, ValueEnum};
+use vin::packet::Vin;
+use vin::sim::{self, Profile, Simulator};
+
+/// Wait for the platform to close a connection after the logout.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Parser)]
+#[command(version, about = "Simulate GB/T 32960 terminals")]
+struct Args {
+    /// VIN of a simulated vehicle, may be repeated.
+    #[arg(long = "vin")]
+    vins: Vec<String>,
+
+    /// File with one VIN per line.
+    #[arg(long = "vins")]
+    vin_file: Option<PathBuf>,
+
+    /// Generated VINs to simulate besides the given ones.
+    #[arg(short, long, default_value_t = 0)]
+    count: usize,
+
+    /// `city`, `highway` or a JSON file with profile fields.
+    #[arg(short, long, default_value = "city")]
+    profile: String,
+
+    /// Seconds from login to logout, overriding the profile.
+    #[arg(short, long)]
+    duration: Option<u32>,
+
+    /// Seed for generated VINs and driving.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// File to write the frames to, stdout if neither this nor `--connect`
+    /// is given.
+    #[arg(short, long, conflicts_with = "connect")]
+    out: Option<PathBuf>,
+
+    /// Write one hex encoded frame per line instead of raw bytes.
+    #[arg(long)]
+    hex: bool,
+
+    /// Platform to connect to, one connection per vehicle.
+    #[arg(long, value_name = "HOST:PORT")]
+    connect: Option<String>,
+
+    /// Send frames when they are due instead of as fast as possible.
+    #[arg(long)]
+    realtime: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Preset {
+    City,
+    Highway,
+}
+
+fn profile(name: &str) -> Result<Profile, String> {
+    if let Ok(preset) = Preset::from_str(name, true) {
+        return Ok(match preset {
+            Preset::City => Profile::city(),
+            Preset::Highway => Profile::highway(),
+        });
+    }
+    let text = std::fs::read_to_string(name).map_err(|err| format!("{}: {}", name, err))?;
+    serde_json::from_str(&text).map_err(|err| format!("{}: {}", name, err))
+}
+
+fn vins(args: &Args) -> Result<Vec<Vin>, String> {
+    let mut texts = args.vins.clone();
+    if let Some(path) = &args.vin_file {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        texts.extend(text.split_whitespace().map(String::from));
+    }
+    let mut vins = Vec::new();
+    for text in texts {
+        vins.push(Vin::new(&text).map_err(|err| format!("VIN {}: {}", text, err))?);
+    }
+    vins.extend(sim::vins(args.count, args.seed));
+    Ok(vins)
+}
+
+/// Connection of one vehicle and the thread draining its answers.
+struct Connection {
+    stream: TcpStream,
+    reader: JoinHandle<()>,
+}
+
+/// Where frames go: one writer, or one connection per VIN.
+enum Output {
+    Writer(Box<dyn Write>, bool),
+    Tcp {
+        addr: String,
+        open: HashMap<String, Connection>,
+        closed: Vec<JoinHandle<()>>,
+    },
+}
+
+impl Output {
+    fn send(&mut self, vin: &str, frame: &[u8]) -> io::Result<()> {
+        match self {
+            Output::Writer(out, true) => writeln!(out, "{}", hex::encode(frame)),
+            Output::Writer(out, false) => out.write_all(frame),
+            Output::Tcp { addr, open, .. } => {
+                if !open.contains_key(vin) {
+                    let stream = TcpStream::connect(addr.as_str())?;
+                    stream.set_nodelay(true)?;
+                    // drain answers so the platform never blocks on us
+                    let mut reader = stream.try_clone()?;
+                    let reader = thread::spawn(move || {
+                        let mut buf = [0; 1024];
+                        while matches!(reader.read(&mut buf), Ok(n) if n > 0) {}
+                    });
+                    open.insert(vin.to_string(), Connection { stream, reader });
+                }
+                open.get_mut(vin).expect("inserted").stream.write_all(frame)
+            }
+        }
+    }
+
+    /// Stops sending for `vin`, still reading the answer to its logout.
+    fn close(&mut self, vin: &str) {
+        if let Output::Tcp { open, closed, .. } = self {
+            if let Some(connection) = open.remove(vin) {
+                let _ = connection.stream.shutdown(Shutdown::Write);
+                let _ = connection.stream.set_read_timeout(Some(CLOSE_TIMEOUT));
+                closed.push(connection.reader);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Writer(out, _) => out.flush(),
+            Output::Tcp { .. } => Ok(()),
+        }
+    }
+
+    /// Flushes, and waits for platforms to close logged out connections.
+    fn finish(mut self) -> io::Result<()> {
+        self.flush()?;
+        if let Output::Tcp { open, .. } = &self {
+            let vins: Vec<String> = open.keys().cloned().collect();
+            for vin in vins {
+                self.close(&vin);
+            }
+        }
+        if let Output::Tcp { closed, .. } = self {
+            for reader in closed {
+                let _ = reader.join();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut profile = profile(&args.profile)?;
+    if let Some(duration) = args.duration {
+        profile.duration = duration;
+    }
+    let vins = vins(args)?;
+    if vins.is_empty() {
+        return Err("no vehicles, give --vin, --vins or --count".into());
+    }
+
+    let mut output = if let Some(addr) = &args.connect {
+        Output::Tcp {
+            addr: addr.clone(),
+            open: HashMap::new(),
+            closed: Vec::new(),
+        }
+    } else if let Some(path) = &args.out {
+        let file = File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Output::Writer(Box::new(BufWriter::new(file)), args.hex)
+    } else {
+        Output::Writer(Box::new(BufWriter::new(io::stdout())), args.hex)
+    };
+
+    let start = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    for frame in Simulator::new(vins, profile, start, args.seed) {
+        if args.realtime {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            if frame.at > now {
+                output.flush().map_err(|err| err.to_string())?;
+                thread::sleep(Duration::from_secs((frame.at - now) as u64));
+            }
+        }
+        let vin = frame.packet.vin.as_str();
+        let data = frame
+            .packet
+            .encode()
+            .map_err(|err| format!("{}: {}", vin, err))?;
+        output
+            .send(vin, &data)
+            .map_err(|err| format!("{}: {}", vin, err))?;
+        if frame.packet.command == vin::packet::Command::VehicleLogout {
+            output.close(vin);
+        }
+    }
+    output.finish().map_err(|err| err.to_string())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("vin-sim: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,696 @@
+//! Real time reports flattened into tables for analysis.
+//!
+//! Every report becomes rows of fixed tables, each starting with the `vin`
+//! and the collection `time` of the report:
+//!
+//! * `vehicles`: one row per report with the vehicle data, location,
+//!   extremes, engine, fuel cell and alarm level, null where the report
+//!   lacks the item;
+//! * `motors`: one row per drive motor;
+//! * `subsystems`: one row per battery subsystem of the voltages item;
+//! * `cell_voltages`: one row per cell voltage;
+//! * `probe_temperatures`: one row per battery temperature probe;
+//! * `faults`: one row per fault code of the alarm item.
+//!
+//! The columns are listed by [`Table::columns`]. Values are in engineering
+//! units, with the scale and offset of the standard applied, and null for
+//! the "abnormal" and "invalid" markers; codes such as `status` are kept as
+//! transmitted. Times are in China time (UTC+08:00).
+//!
+//! An [`Exporter`] buffers rows and hands them in batches to a [`Writer`]:
+//! [`CsvWriter`] writes a CSV file per table and, with the `parquet`
+//! feature, [`ParquetWriter`](parquet::ParquetWriter) a Parquet file per
+//! table.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::packet::body::Body;
+use crate::packet::realtime::{Item, RealTimeReport};
+use crate::packet::{Packet, Time};
+
+#[cfg(feature = "parquet")]
+pub mod parquet;
+
+/// Rows buffered by an [`Exporter`] before they are written.
+pub const BATCH_ROWS: usize = 64 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    #[cfg(feature = "parquet")]
+    #[error(transparent)]
+    Parquet(#[from] ::parquet::errors::ParquetError),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Text,
+    /// Collection time, to the second.
+    Time,
+    Int,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    pub name: &'static str,
+    pub ty: Type,
+    /// Meaning and unit.
+    pub doc: &'static str,
+}
+
+impl Column {
+    /// Whether the column has nulls, which all but `vin` and `time` may.
+    pub fn nullable(&self) -> bool {
+        !matches!(self.ty, Type::Text | Type::Time)
+    }
+}
+
+const fn column(name: &'static str, ty: Type, doc: &'static str) -> Column {
+    Column { name, ty, doc }
+}
+
+const VIN: Column = column("vin", Type::Text, "VIN of the header");
+const TIME: Column = column("time", Type::Time, "collection time of the report");
+const SUBSYS: Column = column("subsys", Type::Int, "number of the battery subsystem");
+
+const VEHICLES: [Column; 45] = [
+    VIN,
+    TIME,
+    column("status", Type::Int, "1 started, 2 stopped, 3 other"),
+    column(
+        "charging",
+        Type::Int,
+        "1 parked charging, 2 driving charging, 3 not charging, 4 charged",
+    ),
+    column("mode", Type::Int, "1 electric, 2 hybrid, 3 fuel"),
+    column("speed", Type::Float, "km/h"),
+    column("mileage", Type::Float, "km"),
+    column("voltage", Type::Float, "total voltage, V"),
+    column("current", Type::Float, "total current, A"),
+    column("soc", Type::Int, "state of charge, %"),
+    column("dc_status", Type::Int, "DC-DC, 1 working, 2 off"),
+    column("gear", Type::Int, "gear byte as transmitted"),
+    column("insulation", Type::Int, "insulation resistance, kΩ"),
+    column("accelerator", Type::Int, "accelerator pedal, %"),
+    column("brake", Type::Int, "brake pedal, %"),
+    column(
+        "location_status",
+        Type::Int,
+        "bit 0 invalid fix, bit 1 south, bit 2 west",
+    ),
+    column("longitude", Type::Float, "°, negative for west"),
+    column("latitude", Type::Float, "°, negative for south"),
+    column(
+        "max_voltage_subsys",
+        Type::Int,
+        "subsystem of the highest cell voltage",
+    ),
+    column(
+        "max_voltage_cell",
+        Type::Int,
+        "cell of the highest cell voltage",
+    ),
+    column("max_cell_voltage", Type::Float, "V"),
+    column(
+        "min_voltage_subsys",
+        Type::Int,
+        "subsystem of the lowest cell voltage",
+    ),
+    column(
+        "min_voltage_cell",
+        Type::Int,
+        "cell of the lowest cell voltage",
+    ),
+    column("min_cell_voltage", Type::Float, "V"),
+    column(
+        "max_temperature_subsys",
+        Type::Int,
+        "subsystem of the highest temperature",
+    ),
+    column(
+        "max_temperature_probe",
+        Type::Int,
+        "probe of the highest temperature",
+    ),
+    column("max_temperature", Type::Float, "°C"),
+    column(
+        "min_temperature_subsys",
+        Type::Int,
+        "subsystem of the lowest temperature",
+    ),
+    column(
+        "min_temperature_probe",
+        Type::Int,
+        "probe of the lowest temperature",
+    ),
+    column("min_temperature", Type::Float, "°C"),
+    column("engine_status", Type::Int, "1 running, 2 off"),
+    column("crankshaft_speed", Type::Int, "r/min"),
+    column("fuel_consumption", Type::Float, "L/100km"),
+    column("fuel_cell_voltage", Type::Float, "V"),
+    column("fuel_cell_current", Type::Float, "A"),
+    column("hydrogen_consumption", Type::Float, "kg/100km"),
+    column("max_hydrogen_temperature", Type::Float, "°C"),
+    column(
+        "max_hydrogen_temperature_probe",
+        Type::Int,
+        "probe of the highest hydrogen temperature",
+    ),
+    column("max_hydrogen_concentration", Type::Int, "mg/kg"),
+    column(
+        "max_hydrogen_concentration_sensor",
+        Type::Int,
+        "sensor of the highest concentration",
+    ),
+    column("max_hydrogen_pressure", Type::Float, "MPa"),
+    column(
+        "max_hydrogen_pressure_sensor",
+        Type::Int,
+        "sensor of the highest pressure",
+    ),
+    column(
+        "fuel_cell_dc_status",
+        Type::Int,
+        "high voltage DC-DC, 1 working, 2 off",
+    ),
+    column("alarm_level", Type::Int, "highest alarm level, 0 to 3"),
+    column("alarm_flags", Type::Int, "general alarm flags"),
+];
+
+const MOTORS: [Column; 10] = [
+    VIN,
+    TIME,
+    column("sn", Type::Int, "number of the motor"),
+    column(
+        "status",
+        Type::Int,
+        "1 consuming, 2 generating, 3 off, 4 ready",
+    ),
+    column("controller_temperature", Type::Float, "°C"),
+    column("speed", Type::Float, "r/min"),
+    column("torque", Type::Float, "N·m"),
+    column("temperature", Type::Float, "°C"),
+    column("controller_voltage", Type::Float, "V"),
+    column("controller_current", Type::Float, "A"),
+];
+
+const SUBSYSTEMS: [Column; 7] = [
+    VIN,
+    TIME,
+    SUBSYS,
+    column("voltage", Type::Float, "V"),
+    column("current", Type::Float, "A"),
+    column("cell_count", Type::Int, "cells of the subsystem"),
+    column("frame_start", Type::Int, "first cell in the report, from 1"),
+];
+
+const CELL_VOLTAGES: [Column; 5] = [
+    VIN,
+    TIME,
+    SUBSYS,
+    column("cell", Type::Int, "number of the cell, from 1"),
+    column("voltage", Type::Float, "V"),
+];
+
+const PROBE_TEMPERATURES: [Column; 5] = [
+    VIN,
+    TIME,
+    SUBSYS,
+    column("probe", Type::Int, "number of the probe, from 1"),
+    column("temperature", Type::Float, "°C"),
+];
+
+const FAULTS: [Column; 4] = [
+    VIN,
+    TIME,
+    column("kind", Type::Text, "battery, motor, engine or other"),
+    column("code", Type::Int, "fault code as transmitted"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Table {
+    Vehicles,
+    Motors,
+    Subsystems,
+    CellVoltages,
+    ProbeTemperatures,
+    Faults,
+}
+
+impl Table {
+    pub const ALL: [Table; 6] = [
+        Table::Vehicles,
+        Table::Motors,
+        Table::Subsystems,
+        Table::CellVoltages,
+        Table::ProbeTemperatures,
+        Table::Faults,
+    ];
+
+    /// Snake case name, which is also the stem of its file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Table::Vehicles => "vehicles",
+            Table::Motors => "motors",
+            Table::Subsystems => "subsystems",
+            Table::CellVoltages => "cell_voltages",
+            Table::ProbeTemperatures => "probe_temperatures",
+            Table::Faults => "faults",
+        }
+    }
+
+    pub fn columns(self) -> &'static [Column] {
+        match self {
+            Table::Vehicles => &VEHICLES,
+            Table::Motors => &MOTORS,
+            Table::Subsystems => &SUBSYSTEMS,
+            Table::CellVoltages => &CELL_VOLTAGES,
+            Table::ProbeTemperatures => &PROBE_TEMPERATURES,
+            Table::Faults => &FAULTS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Text(String),
+    Time(Time),
+    Int(i64),
+    Float(f64),
+}
+
+/// Values in the order of [`Table::columns`].
+pub type Row = Vec<Value>;
+
+fn int(raw: impl Into<i64>) -> Value {
+    Value::Int(raw.into())
+}
+
+/// `raw`, null for the two markers below `max`.
+fn count(raw: u32, max: u32) -> Value {
+    if raw >= max - 1 {
+        return Value::Null;
+    }
+    Value::Int(raw.into())
+}
+
+fn count8(raw: u8) -> Value {
+    count(raw.into(), u8::MAX.into())
+}
+
+fn count16(raw: u16) -> Value {
+    count(raw.into(), u16::MAX.into())
+}
+
+/// Engineering value of `raw`, null for the two markers below `max`.
+fn scaled(raw: u32, max: u32, div: f64, offset: f64) -> Value {
+    if raw >= max - 1 {
+        return Value::Null;
+    }
+    Value::Float(raw as f64 / div + offset)
+}
+
+fn scaled8(raw: u8, div: f64, offset: f64) -> Value {
+    scaled(raw.into(), u8::MAX.into(), div, offset)
+}
+
+fn scaled16(raw: u16, div: f64, offset: f64) -> Value {
+    scaled(raw.into(), u16::MAX.into(), div, offset)
+}
+
+/// Latitude or longitude, negative if the hemisphere `bit` of `status` is
+/// set.
+fn coordinate(raw: u32, status: u8, bit: u8) -> Value {
+    match scaled(raw, u32::MAX, 1e6, 0.0) {
+        Value::Float(value) if status & bit != 0 => Value::Float(-value),
+        value => value,
+    }
+}
+
+/// `values`, or `len` nulls for an item missing from the report.
+fn or_nulls(values: Option<Vec<Value>>, len: usize) -> Vec<Value> {
+    values.unwrap_or_else(|| vec![Value::Null; len])
+}
+
+/// Rows of reports, by table.
+#[derive(Debug, Default)]
+pub struct Rows {
+    tables: BTreeMap<Table, Vec<Row>>,
+    len: usize,
+}
+
+impl Rows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the rows of `report` of the vehicle `vin`.
+    pub fn push(&mut self, vin: &str, report: &RealTimeReport) {
+        let key = || vec![Value::Text(vin.into()), Value::Time(report.at)];
+        let (mut vehicle, mut location, mut extremes) = (None, None, None);
+        let (mut engine, mut fuel_cell, mut alarm) = (None, None, None);
+        for item in &report.items {
+            match item {
+                Item::Vehicle(v) => {
+                    vehicle = Some(vec![
+                        int(v.status),
+                        int(v.charging),
+                        int(v.mode),
+                        scaled16(v.speed, 10.0, 0.0),
+                        scaled(v.mileage, u32::MAX, 10.0, 0.0),
+                        scaled16(v.voltage, 10.0, 0.0),
+                        scaled16(v.current, 10.0, -1000.0),
+                        count8(v.soc),
+                        int(v.dc_status),
+                        int(v.gear),
+                        count16(v.insulation),
+                        count8(v.accelerator),
+                        count8(v.brake),
+                    ])
+                }
+                Item::Location(l) => {
+                    location = Some(vec![
+                        int(l.status),
+                        coordinate(l.longitude, l.status, 0b100),
+                        coordinate(l.latitude, l.status, 0b010),
+                    ])
+                }
+                Item::Extremes(e) => {
+                    extremes = Some(vec![
+                        count8(e.max_voltage_subsys),
+                        count8(e.max_voltage_cell),
+                        scaled16(e.max_cell_voltage, 1000.0, 0.0),
+                        count8(e.min_voltage_subsys),
+                        count8(e.min_voltage_cell),
+                        scaled16(e.min_cell_voltage, 1000.0, 0.0),
+                        count8(e.max_temperature_subsys),
+                        count8(e.max_temperature_probe),
+                        scaled8(e.max_temperature, 1.0, -40.0),
+                        count8(e.min_temperature_subsys),
+                        count8(e.min_temperature_probe),
+                        scaled8(e.min_temperature, 1.0, -40.0),
+                    ])
+                }
+                Item::Engine(e) => {
+                    engine = Some(vec![
+                        int(e.status),
+                        count16(e.crankshaft_speed),
+                        scaled16(e.fuel_consumption, 100.0, 0.0),
+                    ])
+                }
+                Item::FuelCell(f) => {
+                    fuel_cell = Some(vec![
+                        scaled16(f.voltage, 10.0, 0.0),
+                        scaled16(f.current, 10.0, 0.0),
+                        scaled16(f.consumption, 100.0, 0.0),
+                        scaled16(f.max_hydrogen_temperature, 10.0, -40.0),
+                        count8(f.max_hydrogen_temperature_probe),
+                        count16(f.max_hydrogen_concentration),
+                        count8(f.max_hydrogen_concentration_sensor),
+                        scaled16(f.max_hydrogen_pressure, 10.0, 0.0),
+                        count8(f.max_hydrogen_pressure_sensor),
+                        int(f.dc_status),
+                    ])
+                }
+                Item::Alarm(a) => {
+                    alarm = Some(vec![int(a.level), int(a.flags)]);
+                    let faults = [
+                        ("battery", &a.battery_faults),
+                        ("motor", &a.motor_faults),
+                        ("engine", &a.engine_faults),
+                        ("other", &a.other_faults),
+                    ];
+                    for (kind, codes) in faults {
+                        for &code in codes {
+                            let mut row = key();
+                            row.extend([Value::Text(kind.into()), int(code)]);
+                            self.add(Table::Faults, row);
+                        }
+                    }
+                }
+                Item::Motors(m) => {
+                    for m in &m.motors {
+                        let mut row = key();
+                        row.extend([
+                            int(m.sn),
+                            int(m.status),
+                            scaled8(m.controller_temperature, 1.0, -40.0),
+                            scaled16(m.speed, 1.0, -20000.0),
+                            scaled16(m.torque, 10.0, -2000.0),
+                            scaled8(m.temperature, 1.0, -40.0),
+                            scaled16(m.controller_voltage, 10.0, 0.0),
+                            scaled16(m.controller_current, 10.0, -1000.0),
+                        ]);
+                        self.add(Table::Motors, row);
+                    }
+                }
+                Item::Voltages(v) => {
+                    for s in &v.subsystems {
+                        let mut row = key();
+                        row.extend([
+                            int(s.sn),
+                            scaled16(s.voltage, 10.0, 0.0),
+                            scaled16(s.current, 10.0, -1000.0),
+                            int(s.cell_count),
+                            int(s.frame_start),
+                        ]);
+                        self.add(Table::Subsystems, row);
+                        for (i, &cell) in s.cells.iter().enumerate() {
+                            let mut row = key();
+                            let n = s.frame_start as i64 + i as i64;
+                            row.extend([int(s.sn), int(n), scaled16(cell, 1000.0, 0.0)]);
+                            self.add(Table::CellVoltages, row);
+                        }
+                    }
+                }
+                Item::Temperatures(t) => {
+                    for s in &t.subsystems {
+                        for (i, &probe) in s.probes.iter().enumerate() {
+                            let mut row = key();
+                            let n = i as i64 + 1;
+                            row.extend([int(s.sn), int(n), scaled8(probe, 1.0, -40.0)]);
+                            self.add(Table::ProbeTemperatures, row);
+                        }
+                    }
+                }
+                Item::Custom { .. } => {}
+            }
+        }
+
+        let mut row = key();
+        row.extend(or_nulls(vehicle, 13));
+        row.extend(or_nulls(location, 3));
+        row.extend(or_nulls(extremes, 12));
+        row.extend(or_nulls(engine, 3));
+        row.extend(or_nulls(fuel_cell, 10));
+        row.extend(or_nulls(alarm, 2));
+        self.add(Table::Vehicles, row);
+    }
+
+    /// Adds the rows of `packet` if it is a real time or reissue report,
+    /// returning whether it is.
+    pub fn push_packet(&mut self, packet: &Packet) -> bool {
+        match &packet.body {
+            Body::RealTimeReport(report) | Body::ReissueReport(report) => {
+                self.push(packet.vin.as_str(), report);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn add(&mut self, table: Table, row: Row) {
+        debug_assert_eq!(row.len(), table.columns().len(), "{:?}", table);
+        self.tables.entry(table).or_default().push(row);
+        self.len += 1;
+    }
+
+    pub fn get(&self, table: Table) -> &[Row] {
+        self.tables.get(&table).map_or(&[], Vec::as_slice)
+    }
+
+    /// Rows of all tables.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes and returns the rows of `table`.
+    pub fn take(&mut self, table: Table) -> Vec<Row> {
+        let rows = self.tables.remove(&table).unwrap_or_default();
+        self.len -= rows.len();
+        rows
+    }
+}
+
+/// Destination of the rows of all tables.
+pub trait Writer {
+    /// Writes the next `rows` of `table`.
+    fn write(&mut self, table: Table, rows: &[Row]) -> Result<()>;
+
+    /// Completes the tables; nothing may be written after.
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Buffers rows of reports and writes them in batches.
+#[derive(Debug)]
+pub struct Exporter<W: Writer> {
+    writer: W,
+    rows: Rows,
+    batch_rows: usize,
+}
+
+impl<W: Writer> Exporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_batch_rows(writer, BATCH_ROWS)
+    }
+
+    /// Writes once `batch_rows` rows are buffered.
+    pub fn with_batch_rows(writer: W, batch_rows: usize) -> Self {
+        Self {
+            writer,
+            rows: Rows::new(),
+            batch_rows,
+        }
+    }
+
+    pub fn push(&mut self, vin: &str, report: &RealTimeReport) -> Result<()> {
+        self.rows.push(vin, report);
+        self.flush_if_full()
+    }
+
+    /// Exports `packet` if it is a real time or reissue report, returning
+    /// whether it is.
+    pub fn push_packet(&mut self, packet: &Packet) -> Result<bool> {
+        let pushed = self.rows.push_packet(packet);
+        self.flush_if_full()?;
+        Ok(pushed)
+    }
+
+    fn flush_if_full(&mut self) -> Result<()> {
+        if self.rows.len() >= self.batch_rows {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows.
+    pub fn flush(&mut self) -> Result<()> {
+        for table in Table::ALL {
+            let rows = self.rows.take(table);
+            if !rows.is_empty() {
+                self.writer.write(table, &rows)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows and completes the tables.
+    pub fn finish(mut self) -> Result<W> {
+        self.flush()?;
+        self.writer.finish()?;
+        Ok(self.writer)
+    }
+}
+
+/// Formats `at` as ISO-8601 in China time.
+fn iso(at: &Time) -> String {
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}+08:00",
+        at.full_year(),
+        at.month,
+        at.day,
+        at.hour,
+        at.minute,
+        at.second
+    )
+}
+
+/// `text` as a CSV field, quoted if needed.
+fn field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.into()
+    }
+}
+
+/// A CSV file per table, with a header line of the column names. Nulls are
+/// empty and times are ISO-8601 with their offset.
+#[derive(Debug)]
+pub struct CsvWriter<W: Write> {
+    outputs: BTreeMap<Table, W>,
+}
+
+impl CsvWriter<BufWriter<File>> {
+    /// Creates `<table>.csv` in `dir` for every table.
+    pub fn create<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        Self::new(|table| {
+            let path = dir.join(format!("{}.csv", table.name()));
+            Ok(BufWriter::new(File::create(path)?))
+        })
+    }
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Writes to the output `open` gives for every table.
+    pub fn new(mut open: impl FnMut(Table) -> io::Result<W>) -> Result<Self> {
+        let mut outputs = BTreeMap::new();
+        for table in Table::ALL {
+            let mut output = open(table)?;
+            let names: Vec<&str> = table.columns().iter().map(|c| c.name).collect();
+            writeln!(output, "{}", names.join(","))?;
+            outputs.insert(table, output);
+        }
+        Ok(Self { outputs })
+    }
+
+    pub fn into_inner(self) -> BTreeMap<Table, W> {
+        self.outputs
+    }
+}
+
+impl<W: Write> Writer for CsvWriter<W> {
+    fn write(&mut self, table: Table, rows: &[Row]) -> Result<()> {
+        let output = self.outputs.get_mut(&table).expect("every table is open");
+        for row in rows {
+            let fields: Vec<String> = row
+                .iter()
+                .map(|value| match value {
+                    Value::Null => String::new(),
+                    Value::Text(text) => field(text),
+                    Value::Time(at) => iso(at),
+                    Value::Int(n) => n.to_string(),
+                    Value::Float(x) => x.to_string(),
+                })
+                .collect();
+            writeln!(output, "{}", fields.join(","))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for output in self.outputs.values_mut() {
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+//! Tables as Apache Arrow record batches and Parquet files.
+//!
+//! Text columns are `Utf8`, times `Timestamp(Second, "+08:00")`, integers
+//! `Int64` and engineering values `Float64`.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::TimestampSecondArray;
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use super::{Result, Row, Table, Type, Value, Writer};
+
+/// Time zone of collection times.
+pub const TIME_ZONE: &str = "+08:00";
+
+pub fn schema(table: Table) -> SchemaRef {
+    let fields: Vec<Field> = table
+        .columns()
+        .iter()
+        .map(|column| {
+            let ty = match column.ty {
+                Type::Text => DataType::Utf8,
+                Type::Time => DataType::Timestamp(TimeUnit::Second, Some(TIME_ZONE.into())),
+                Type::Int => DataType::Int64,
+                Type::Float => DataType::Float64,
+            };
+            Field::new(column.name, ty, column.nullable())
+        })
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// `rows` of `table` as a record batch.
+pub fn record_batch(table: Table, rows: &[Row]) -> Result<RecordBatch> {
+    let arrays: Vec<ArrayRef> = table
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| -> ArrayRef {
+            let values = rows.iter().map(|row| &row[i]);
+            match column.ty {
+                Type::Text => Arc::new(
+                    values
+                        .map(|value| match value {
+                            Value::Text(text) => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<StringArray>(),
+                ),
+                Type::Time => Arc::new(
+                    values
+                        .map(|value| match value {
+                            Value::Time(at) => Some(at.to_unix()),
+                            _ => None,
+                        })
+                        .collect::<TimestampSecondArray>()
+                        .with_timezone(TIME_ZONE),
+                ),
+                Type::Int => Arc::new(
+                    values
+                        .map(|value| match value {
+                            Value::Int(n) => Some(*n),
+                            _ => None,
+                        })
+                        .collect::<Int64Array>(),
+                ),
+                Type::Float => Arc::new(
+                    values
+                        .map(|value| match value {
+                            Value::Float(x) => Some(*x),
+                            _ => None,
+                        })
+                        .collect::<Float64Array>(),
+                ),
+            }
+        })
+        .collect();
+    Ok(RecordBatch::try_new(schema(table), arrays)?)
+}
+
+/// A Snappy compressed Parquet file per table. The files are only
+/// readable once [`Writer::finish`] wrote their footers.
+pub struct ParquetWriter<W: Write + Send> {
+    writers: BTreeMap<Table, ArrowWriter<W>>,
+}
+
+impl ParquetWriter<File> {
+    /// Creates `<table>.parquet` in `dir` for every table.
+    pub fn create<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        Self::new(|table| File::create(dir.join(format!("{}.parquet", table.name()))))
+    }
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Writes to the output `open` gives for every table.
+    pub fn new(mut open: impl FnMut(Table) -> std::io::Result<W>) -> Result<Self> {
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writers = BTreeMap::new();
+        for table in Table::ALL {
+            let writer =
+                ArrowWriter::try_new(open(table)?, schema(table), Some(properties.clone()))?;
+            writers.insert(table, writer);
+        }
+        Ok(Self { writers })
+    }
+}
+
+impl<W: Write + Send> core::fmt::Debug for ParquetWriter<W> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ParquetWriter")
+            .field("tables", &self.writers.keys())
+            .finish()
+    }
+}
+
+impl<W: Write + Send> Writer for ParquetWriter<W> {
+    fn write(&mut self, table: Table, rows: &[Row]) -> Result<()> {
+        let batch = record_batch(table, rows)?;
+        let writer = self.writers.get_mut(&table).expect("every table is open");
+        writer.write(&batch)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for (_, writer) in core::mem::take(&mut self.writers) {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,24 @@
+//! GB/T 32960 packet codec.
+//!
+//! Builds without `std` (but with `alloc`) when the default features are
+//! disabled; the `gbk` feature brings in the GBK/GB18030 tables.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub use crate::serde::from_str;
 pub use crate::serde::to_string;
 
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod export;
 pub mod packet;
+#[cfg(feature = "pcap")]
+pub mod pcap;
 pub mod serde;
+#[cfg(feature = "std")]
+pub mod sim;
+#[cfg(feature = "json")]
+pub mod sink;
@@ -1,58 +1,211 @@
-use std::fmt::Debug;
-use std::io::Read;
+use alloc::vec::Vec;
 
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
+use serde::{Deserialize, Serialize, Serializer as _};
 
 use crate::packet::error::{Error, Result};
-use crate::packet::{types, Time};
+use crate::packet::realtime::RealTimeReport;
+use crate::packet::{parser, types, Command, Time};
+use crate::serde::read::Read;
+use crate::serde::write::Write;
 use crate::serde::{Deserializer, Serializer};
 
-pub trait Body: Debug {}
+/// Longest rechargeable energy storage subsystem code.
+pub const MAX_SUBSYS_CODE_LEN: u8 = 50;
+
+/// Most rechargeable energy storage subsystems of a vehicle.
+pub const MAX_SUBSYS_NUM: usize = 250;
+
+/// Decoded body of a packet, one variant per command.
+#[derive(Debug)]
+pub enum Body {
+    VehicleLogin(VehicleLogin),
+    RealTimeReport(RealTimeReport),
+    ReissueReport(RealTimeReport),
+    VehicleLogout(VehicleLogout),
+    PlatformLogin(PlatformLogin),
+    PlatformLogout(PlatformLogout),
+    /// Heart beats carry no data, anything sent anyway is kept.
+    HeartBeat(Raw),
+    /// Time requests carry no data, anything sent anyway is kept.
+    Time(Raw),
+    /// Body of a reserved or vendor defined command, kept undecoded.
+    Unknown {
+        command: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl Body {
+    pub fn command(&self) -> Command {
+        match self {
+            Body::VehicleLogin(_) => Command::VehicleLogin,
+            Body::RealTimeReport(_) => Command::RealTimeReport,
+            Body::ReissueReport(_) => Command::ReissueReport,
+            Body::VehicleLogout(_) => Command::VehicleLogout,
+            Body::PlatformLogin(_) => Command::PlatformLogin,
+            Body::PlatformLogout(_) => Command::PlatformLogout,
+            Body::HeartBeat(_) => Command::HeartBeat,
+            Body::Time(_) => Command::Time,
+            Body::Unknown { command, .. } => Command::Unknown(*command),
+        }
+    }
+
+    /// Encodes the body, the reverse of [`parser::parse_body`].
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        match self {
+            Body::VehicleLogin(b) => b.serialize(ser),
+            Body::RealTimeReport(b) | Body::ReissueReport(b) => b.serialize(ser),
+            Body::VehicleLogout(b) => b.serialize(ser),
+            Body::PlatformLogin(b) => b.serialize(ser),
+            Body::PlatformLogout(b) => b.serialize(ser),
+            Body::HeartBeat(b) | Body::Time(b) => b.serialize(ser),
+            Body::Unknown { data, .. } => ser.serialize_bytes(data).map_err(Error::from),
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut buff = Vec::new();
+        self.serialize(&mut Serializer::new(&mut buff))?;
+        Ok(buff)
+    }
+}
 
 #[derive(Debug)]
 pub struct VehicleLogin {
     pub at: types::Time,
     pub sn: u16,
     pub iccid: types::Iccid,
-    pub subsys_num: u8,
+    /// Length in bytes of every code in `subsys_codes`.
     pub subsys_len: u8,
-    pub subsys_sn: Vec<u8>,
+    pub subsys_codes: Vec<types::SubsysCode>,
 }
 
-impl Body for VehicleLogin {}
-
 impl VehicleLogin {
-    pub fn deserialize<R: Read>(de: &mut Deserializer<R>) -> Result<Self> {
-        let mut subsys_sn: Vec<u8> = Vec::new();
-        let at: types::Time = serde::Deserialize::deserialize(&mut *de)?;
-        let sn = de.deserialize_u16()?;
-        let iccid: types::Iccid = serde::Deserialize::deserialize(&mut *de)?;
-        let subsys_num = de.deserialize_u8()?;
-        let subsys_len = de.deserialize_u8()?;
-        let len = (subsys_num * subsys_len) as usize;
-        if len > 0 {
-            subsys_sn = de.read_bytes(len)?;
-        }
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        Self::deserialize_with(de, &parser::Options::default())
+    }
+
+    /// Decodes the body, checking the ICCID according to `options`.
+    pub fn deserialize_with<'de, R: Read<'de>>(
+        de: &mut Deserializer<R>,
+        options: &parser::Options,
+    ) -> Result<Self> {
+        let at: types::Time = de.field("at", |de| serde::Deserialize::deserialize(de))?;
+        let sn = de.field("sn", |de| de.deserialize_u16())?;
+        let iccid = de.field("iccid", |de| -> Result<types::Iccid> {
+            let mut iccid = types::Iccid::from(de.deserialize_gbk_string()?);
+            iccid.check(options.iccid)?;
+            Ok(iccid)
+        })?;
+        let subsys_num = de.field("subsys_num", |de| -> Result<usize> {
+            let num = de.deserialize_u8()? as usize;
+            if num > MAX_SUBSYS_NUM {
+                return Err(Error::SubsysCount(num));
+            }
+            Ok(num)
+        })?;
+        let subsys_len = de.field("subsys_len", |de| -> Result<u8> {
+            let len = de.deserialize_u8()?;
+            if len > MAX_SUBSYS_CODE_LEN {
+                return Err(Error::SubsysCodeLength(len));
+            }
+            Ok(len)
+        })?;
+        let subsys_codes = de.field("subsys_codes", |de| -> Result<Vec<types::SubsysCode>> {
+            let mut codes = Vec::with_capacity(subsys_num);
+            for i in 0..subsys_num {
+                codes.push(de.element(i, |de| de.deserialize_gbk_string_len(subsys_len as usize))?);
+            }
+            Ok(codes)
+        })?;
         Ok(Self {
             at,
             sn,
             iccid,
-            subsys_num,
             subsys_len,
-            subsys_sn,
+            subsys_codes,
         })
     }
+
+    /// Encodes the body, the reverse of [`deserialize`](Self::deserialize).
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        if self.subsys_len > MAX_SUBSYS_CODE_LEN {
+            return Err(Error::SubsysCodeLength(self.subsys_len));
+        }
+        if self.subsys_codes.len() > MAX_SUBSYS_NUM {
+            return Err(Error::SubsysCount(self.subsys_codes.len()));
+        }
+        self.at.serialize(&mut *ser)?;
+        ser.serialize_u16(self.sn)?;
+        self.iccid.serialize(&mut *ser)?;
+        ser.serialize_u8(self.subsys_codes.len() as u8)?;
+        ser.serialize_u8(self.subsys_len)?;
+        for code in self.subsys_codes.iter() {
+            ser.serialize_gbk_string_len(code, self.subsys_len as usize)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct VehicleLogout {
     pub at: Time,
     pub sn: u16,
 }
 
 impl VehicleLogout {
-    pub fn deserialize<R: Read>(de: &mut Deserializer<R>) -> Result<Self> {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        ::serde::Deserialize::deserialize(&mut *de).map_err(Error::from)
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ::serde::Serialize::serialize(self, &mut *ser).map_err(Error::from)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlatformLogin {
+    pub at: Time,
+    pub sn: u16,
+    pub username: types::Username,
+    pub password: types::Password,
+    pub encrypt: types::Encrypt,
+}
+
+impl PlatformLogin {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
         ::serde::Deserialize::deserialize(&mut *de).map_err(Error::from)
     }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ::serde::Serialize::serialize(self, &mut *ser).map_err(Error::from)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlatformLogout {
+    pub at: Time,
+    pub sn: u16,
+}
+
+impl PlatformLogout {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        ::serde::Deserialize::deserialize(&mut *de).map_err(Error::from)
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ::serde::Serialize::serialize(self, &mut *ser).map_err(Error::from)
+    }
+}
+
+/// Body of commands without a dedicated type, such as heart beats.
+#[derive(Debug)]
+pub struct Raw {
+    pub data: Vec<u8>,
+}
+
+impl Raw {
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_bytes(&self.data).map_err(Error::from)
+    }
 }
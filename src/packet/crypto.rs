@@ -0,0 +1,208 @@
+//! Body encryption announced by the `encrypt` header field.
+//!
+//! The standard leaves the cipher modes open; the built-in ciphers follow
+//! common platform practice:
+//!
+//! * [`Aes128`] (feature `aes`): AES-128 in ECB mode with PKCS#7 padding.
+//! * [`Rsa`] (feature `rsa`): RSA with PKCS#1 v1.5 padding, the body split
+//!   into as many blocks as needed.
+
+use alloc::vec::Vec;
+
+use crate::packet::Encrypt;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("no {0:?} key for the packet")]
+    MissingKey(Encrypt),
+
+    #[error("packet is encrypted with {actual:?} but the key is for {expected:?}")]
+    KeyKind { expected: Encrypt, actual: Encrypt },
+
+    #[error("{0:?} key cannot be used for {1}")]
+    KeyUse(Encrypt, &'static str),
+
+    #[error("invalid key: {0}")]
+    Key(&'static str),
+
+    #[error("failed to encrypt body")]
+    Encrypt,
+
+    #[error("failed to decrypt body")]
+    Decrypt,
+}
+
+/// Encrypts and decrypts packet bodies.
+pub trait Cipher: Send + Sync {
+    /// Value of the `encrypt` header field for bodies of this cipher.
+    fn kind(&self) -> Encrypt;
+
+    fn encrypt(&self, body: &[u8]) -> Result<Vec<u8>, Error>;
+
+    fn decrypt(&self, body: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// Checks that `cipher` can handle bodies encrypted with `encrypt`.
+pub(crate) fn select(encrypt: Encrypt, cipher: Option<&dyn Cipher>) -> Result<&dyn Cipher, Error> {
+    let cipher = cipher.ok_or(Error::MissingKey(encrypt))?;
+    if cipher.kind() != encrypt {
+        return Err(Error::KeyKind {
+            expected: cipher.kind(),
+            actual: encrypt,
+        });
+    }
+    Ok(cipher)
+}
+
+#[cfg(feature = "aes")]
+pub use self::aes128::Aes128;
+
+#[cfg(feature = "aes")]
+mod aes128 {
+    use alloc::vec::Vec;
+    use core::convert::TryInto;
+
+    use aes::cipher::generic_array::GenericArray;
+    use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+
+    use super::{Cipher, Error};
+    use crate::packet::Encrypt;
+
+    const BLOCK: usize = 16;
+
+    pub struct Aes128 {
+        cipher: aes::Aes128,
+    }
+
+    impl Aes128 {
+        pub fn new(key: &[u8; 16]) -> Self {
+            Self {
+                cipher: aes::Aes128::new(GenericArray::from_slice(key)),
+            }
+        }
+
+        pub fn from_slice(key: &[u8]) -> Result<Self, Error> {
+            let key: &[u8; 16] = key
+                .try_into()
+                .map_err(|_| Error::Key("AES-128 keys have 16 bytes"))?;
+            Ok(Self::new(key))
+        }
+    }
+
+    impl Cipher for Aes128 {
+        fn kind(&self) -> Encrypt {
+            Encrypt::Aes128
+        }
+
+        fn encrypt(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+            let pad = BLOCK - body.len() % BLOCK;
+            let mut data = Vec::with_capacity(body.len() + pad);
+            data.extend_from_slice(body);
+            data.resize(body.len() + pad, pad as u8);
+            for block in data.chunks_exact_mut(BLOCK) {
+                self.cipher
+                    .encrypt_block(GenericArray::from_mut_slice(block));
+            }
+            Ok(data)
+        }
+
+        fn decrypt(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+            if body.is_empty() || !body.len().is_multiple_of(BLOCK) {
+                return Err(Error::Decrypt);
+            }
+            let mut data = body.to_vec();
+            for block in data.chunks_exact_mut(BLOCK) {
+                self.cipher
+                    .decrypt_block(GenericArray::from_mut_slice(block));
+            }
+            let pad = data[data.len() - 1] as usize;
+            let valid = (1..=BLOCK).contains(&pad)
+                && data[data.len() - pad..].iter().all(|&b| b as usize == pad);
+            if !valid {
+                return Err(Error::Decrypt);
+            }
+            data.truncate(data.len() - pad);
+            Ok(data)
+        }
+    }
+}
+
+#[cfg(feature = "rsa")]
+pub use self::rsa_pkcs1::Rsa;
+
+#[cfg(feature = "rsa")]
+mod rsa_pkcs1 {
+    use alloc::vec::Vec;
+
+    use rsa::rand_core::OsRng;
+    use rsa::traits::PublicKeyParts;
+    use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+
+    use super::{Cipher, Error};
+    use crate::packet::Encrypt;
+
+    /// Bytes of PKCS#1 v1.5 padding in every block.
+    const PADDING: usize = 11;
+
+    /// Encrypts with the public key of the receiver and decrypts with the
+    /// own private key; either may be missing for one way use.
+    pub struct Rsa {
+        public: Option<RsaPublicKey>,
+        private: Option<RsaPrivateKey>,
+    }
+
+    impl Rsa {
+        pub fn new(public: Option<RsaPublicKey>, private: Option<RsaPrivateKey>) -> Self {
+            Self { public, private }
+        }
+
+        /// Encrypts only.
+        pub fn from_public(public: RsaPublicKey) -> Self {
+            Self::new(Some(public), None)
+        }
+
+        /// Decrypts only.
+        pub fn from_private(private: RsaPrivateKey) -> Self {
+            Self::new(None, Some(private))
+        }
+    }
+
+    impl Cipher for Rsa {
+        fn kind(&self) -> Encrypt {
+            Encrypt::Rsa
+        }
+
+        fn encrypt(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+            let key = self
+                .public
+                .as_ref()
+                .ok_or(Error::KeyUse(Encrypt::Rsa, "encryption"))?;
+            let mut data = Vec::new();
+            for chunk in body.chunks(key.size() - PADDING) {
+                let block = key
+                    .encrypt(&mut OsRng, Pkcs1v15Encrypt, chunk)
+                    .map_err(|_| Error::Encrypt)?;
+                data.extend_from_slice(&block);
+            }
+            Ok(data)
+        }
+
+        fn decrypt(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+            let key = self
+                .private
+                .as_ref()
+                .ok_or(Error::KeyUse(Encrypt::Rsa, "decryption"))?;
+            if !body.len().is_multiple_of(key.size()) {
+                return Err(Error::Decrypt);
+            }
+            let mut data = Vec::new();
+            for chunk in body.chunks(key.size()) {
+                let block = key
+                    .decrypt(Pkcs1v15Encrypt, chunk)
+                    .map_err(|_| Error::Decrypt)?;
+                data.extend_from_slice(&block);
+            }
+            Ok(data)
+        }
+    }
+}
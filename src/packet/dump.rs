@@ -0,0 +1,71 @@
+//! Annotated hex dumps of frames, listing the bytes of every field next to
+//! its path and decoded value:
+//!
+//! ```text
+//! 0000  23 23                    Header.begin       8995
+//! 0002  01                       Header.command     1
+//! 0003  fe                       Header.response    254
+//! 0004  4c 5a 59 54 42 47 42 57  Header.vin         "LZYTBGBW6J1014194"
+//! 000c  36 4a 31 30 31 34 31 39
+//! 0014  34
+//! ```
+//!
+//! Bytes without a span, such as those after a decoding error, are listed
+//! as not decoded.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::packet::error::Result;
+use crate::packet::{parser, Packet};
+use crate::serde::Span;
+
+/// Bytes shown on one line.
+const LINE_BYTES: usize = 8;
+
+const NOT_DECODED: &str = "(not decoded)";
+
+/// Parses a plain packet and renders the dump of every field read until
+/// decoding finished or failed.
+pub fn dump(data: &[u8], options: &parser::Options) -> (String, Result<Packet>) {
+    let (spans, packet) = parser::annotate(data, options);
+    (render(data, &spans), packet)
+}
+
+/// Renders `spans`, sorted by offset, over the frame `data`.
+pub fn render(data: &[u8], spans: &[Span]) -> String {
+    let mut rows: Vec<(usize, &[u8], &str, &str)> = Vec::new();
+    let mut pos = 0;
+    for span in spans {
+        if span.offset > pos {
+            rows.push((
+                pos,
+                &data[pos..span.offset.min(data.len())],
+                NOT_DECODED,
+                "",
+            ));
+        }
+        rows.push((span.offset, &span.raw, &span.path, &span.value));
+        pos = pos.max(span.end());
+    }
+    if pos < data.len() {
+        rows.push((pos, &data[pos..], NOT_DECODED, ""));
+    }
+
+    let width = rows.iter().map(|row| row.2.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (offset, raw, path, value) in rows {
+        for (i, chunk) in raw.chunks(LINE_BYTES).enumerate() {
+            let bytes: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let mut line = format!("{:04x}  {:<23}", offset + i * LINE_BYTES, bytes.join(" "));
+            if i == 0 {
+                let _ = write!(line, "  {:<width$}  {}", path, value, width = width);
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -1,15 +1,131 @@
-use std::fmt;
+use alloc::boxed::Box;
+use alloc::string::String;
+
+use crate::serde::Positioned;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Unimplemented")]
     Unimplemented,
 
-    #[error(transparent)]
-    HexString(#[from] hex::FromHexError),
+    #[error("invalid start of packet: {0:#06x}")]
+    Begin(u16),
+
+    #[error("{command:?} packet with a {body:?} body")]
+    CommandMismatch {
+        command: crate::packet::Command,
+        body: crate::packet::Command,
+    },
+
+    #[error("unknown real time item: {0:#04x}")]
+    UnknownItem(u8),
+
+    #[error("checksum mismatch, expected {expected:#04x} but got {actual:#04x}")]
+    Checksum { expected: u8, actual: u8 },
+
+    #[error("subsystem code length {0} exceeds 50")]
+    SubsysCodeLength(u8),
+
+    #[error("{0} subsystem codes, more than 250")]
+    SubsysCount(usize),
+
+    #[error("{len} elements of {field} do not fit its count")]
+    Count { field: &'static str, len: usize },
+
+    #[error("{0} trailing bytes after body")]
+    TrailingBytes(usize),
+
+    #[error("invalid hex string: {0}")]
+    HexString(hex::FromHexError),
 
     #[error(transparent)]
     Serde(#[from] crate::serde::Error),
+
+    #[error(transparent)]
+    Crypto(#[from] crate::packet::crypto::Error),
+
+    #[error(transparent)]
+    Iccid(#[from] crate::packet::iccid::Error),
+
+    #[error(transparent)]
+    Vin(#[from] crate::packet::vin::Error),
+
+    #[error("{source} at offset {offset} while decoding {path}")]
+    At {
+        offset: usize,
+        path: String,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Offset of the field that failed to decode.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::At { offset, .. } => Some(*offset),
+            Error::Serde(e) => e.offset(),
+            _ => None,
+        }
+    }
+
+    /// Path of the field that failed to decode, such as
+    /// `RealTimeReport.items[3].motors[1].torque`.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::At { path, .. } => Some(path.as_str()),
+            Error::Serde(e) => e.path(),
+            _ => None,
+        }
+    }
+
+    /// Short snake case name of the kind of error, such as `checksum` or
+    /// `unknown_item`, the same for an error wherever it occurred.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Unimplemented => "unimplemented",
+            Error::Begin(_) => "begin",
+            Error::CommandMismatch { .. } => "command_mismatch",
+            Error::UnknownItem(_) => "unknown_item",
+            Error::Checksum { .. } => "checksum",
+            Error::SubsysCodeLength(_) => "subsys_code_length",
+            Error::SubsysCount(_) => "subsys_count",
+            Error::Count { .. } => "count",
+            Error::TrailingBytes(_) => "trailing_bytes",
+            Error::HexString(_) => "hex_string",
+            Error::Serde(e) => e.kind(),
+            Error::Crypto(_) => "crypto",
+            Error::Iccid(_) => "iccid",
+            Error::Vin(_) => "vin",
+            Error::At { source, .. } => source.kind(),
+        }
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(err: hex::FromHexError) -> Self {
+        Error::HexString(err)
+    }
+}
+
+impl Positioned for Error {
+    fn is_positioned(&self) -> bool {
+        match self {
+            Error::At { .. } => true,
+            Error::Serde(e) => e.is_positioned(),
+            _ => false,
+        }
+    }
+
+    fn positioned(self, offset: usize, path: String) -> Self {
+        match self {
+            Error::Serde(e) => Error::Serde(e.positioned(offset, path)),
+            e => Error::At {
+                offset,
+                path,
+                source: Box::new(e),
+            },
+        }
+    }
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
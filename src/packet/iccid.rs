@@ -0,0 +1,216 @@
+//! Integrated circuit card identifiers of SIM cards per ITU-T E.118.
+//!
+//! | digits   | part                                        |
+//! |----------|---------------------------------------------|
+//! | 1-2      | major industry identifier, `89` for telecom |
+//! | 3-5      | country code, one to three digits (E.164)   |
+//! | next 1-4 | issuer identifier                           |
+//! | rest     | account number                              |
+//! | last     | Luhn check digit                            |
+
+use alloc::string::String;
+use core::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::packet::types::{IccidOpts, Strictness};
+use crate::serde::gbk::GBKString;
+
+/// Major industry identifier of telecommunication cards.
+pub const PREFIX: &str = "89";
+
+pub const MIN_LENGTH: usize = 19;
+
+pub const MAX_LENGTH: usize = 20;
+
+/// Length of the issuer identifier assumed by [`Iccid::issuer`].
+const ISSUER_LENGTH: usize = 2;
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("ICCID has {0} characters instead of 19 or 20")]
+    Length(usize),
+
+    #[error("invalid character {ch:?} at position {position} of ICCID")]
+    Char { position: usize, ch: char },
+
+    #[error("ICCID does not start with 89")]
+    Prefix,
+
+    #[error("ICCID check digit is {actual:?} but should be {expected:?}")]
+    CheckDigit { expected: char, actual: char },
+}
+
+pub struct Iccid {
+    inner: GBKString<IccidOpts>,
+    issue: Option<Error>,
+}
+
+impl Iccid {
+    /// Validates `iccid` before accepting it.
+    pub fn new(iccid: &str) -> Result<Self, Error> {
+        validate(iccid)?;
+        Ok(Self::new_unchecked(String::from(iccid)))
+    }
+
+    pub fn new_unchecked(iccid: String) -> Self {
+        Self {
+            inner: GBKString::from(iccid),
+            issue: None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.inner.message.as_str()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        validate(self.as_str())
+    }
+
+    /// Applies `strictness` to the decoded ICCID, the same way as
+    /// [`Vin::check`](crate::packet::Vin::check).
+    pub fn check(&mut self, strictness: Strictness) -> Result<(), Error> {
+        match strictness {
+            Strictness::Reject => self.validate(),
+            Strictness::Warn => {
+                self.issue = self.validate().err();
+                Ok(())
+            }
+            Strictness::Accept => Ok(()),
+        }
+    }
+
+    /// Problem found by [`check`](Self::check) with [`Strictness::Warn`].
+    pub fn issue(&self) -> Option<Error> {
+        self.issue
+    }
+
+    /// E.164 country code, `86` for China.
+    pub fn country_code(&self) -> Option<&str> {
+        let digits = self.digits()?;
+        let len = country_code_len(&digits[PREFIX.len()..])?;
+        digits.get(PREFIX.len()..PREFIX.len() + len)
+    }
+
+    /// Issuer identifier, assuming the two digits used in China, such as
+    /// `00` for China Mobile or `01` for China Unicom.
+    pub fn issuer(&self) -> Option<&str> {
+        let start = PREFIX.len() + self.country_code()?.len();
+        self.digits()?.get(start..start + ISSUER_LENGTH)
+    }
+
+    pub fn check_digit(&self) -> Option<char> {
+        self.digits()?.chars().last()
+    }
+
+    /// The ICCID if it has the right length and consists of digits.
+    fn digits(&self) -> Option<&str> {
+        let s = self.as_str();
+        let valid = (MIN_LENGTH..=MAX_LENGTH).contains(&s.len())
+            && s.starts_with(PREFIX)
+            && s.bytes().all(|b| b.is_ascii_digit());
+        if valid {
+            Some(s)
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks length, alphabet, prefix and check digit of `iccid`.
+pub fn validate(iccid: &str) -> Result<(), Error> {
+    let len = iccid.chars().count();
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&len) {
+        return Err(Error::Length(len));
+    }
+    for (i, ch) in iccid.chars().enumerate() {
+        if !ch.is_ascii_digit() {
+            return Err(Error::Char {
+                position: i + 1,
+                ch,
+            });
+        }
+    }
+    if !iccid.starts_with(PREFIX) {
+        return Err(Error::Prefix);
+    }
+    let (payload, actual) = iccid.split_at(len - 1);
+    let expected = compute_check_digit(payload).ok_or(Error::Length(len))?;
+    let actual = actual.as_bytes()[0] as char;
+    if expected != actual {
+        return Err(Error::CheckDigit { expected, actual });
+    }
+    Ok(())
+}
+
+/// Luhn check digit to append to the digits in `payload`.
+pub fn compute_check_digit(payload: &str) -> Option<char> {
+    let mut sum = 0;
+    for (i, ch) in payload.chars().rev().enumerate() {
+        let mut digit = ch.to_digit(10)?;
+        if i % 2 == 0 {
+            digit *= 2;
+            if digit > 9 {
+                digit -= 9;
+            }
+        }
+        sum += digit;
+    }
+    core::char::from_digit((10 - sum % 10) % 10, 10)
+}
+
+/// Length of the country code at the start of `digits`, following the
+/// E.164 assignment of one, two and three digit codes.
+fn country_code_len(digits: &str) -> Option<usize> {
+    let bytes = digits.as_bytes();
+    let first = *bytes.first()?;
+    let second = *bytes.get(1)?;
+    let two_digits: &[u8] = match first {
+        b'1' | b'7' => return Some(1),
+        b'2' => b"07",
+        b'3' => b"0123469",
+        b'4' => b"013456789",
+        b'5' => b"12345678",
+        b'6' => b"0123456",
+        b'8' => b"1246",
+        b'9' => b"0123458",
+        _ => return None,
+    };
+    if two_digits.contains(&second) {
+        Some(2)
+    } else {
+        Some(3)
+    }
+}
+
+impl Serialize for Iccid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Iccid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let inner = GBKString::deserialize(deserializer)?;
+        Ok(Self { inner, issue: None })
+    }
+}
+
+impl From<GBKString<IccidOpts>> for Iccid {
+    fn from(inner: GBKString<IccidOpts>) -> Self {
+        Self { inner, issue: None }
+    }
+}
+
+impl fmt::Display for Iccid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Iccid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,692 @@
+//! JSON representation of decoded packets (feature `json`).
+//!
+//! The serde implementations of the packet types describe the binary
+//! format, so this module maps packets to and from [`serde_json::Value`] by
+//! hand. The representation is stable:
+//!
+//! * Enumerations are snake case names: `"vehicle_login"` for commands,
+//!   or the command byte as a number for reserved and vendor commands,
+//!   `"success"`, `"fail"`, `"dup_vin"` or `"command"` for responses and
+//!   `"none"`, `"rsa"` or `"aes128"` for encryption.
+//! * VINs, ICCIDs and other text fields are strings; opaque bytes such as
+//!   the data of heart beats and custom items are hex strings.
+//! * Times are ISO-8601 in China time, `"2018-10-30T20:35:54+08:00"`.
+//! * Fields keep their raw value under their name. Fields with a scale or an
+//!   offset also get `<name>_value` with the engineering value in the unit
+//!   documented on the field, which is `null` for the "abnormal" and
+//!   "invalid" markers (`0xFE`/`0xFF`, `0xFFFE`/`0xFFFF`, ...).
+//!   Location values are signed by the hemisphere bits of the status.
+//! * Real time items are objects tagged with `"type"`: `"vehicle"`,
+//!   `"motors"`, `"fuel_cell"`, `"engine"`, `"location"`, `"extremes"`,
+//!   `"alarm"`, `"voltages"`, `"temperatures"` or `"custom"`.
+//!
+//! ```json
+//! {
+//!   "command": "vehicle_logout",
+//!   "response": "command",
+//!   "vin": "LZYTBGBW6J1014194",
+//!   "encrypt": "none",
+//!   "body_len": 8,
+//!   "bcc": 70,
+//!   "body": { "at": "2018-10-30T20:35:54+08:00", "sn": 253 }
+//! }
+//! ```
+//!
+//! The reverse direction only reads raw values, ignoring `*_value` fields,
+//! and defaults `body_len` and `bcc` to zero since encoding computes them.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::Display;
+
+use serde_json::{json, Map, Value};
+
+use crate::packet::body::{self, Body};
+use crate::packet::realtime::{self, Item, RealTimeReport};
+use crate::packet::time::BASE_YEAR;
+use crate::packet::types::{Iccid, Vin};
+use crate::packet::{Command, Encrypt, Packet, Response, Time, BEGIN};
+use crate::serde::gbk::GBKString;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Syntax(#[from] serde_json::Error),
+
+    #[error("{reason} at {path}")]
+    Field { path: String, reason: String },
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+const COMMANDS: [(&str, Command); 8] = [
+    ("vehicle_login", Command::VehicleLogin),
+    ("real_time_report", Command::RealTimeReport),
+    ("reissue_report", Command::ReissueReport),
+    ("vehicle_logout", Command::VehicleLogout),
+    ("platform_login", Command::PlatformLogin),
+    ("platform_logout", Command::PlatformLogout),
+    ("heart_beat", Command::HeartBeat),
+    ("time", Command::Time),
+];
+
+const RESPONSES: [(&str, Response); 4] = [
+    ("success", Response::Success),
+    ("fail", Response::Fail),
+    ("dup_vin", Response::DupVin),
+    ("command", Response::Command),
+];
+
+const ENCRYPTS: [(&str, Encrypt); 3] = [
+    ("none", Encrypt::None),
+    ("rsa", Encrypt::Rsa),
+    ("aes128", Encrypt::Aes128),
+];
+
+fn name_of<T: PartialEq>(names: &[(&'static str, T)], value: T) -> &'static str {
+    names
+        .iter()
+        .find(|(_, v)| *v == value)
+        .map(|(name, _)| *name)
+        .expect("every variant is named")
+}
+
+pub fn to_value(packet: &Packet) -> Value {
+    json!({
+        "command": command_to_value(packet.command),
+        "response": name_of(&RESPONSES, packet.response),
+        "vin": packet.vin.as_str(),
+        "encrypt": name_of(&ENCRYPTS, packet.encrypt),
+        "body_len": packet.body_len,
+        "bcc": packet.bcc,
+        "body": body_to_value(&packet.body),
+    })
+}
+
+fn command_to_value(command: Command) -> Value {
+    match command {
+        Command::Unknown(command) => command.into(),
+        command => name_of(&COMMANDS, command).into(),
+    }
+}
+
+pub fn to_string(packet: &Packet) -> String {
+    to_value(packet).to_string()
+}
+
+pub fn to_string_pretty(packet: &Packet) -> String {
+    format!("{:#}", to_value(packet))
+}
+
+pub fn from_value(value: &Value) -> Result<Packet> {
+    let node = Node::root(value);
+    let command = node.get("command")?.command()?;
+    let vin = node.get("vin")?.str()?;
+    Ok(Packet {
+        begin: BEGIN,
+        command,
+        response: node.get("response")?.name(&RESPONSES)?,
+        vin: Vin::new_unchecked(vin.into()),
+        encrypt: node.get("encrypt")?.name(&ENCRYPTS)?,
+        body_len: node.opt("body_len").map_or(Ok(0), |n| n.int())?,
+        body: body_from_node(command, &node.get("body")?)?,
+        bcc: node.opt("bcc").map_or(Ok(0), |n| n.int())?,
+    })
+}
+
+pub fn from_str(text: &str) -> Result<Packet> {
+    from_value(&serde_json::from_str(text)?)
+}
+
+pub fn body_to_value(body: &Body) -> Value {
+    match body {
+        Body::VehicleLogin(b) => json!({
+            "at": time(&b.at),
+            "sn": b.sn,
+            "iccid": b.iccid.as_str(),
+            "subsys_len": b.subsys_len,
+            "subsys_codes": b.subsys_codes.iter().map(|c| c.message.as_str()).collect::<Vec<_>>(),
+        }),
+        Body::RealTimeReport(b) | Body::ReissueReport(b) => json!({
+            "at": time(&b.at),
+            "items": b.items.iter().map(item_to_value).collect::<Vec<_>>(),
+        }),
+        Body::VehicleLogout(b) => json!({ "at": time(&b.at), "sn": b.sn }),
+        Body::PlatformLogin(b) => json!({
+            "at": time(&b.at),
+            "sn": b.sn,
+            "username": b.username.message,
+            "password": b.password.message,
+            "encrypt": name_of(&ENCRYPTS, b.encrypt),
+        }),
+        Body::PlatformLogout(b) => json!({ "at": time(&b.at), "sn": b.sn }),
+        Body::HeartBeat(b) | Body::Time(b) => json!({ "data": hex::encode(&b.data) }),
+        Body::Unknown { data, .. } => json!({ "data": hex::encode(data) }),
+    }
+}
+
+/// Decodes the JSON body of a `command` packet.
+pub fn body_from_value(command: Command, value: &Value) -> Result<Body> {
+    body_from_node(command, &Node::root(value))
+}
+
+fn body_from_node(command: Command, node: &Node) -> Result<Body> {
+    let body = match command {
+        Command::VehicleLogin => Body::VehicleLogin(body::VehicleLogin {
+            at: node.get("at")?.time()?,
+            sn: node.get("sn")?.int()?,
+            iccid: Iccid::new_unchecked(node.get("iccid")?.str()?.into()),
+            subsys_len: node.get("subsys_len")?.int()?,
+            subsys_codes: node
+                .get("subsys_codes")?
+                .elements()?
+                .iter()
+                .map(|n| n.str().map(|s| GBKString::from(s.into())))
+                .collect::<Result<_>>()?,
+        }),
+        Command::RealTimeReport => Body::RealTimeReport(report_from_node(node)?),
+        Command::ReissueReport => Body::ReissueReport(report_from_node(node)?),
+        Command::VehicleLogout => Body::VehicleLogout(body::VehicleLogout {
+            at: node.get("at")?.time()?,
+            sn: node.get("sn")?.int()?,
+        }),
+        Command::PlatformLogin => Body::PlatformLogin(body::PlatformLogin {
+            at: node.get("at")?.time()?,
+            sn: node.get("sn")?.int()?,
+            username: GBKString::from(node.get("username")?.str()?.into()),
+            password: GBKString::from(node.get("password")?.str()?.into()),
+            encrypt: node.get("encrypt")?.name(&ENCRYPTS)?,
+        }),
+        Command::PlatformLogout => Body::PlatformLogout(body::PlatformLogout {
+            at: node.get("at")?.time()?,
+            sn: node.get("sn")?.int()?,
+        }),
+        Command::HeartBeat => Body::HeartBeat(raw_from_node(node)?),
+        Command::Time => Body::Time(raw_from_node(node)?),
+        Command::Unknown(command) => Body::Unknown {
+            command,
+            data: raw_from_node(node)?.data,
+        },
+    };
+    Ok(body)
+}
+
+fn raw_from_node(node: &Node) -> Result<body::Raw> {
+    // bodies without data may be written as `{}`
+    let data = match node.opt("data") {
+        Some(data) => data.hex()?,
+        None => Vec::new(),
+    };
+    Ok(body::Raw { data })
+}
+
+fn report_from_node(node: &Node) -> Result<RealTimeReport> {
+    Ok(RealTimeReport {
+        at: node.get("at")?.time()?,
+        items: node
+            .get("items")?
+            .elements()?
+            .iter()
+            .map(item_from_node)
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// Formats `at` as ISO-8601 in China time.
+fn time(at: &Time) -> String {
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}+08:00",
+        at.full_year(),
+        at.month,
+        at.day,
+        at.hour,
+        at.minute,
+        at.second
+    )
+}
+
+/// Engineering value of `raw`, `null` for the two markers below `max`.
+fn scaled(raw: u32, max: u32, div: f64, offset: f64) -> Value {
+    if raw >= max - 1 {
+        return Value::Null;
+    }
+    json!(raw as f64 / div + offset)
+}
+
+fn scaled8(raw: u8, div: f64, offset: f64) -> Value {
+    scaled(raw.into(), u8::MAX.into(), div, offset)
+}
+
+fn scaled16(raw: u16, div: f64, offset: f64) -> Value {
+    scaled(raw.into(), u16::MAX.into(), div, offset)
+}
+
+fn scaled32(raw: u32, div: f64, offset: f64) -> Value {
+    scaled(raw, u32::MAX, div, offset)
+}
+
+/// Latitude or longitude, negative if the hemisphere `bit` of `status` is
+/// set.
+fn coordinate(raw: u32, status: u8, bit: u8) -> Value {
+    match scaled32(raw, 1e6, 0.0) {
+        Value::Number(n) if status & bit != 0 => json!(-n.as_f64().unwrap_or_default()),
+        value => value,
+    }
+}
+
+fn item_to_value(item: &Item) -> Value {
+    match item {
+        Item::Vehicle(v) => json!({
+            "type": "vehicle",
+            "status": v.status,
+            "charging": v.charging,
+            "mode": v.mode,
+            "speed": v.speed,
+            "speed_value": scaled16(v.speed, 10.0, 0.0),
+            "mileage": v.mileage,
+            "mileage_value": scaled32(v.mileage, 10.0, 0.0),
+            "voltage": v.voltage,
+            "voltage_value": scaled16(v.voltage, 10.0, 0.0),
+            "current": v.current,
+            "current_value": scaled16(v.current, 10.0, -1000.0),
+            "soc": v.soc,
+            "dc_status": v.dc_status,
+            "gear": v.gear,
+            "insulation": v.insulation,
+            "accelerator": v.accelerator,
+            "brake": v.brake,
+        }),
+        Item::Motors(m) => json!({
+            "type": "motors",
+            "motors": m.motors.iter().map(|m| json!({
+                "sn": m.sn,
+                "status": m.status,
+                "controller_temperature": m.controller_temperature,
+                "controller_temperature_value": scaled8(m.controller_temperature, 1.0, -40.0),
+                "speed": m.speed,
+                "speed_value": scaled16(m.speed, 1.0, -20000.0),
+                "torque": m.torque,
+                "torque_value": scaled16(m.torque, 10.0, -2000.0),
+                "temperature": m.temperature,
+                "temperature_value": scaled8(m.temperature, 1.0, -40.0),
+                "controller_voltage": m.controller_voltage,
+                "controller_voltage_value": scaled16(m.controller_voltage, 10.0, 0.0),
+                "controller_current": m.controller_current,
+                "controller_current_value": scaled16(m.controller_current, 10.0, -1000.0),
+            })).collect::<Vec<_>>(),
+        }),
+        Item::FuelCell(f) => json!({
+            "type": "fuel_cell",
+            "voltage": f.voltage,
+            "voltage_value": scaled16(f.voltage, 10.0, 0.0),
+            "current": f.current,
+            "current_value": scaled16(f.current, 10.0, 0.0),
+            "consumption": f.consumption,
+            "consumption_value": scaled16(f.consumption, 100.0, 0.0),
+            "probe_temperatures": f.probe_temperatures,
+            "probe_temperatures_value": f.probe_temperatures
+                .iter()
+                .map(|&t| scaled8(t, 1.0, -40.0))
+                .collect::<Vec<_>>(),
+            "max_hydrogen_temperature": f.max_hydrogen_temperature,
+            "max_hydrogen_temperature_value": scaled16(f.max_hydrogen_temperature, 10.0, -40.0),
+            "max_hydrogen_temperature_probe": f.max_hydrogen_temperature_probe,
+            "max_hydrogen_concentration": f.max_hydrogen_concentration,
+            "max_hydrogen_concentration_sensor": f.max_hydrogen_concentration_sensor,
+            "max_hydrogen_pressure": f.max_hydrogen_pressure,
+            "max_hydrogen_pressure_value": scaled16(f.max_hydrogen_pressure, 10.0, 0.0),
+            "max_hydrogen_pressure_sensor": f.max_hydrogen_pressure_sensor,
+            "dc_status": f.dc_status,
+        }),
+        Item::Engine(e) => json!({
+            "type": "engine",
+            "status": e.status,
+            "crankshaft_speed": e.crankshaft_speed,
+            "fuel_consumption": e.fuel_consumption,
+            "fuel_consumption_value": scaled16(e.fuel_consumption, 100.0, 0.0),
+        }),
+        Item::Location(l) => json!({
+            "type": "location",
+            "status": l.status,
+            "longitude": l.longitude,
+            "longitude_value": coordinate(l.longitude, l.status, 0b100),
+            "latitude": l.latitude,
+            "latitude_value": coordinate(l.latitude, l.status, 0b010),
+        }),
+        Item::Extremes(e) => json!({
+            "type": "extremes",
+            "max_voltage_subsys": e.max_voltage_subsys,
+            "max_voltage_cell": e.max_voltage_cell,
+            "max_cell_voltage": e.max_cell_voltage,
+            "max_cell_voltage_value": scaled16(e.max_cell_voltage, 1000.0, 0.0),
+            "min_voltage_subsys": e.min_voltage_subsys,
+            "min_voltage_cell": e.min_voltage_cell,
+            "min_cell_voltage": e.min_cell_voltage,
+            "min_cell_voltage_value": scaled16(e.min_cell_voltage, 1000.0, 0.0),
+            "max_temperature_subsys": e.max_temperature_subsys,
+            "max_temperature_probe": e.max_temperature_probe,
+            "max_temperature": e.max_temperature,
+            "max_temperature_value": scaled8(e.max_temperature, 1.0, -40.0),
+            "min_temperature_subsys": e.min_temperature_subsys,
+            "min_temperature_probe": e.min_temperature_probe,
+            "min_temperature": e.min_temperature,
+            "min_temperature_value": scaled8(e.min_temperature, 1.0, -40.0),
+        }),
+        Item::Alarm(a) => json!({
+            "type": "alarm",
+            "level": a.level,
+            "flags": a.flags,
+            "battery_faults": a.battery_faults,
+            "motor_faults": a.motor_faults,
+            "engine_faults": a.engine_faults,
+            "other_faults": a.other_faults,
+        }),
+        Item::Voltages(v) => json!({
+            "type": "voltages",
+            "subsystems": v.subsystems.iter().map(|s| json!({
+                "sn": s.sn,
+                "voltage": s.voltage,
+                "voltage_value": scaled16(s.voltage, 10.0, 0.0),
+                "current": s.current,
+                "current_value": scaled16(s.current, 10.0, -1000.0),
+                "cell_count": s.cell_count,
+                "frame_start": s.frame_start,
+                "cells": s.cells,
+                "cells_value": s.cells
+                    .iter()
+                    .map(|&c| scaled16(c, 1000.0, 0.0))
+                    .collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        }),
+        Item::Temperatures(t) => json!({
+            "type": "temperatures",
+            "subsystems": t.subsystems.iter().map(|s| json!({
+                "sn": s.sn,
+                "probes": s.probes,
+                "probes_value": s.probes
+                    .iter()
+                    .map(|&p| scaled8(p, 1.0, -40.0))
+                    .collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        }),
+        Item::Custom { kind, data } => json!({
+            "type": "custom",
+            "kind": kind,
+            "data": hex::encode(data),
+        }),
+    }
+}
+
+fn item_from_node(node: &Node) -> Result<Item> {
+    let kind = node.get("type")?;
+    let item = match kind.str()? {
+        "vehicle" => Item::Vehicle(realtime::Vehicle {
+            status: node.get("status")?.int()?,
+            charging: node.get("charging")?.int()?,
+            mode: node.get("mode")?.int()?,
+            speed: node.get("speed")?.int()?,
+            mileage: node.get("mileage")?.int()?,
+            voltage: node.get("voltage")?.int()?,
+            current: node.get("current")?.int()?,
+            soc: node.get("soc")?.int()?,
+            dc_status: node.get("dc_status")?.int()?,
+            gear: node.get("gear")?.int()?,
+            insulation: node.get("insulation")?.int()?,
+            accelerator: node.get("accelerator")?.int()?,
+            brake: node.get("brake")?.int()?,
+        }),
+        "motors" => Item::Motors(realtime::Motors {
+            motors: node
+                .get("motors")?
+                .elements()?
+                .iter()
+                .map(|m| {
+                    Ok(realtime::Motor {
+                        sn: m.get("sn")?.int()?,
+                        status: m.get("status")?.int()?,
+                        controller_temperature: m.get("controller_temperature")?.int()?,
+                        speed: m.get("speed")?.int()?,
+                        torque: m.get("torque")?.int()?,
+                        temperature: m.get("temperature")?.int()?,
+                        controller_voltage: m.get("controller_voltage")?.int()?,
+                        controller_current: m.get("controller_current")?.int()?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        }),
+        "fuel_cell" => Item::FuelCell(realtime::FuelCell {
+            voltage: node.get("voltage")?.int()?,
+            current: node.get("current")?.int()?,
+            consumption: node.get("consumption")?.int()?,
+            probe_temperatures: node.get("probe_temperatures")?.ints()?,
+            max_hydrogen_temperature: node.get("max_hydrogen_temperature")?.int()?,
+            max_hydrogen_temperature_probe: node.get("max_hydrogen_temperature_probe")?.int()?,
+            max_hydrogen_concentration: node.get("max_hydrogen_concentration")?.int()?,
+            max_hydrogen_concentration_sensor: node
+                .get("max_hydrogen_concentration_sensor")?
+                .int()?,
+            max_hydrogen_pressure: node.get("max_hydrogen_pressure")?.int()?,
+            max_hydrogen_pressure_sensor: node.get("max_hydrogen_pressure_sensor")?.int()?,
+            dc_status: node.get("dc_status")?.int()?,
+        }),
+        "engine" => Item::Engine(realtime::Engine {
+            status: node.get("status")?.int()?,
+            crankshaft_speed: node.get("crankshaft_speed")?.int()?,
+            fuel_consumption: node.get("fuel_consumption")?.int()?,
+        }),
+        "location" => Item::Location(realtime::Location {
+            status: node.get("status")?.int()?,
+            longitude: node.get("longitude")?.int()?,
+            latitude: node.get("latitude")?.int()?,
+        }),
+        "extremes" => Item::Extremes(realtime::Extremes {
+            max_voltage_subsys: node.get("max_voltage_subsys")?.int()?,
+            max_voltage_cell: node.get("max_voltage_cell")?.int()?,
+            max_cell_voltage: node.get("max_cell_voltage")?.int()?,
+            min_voltage_subsys: node.get("min_voltage_subsys")?.int()?,
+            min_voltage_cell: node.get("min_voltage_cell")?.int()?,
+            min_cell_voltage: node.get("min_cell_voltage")?.int()?,
+            max_temperature_subsys: node.get("max_temperature_subsys")?.int()?,
+            max_temperature_probe: node.get("max_temperature_probe")?.int()?,
+            max_temperature: node.get("max_temperature")?.int()?,
+            min_temperature_subsys: node.get("min_temperature_subsys")?.int()?,
+            min_temperature_probe: node.get("min_temperature_probe")?.int()?,
+            min_temperature: node.get("min_temperature")?.int()?,
+        }),
+        "alarm" => Item::Alarm(realtime::Alarm {
+            level: node.get("level")?.int()?,
+            flags: node.get("flags")?.int()?,
+            battery_faults: node.get("battery_faults")?.ints()?,
+            motor_faults: node.get("motor_faults")?.ints()?,
+            engine_faults: node.get("engine_faults")?.ints()?,
+            other_faults: node.get("other_faults")?.ints()?,
+        }),
+        "voltages" => Item::Voltages(realtime::Voltages {
+            subsystems: node
+                .get("subsystems")?
+                .elements()?
+                .iter()
+                .map(|s| {
+                    Ok(realtime::SubsystemVoltage {
+                        sn: s.get("sn")?.int()?,
+                        voltage: s.get("voltage")?.int()?,
+                        current: s.get("current")?.int()?,
+                        cell_count: s.get("cell_count")?.int()?,
+                        frame_start: s.get("frame_start")?.int()?,
+                        cells: s.get("cells")?.ints()?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        }),
+        "temperatures" => Item::Temperatures(realtime::Temperatures {
+            subsystems: node
+                .get("subsystems")?
+                .elements()?
+                .iter()
+                .map(|s| {
+                    Ok(realtime::SubsystemTemperature {
+                        sn: s.get("sn")?.int()?,
+                        probes: s.get("probes")?.ints()?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        }),
+        "custom" => {
+            let kind = node.get("kind")?;
+            match kind.int()? {
+                k @ 0x80..=0xFE => Item::Custom {
+                    kind: k,
+                    data: node.get("data")?.hex()?,
+                },
+                k => return Err(kind.error(format!("{:#04x} is not a custom item", k))),
+            }
+        }
+        other => return Err(kind.error(format!("unknown item type {:?}", other))),
+    };
+    Ok(item)
+}
+
+/// Value being decoded along with its path from the root, such as
+/// `body.items[2].motors[0].speed`.
+struct Node<'a> {
+    value: &'a Value,
+    path: String,
+}
+
+impl<'a> Node<'a> {
+    fn root(value: &'a Value) -> Self {
+        Self {
+            value,
+            path: String::new(),
+        }
+    }
+
+    fn error(&self, reason: impl Display) -> Error {
+        let path = if self.path.is_empty() {
+            "top level".into()
+        } else {
+            self.path.clone()
+        };
+        Error::Field {
+            path,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn object(&self) -> Result<&'a Map<String, Value>> {
+        self.value
+            .as_object()
+            .ok_or_else(|| self.error("expected an object"))
+    }
+
+    fn child(&self, name: &str, value: &'a Value) -> Self {
+        let path = if self.path.is_empty() {
+            name.into()
+        } else {
+            format!("{}.{}", self.path, name)
+        };
+        Self { value, path }
+    }
+
+    fn opt(&self, name: &str) -> Option<Self> {
+        let value = self.value.as_object()?.get(name)?;
+        Some(self.child(name, value))
+    }
+
+    fn get(&self, name: &str) -> Result<Self> {
+        match self.object()?.get(name) {
+            Some(value) => Ok(self.child(name, value)),
+            None => Err(self.child(name, &Value::Null).error("missing field")),
+        }
+    }
+
+    fn elements(&self) -> Result<Vec<Self>> {
+        let array = self
+            .value
+            .as_array()
+            .ok_or_else(|| self.error("expected an array"))?;
+        Ok(array
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Self {
+                value,
+                path: format!("{}[{}]", self.path, i),
+            })
+            .collect())
+    }
+
+    fn int<T: TryFrom<u64>>(&self) -> Result<T> {
+        self.value
+            .as_u64()
+            .ok_or_else(|| self.error("expected an unsigned integer"))
+            .and_then(|n| T::try_from(n).map_err(|_| self.error(format!("{} is out of range", n))))
+    }
+
+    fn ints<T: TryFrom<u64>>(&self) -> Result<Vec<T>> {
+        self.elements()?.iter().map(Node::int).collect()
+    }
+
+    fn str(&self) -> Result<&'a str> {
+        self.value
+            .as_str()
+            .ok_or_else(|| self.error("expected a string"))
+    }
+
+    fn hex(&self) -> Result<Vec<u8>> {
+        hex::decode(self.str()?).map_err(|e| self.error(e))
+    }
+
+    fn name<T: Copy>(&self, names: &[(&str, T)]) -> Result<T> {
+        let name = self.str()?;
+        names
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| self.error(format!("unknown name {:?}", name)))
+    }
+
+    /// Name of a command, or its byte.
+    fn command(&self) -> Result<Command> {
+        if self.value.is_number() {
+            return self.int::<u8>().map(Command::from);
+        }
+        self.name(&COMMANDS)
+    }
+
+    /// Parses `YYYY-MM-DDTHH:MM:SS`, optionally followed by `+08:00`.
+    fn time(&self) -> Result<Time> {
+        let text = self.str()?;
+        let invalid = || self.error(format!("{:?} is not an ISO-8601 time in +08:00", text));
+        let local = text.strip_suffix("+08:00").unwrap_or(text);
+        let b = local.as_bytes();
+        if b.len() != 19 || b[4] != b'-' || b[7] != b'-' || b[10] != b'T' {
+            return Err(invalid());
+        }
+        if b[13] != b':' || b[16] != b':' {
+            return Err(invalid());
+        }
+        let field = |range: core::ops::Range<usize>| -> Result<u16> {
+            let digits = &local[range];
+            if !digits.bytes().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            digits.parse().map_err(|_| invalid())
+        };
+        let year = i32::from(field(0..4)?);
+        if !(BASE_YEAR..BASE_YEAR + 100).contains(&year) {
+            return Err(self.error(format!("year {} is outside 2000 to 2099", year)));
+        }
+        let at = Time {
+            year: (year - BASE_YEAR) as u8,
+            month: field(5..7)? as u8,
+            day: field(8..10)? as u8,
+            hour: field(11..13)? as u8,
+            minute: field(14..16)? as u8,
+            second: field(17..19)? as u8,
+        };
+        at.validate().map_err(|e| self.error(e))?;
+        Ok(at)
+    }
+}
@@ -1,31 +1,93 @@
-use serde::{Deserialize, Serialize};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use serde::{Deserialize, Serialize, Serializer as _};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use error::{Error, Result};
+use crate::serde::Serializer;
+
+use error::Error;
 pub use types::Encrypt;
 pub use types::Iccid;
+pub use types::Strictness;
 pub use types::Time;
 pub use types::Vin;
 
 pub mod body;
+pub mod crypto;
+pub mod dump;
 pub mod error;
+pub mod iccid;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod keys;
 pub mod parser;
+pub mod platform;
+pub mod realtime;
+pub mod reissue;
+pub mod relay;
+pub mod serial;
+pub mod stream;
+pub mod terminal;
+pub mod time;
 pub mod types;
+pub mod view;
+pub mod vin;
 
-#[derive(Debug, Serialize_repr, Deserialize_repr)]
-#[repr(u8)]
+/// Start marker `##` of every packet.
+pub const BEGIN: u16 = 0x2323;
+
+/// Length of the header preceding the body.
+pub const HEADER_LEN: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
-    VehicleLogin = 0x01,
-    RealTimeReport = 0x02,
-    ReissueReport = 0x03,
-    VehicleLogout = 0x04,
-    PlatformLogin = 0x5,
-    PlatformLogout = 0x6,
-    HeartBeat = 0x7,
-    Time = 0x8,
+    VehicleLogin,
+    RealTimeReport,
+    ReissueReport,
+    VehicleLogout,
+    PlatformLogin,
+    PlatformLogout,
+    HeartBeat,
+    Time,
+    /// Reserved or vendor defined command, such as the platform defined
+    /// 0xC0 to 0xFE.
+    Unknown(u8),
+}
+
+impl From<u8> for Command {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => Command::VehicleLogin,
+            0x02 => Command::RealTimeReport,
+            0x03 => Command::ReissueReport,
+            0x04 => Command::VehicleLogout,
+            0x05 => Command::PlatformLogin,
+            0x06 => Command::PlatformLogout,
+            0x07 => Command::HeartBeat,
+            0x08 => Command::Time,
+            _ => Command::Unknown(value),
+        }
+    }
 }
 
-#[derive(Debug, Serialize_repr, Deserialize_repr)]
+impl From<Command> for u8 {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::VehicleLogin => 0x01,
+            Command::RealTimeReport => 0x02,
+            Command::ReissueReport => 0x03,
+            Command::VehicleLogout => 0x04,
+            Command::PlatformLogin => 0x05,
+            Command::PlatformLogout => 0x06,
+            Command::HeartBeat => 0x07,
+            Command::Time => 0x08,
+            Command::Unknown(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum Response {
     Success,
@@ -42,10 +104,79 @@ pub struct Packet {
     pub vin: Vin,
     pub encrypt: Encrypt,
     pub body_len: u16,
-    pub body: Box<dyn body::Body>,
+    pub body: body::Body,
     pub bcc: u8,
 }
 
+impl Packet {
+    /// Encodes the packet, taking the body length and check byte from the
+    /// encoded body rather than from `body_len` and `bcc`.
+    ///
+    /// Fails unless `encrypt` is [`Encrypt::None`], see
+    /// [`encode_with_cipher`](Self::encode_with_cipher).
+    pub fn encode(&self) -> error::Result<Vec<u8>> {
+        self.encode_with_cipher(None)
+    }
+
+    /// Answer to this packet with `response`, echoing its body in plain
+    /// text as platforms and terminals usually do.
+    pub fn reply(&self, response: Response) -> error::Result<Packet> {
+        let body = parser::parse_body(self.command, &self.body.encode()?)?;
+        Ok(Packet {
+            begin: BEGIN,
+            command: self.command,
+            response,
+            vin: Vin::new_unchecked(self.vin.as_str().into()),
+            encrypt: Encrypt::None,
+            body_len: 0,
+            body,
+            bcc: 0,
+        })
+    }
+
+    /// Encrypts the body with the key `keys` have for the VIN unless
+    /// `encrypt` says it is plain.
+    pub fn encode_with_keys(&self, keys: &dyn keys::KeyProvider) -> error::Result<Vec<u8>> {
+        let cipher = match self.encrypt {
+            Encrypt::None => None,
+            encrypt => keys.cipher(self.vin.as_str(), encrypt),
+        };
+        self.encode_with_cipher(cipher.as_deref())
+    }
+
+    /// Encrypts the body with `cipher` unless `encrypt` says it is plain.
+    pub fn encode_with_cipher(
+        &self,
+        cipher: Option<&dyn crypto::Cipher>,
+    ) -> error::Result<Vec<u8>> {
+        if self.body.command() != self.command {
+            return Err(Error::CommandMismatch {
+                command: self.command,
+                body: self.body.command(),
+            });
+        }
+        let mut body = self.body.encode()?;
+        if self.encrypt != Encrypt::None {
+            body = crypto::select(self.encrypt, cipher)?.encrypt(&body)?;
+        }
+        let body_len = u16::try_from(body.len()).map_err(|_| Error::Count {
+            field: "body",
+            len: body.len(),
+        })?;
+        let mut buff = Vec::with_capacity(HEADER_LEN + body.len() + 1);
+        let mut ser = Serializer::new(&mut buff);
+        ser.serialize_u16(self.begin)?;
+        ser.serialize_u8(self.command.into())?;
+        self.response.serialize(&mut ser)?;
+        self.vin.serialize(&mut ser)?;
+        self.encrypt.serialize(&mut ser)?;
+        ser.serialize_u16(body_len)?;
+        buff.extend_from_slice(&body);
+        buff.push(parser::checksum(&buff[2..]));
+        Ok(buff)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MyCommand(u8);
 
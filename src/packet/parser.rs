@@ -1,26 +1,249 @@
-use std::io::Read;
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
 
-use crate::packet::body;
+use crate::packet::body::{self, Body};
+use crate::packet::crypto::{self, Cipher};
 use crate::packet::error::{Error, Result};
-use crate::packet::types;
-use crate::packet::{Command, Header, Packet};
-use crate::serde;
+use crate::packet::keys::KeyProvider;
+use crate::packet::realtime::RealTimeReport;
+use crate::packet::{Command, Encrypt, Header, Packet, Strictness, BEGIN, HEADER_LEN};
+use crate::serde::read::{Read, SliceRead};
+use crate::serde::{self, Positioned, Span};
 
-pub fn parse_header<R: Read>(de: &mut serde::Deserializer<R>) -> Result<Header> {
-    let h: Header = ::serde::Deserialize::deserialize(de).unwrap();
+pub fn parse_header<'de, R: Read<'de>>(de: &mut serde::Deserializer<R>) -> Result<Header> {
+    let h: Header = ::serde::Deserialize::deserialize(de)?;
+    if h.begin != BEGIN {
+        return Err(Error::Begin(h.begin));
+    }
     Ok(h)
 }
 
-pub fn pares_hex(text: &str) -> Result<()> {
+/// Offset of the VIN in the header.
+pub(crate) const VIN_OFFSET: usize = 4;
+
+/// Validation applied while parsing.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub vin: Strictness,
+    /// Applied to the ICCID of vehicle logins.
+    pub iccid: Strictness,
+}
+
+pub fn pares_hex(text: &str) -> Result<Packet> {
     let data = hex::decode(text)?;
     parse_bytes(data.as_slice())
 }
 
-pub fn parse_bytes(data: &[u8]) -> Result<()> {
-    let mut de = serde::Deserializer::new(data);
-    let header: Header = ::serde::Deserialize::deserialize(&mut de)?;
-    println!("{:?}", header);
-    let body = body::VehicleLogin::deserialize(&mut de)?;
-    println!("{:?}", body);
+pub fn parse_bytes(data: &[u8]) -> Result<Packet> {
+    parse_bytes_with(data, &Options::default())
+}
+
+/// Fails on encrypted bodies, see [`parse_bytes_with_cipher`].
+pub fn parse_bytes_with(data: &[u8], options: &Options) -> Result<Packet> {
+    parse_bytes_with_cipher(data, options, None)
+}
+
+/// Decrypts the body with `cipher` unless the header says it is plain.
+pub fn parse_bytes_with_cipher(
+    data: &[u8],
+    options: &Options,
+    cipher: Option<&dyn Cipher>,
+) -> Result<Packet> {
+    parse_frame(
+        data,
+        options,
+        |header, body| decrypt_body(header.encrypt, body, cipher),
+        None,
+    )
+}
+
+/// Decrypts the body with the key `keys` have for the VIN unless the header
+/// says it is plain.
+pub fn parse_bytes_with_keys(
+    data: &[u8],
+    options: &Options,
+    keys: &dyn KeyProvider,
+) -> Result<Packet> {
+    let decrypt = |header: &Header, body| {
+        let cipher = match header.encrypt {
+            Encrypt::None => None,
+            encrypt => keys.cipher(header.vin.as_str(), encrypt),
+        };
+        decrypt_body(header.encrypt, body, cipher.as_deref())
+    };
+    parse_frame(data, options, decrypt, None)
+}
+
+/// Parses a plain packet, recording the [`Span`] of every field read before
+/// decoding finished or failed.
+pub fn annotate(data: &[u8], options: &Options) -> (Vec<Span>, Result<Packet>) {
+    let mut spans = Vec::new();
+    let decrypt = |header: &Header, body| decrypt_body(header.encrypt, body, None);
+    let packet = parse_frame(data, options, decrypt, Some(&mut spans));
+    (spans, packet)
+}
+
+fn parse_frame<'a, F>(
+    data: &'a [u8],
+    options: &Options,
+    decrypt: F,
+    mut spans: Option<&mut Vec<Span>>,
+) -> Result<Packet>
+where
+    F: FnOnce(&Header, &'a [u8]) -> Result<Cow<'a, [u8]>>,
+{
+    let mut de = serde::Deserializer::from_slice(data);
+    if spans.is_some() {
+        de = de.annotated();
+    }
+    let frame = parse_envelope(&mut de, data, options);
+    if let Some(spans) = spans.as_deref_mut() {
+        spans.append(&mut de.take_spans());
+    }
+    let (header, command, body_data, bcc) = frame?;
+
+    let body_data = decrypt(&header, body_data)?;
+    let mut de = serde::Deserializer::from_slice(&body_data).with_offset(HEADER_LEN);
+    if spans.is_some() {
+        de = de.annotated();
+    }
+    let body = decode_body(command, &mut de, options);
+    if let Some(spans) = spans {
+        spans.append(&mut de.take_spans());
+        spans.sort_by_key(|span| span.offset);
+    }
+    let body = body?;
+    Ok(Packet {
+        begin: header.begin,
+        command,
+        response: header.response,
+        vin: header.vin,
+        encrypt: header.encrypt,
+        body_len: header.body_len,
+        body,
+        bcc,
+    })
+}
+
+/// Decodes everything around the body, which is left undecoded.
+fn parse_envelope<'a>(
+    de: &mut serde::Deserializer<SliceRead<'a>>,
+    data: &[u8],
+    options: &Options,
+) -> Result<(Header, Command, &'a [u8], u8)> {
+    let mut header = parse_header(de)?;
+    header
+        .vin
+        .check(options.vin)
+        .map_err(|e| Error::from(e).positioned(VIN_OFFSET, "Header.vin".into()))?;
+    let command = Command::from(header.command.0);
+    let body_data = de.field("body", |de| de.borrow_bytes(header.body_len as usize))?;
+    let bcc = de.field("bcc", |de| de.deserialize_u8())?;
+    if !de.remaining().is_empty() {
+        return Err(Error::TrailingBytes(de.remaining().len()));
+    }
+    verify_checksum(data)?;
+    Ok((header, command, body_data, bcc))
+}
+
+/// Decodes the body of a `command` packet, which must span all of `data`.
+///
+/// Offsets in errors count from the start of the packet.
+pub fn parse_body(command: Command, data: &[u8]) -> Result<Body> {
+    parse_body_with(command, data, &Options::default())
+}
+
+pub fn parse_body_with(command: Command, data: &[u8], options: &Options) -> Result<Body> {
+    let mut de = serde::Deserializer::from_slice(data).with_offset(HEADER_LEN);
+    decode_body(command, &mut de, options)
+}
+
+fn decode_body(
+    command: Command,
+    de: &mut serde::Deserializer<SliceRead>,
+    options: &Options,
+) -> Result<Body> {
+    let body = match command {
+        Command::VehicleLogin => Body::VehicleLogin(de.field("VehicleLogin", |de| {
+            body::VehicleLogin::deserialize_with(de, options)
+        })?),
+        Command::RealTimeReport => {
+            Body::RealTimeReport(de.field("RealTimeReport", RealTimeReport::deserialize)?)
+        }
+        Command::ReissueReport => {
+            Body::ReissueReport(de.field("ReissueReport", RealTimeReport::deserialize)?)
+        }
+        Command::VehicleLogout => {
+            Body::VehicleLogout(de.field("VehicleLogout", body::VehicleLogout::deserialize)?)
+        }
+        Command::PlatformLogin => {
+            Body::PlatformLogin(de.field("PlatformLogin", body::PlatformLogin::deserialize)?)
+        }
+        Command::PlatformLogout => {
+            Body::PlatformLogout(de.field("PlatformLogout", body::PlatformLogout::deserialize)?)
+        }
+        Command::HeartBeat => Body::HeartBeat(body::Raw {
+            data: de.field("HeartBeat", |de| de.read_bytes(de.remaining().len()))?,
+        }),
+        Command::Time => Body::Time(body::Raw {
+            data: de.field("Time", |de| de.read_bytes(de.remaining().len()))?,
+        }),
+        Command::Unknown(command) => Body::Unknown {
+            command,
+            data: de.field("Unknown", |de| de.read_bytes(de.remaining().len()))?,
+        },
+    };
+    ensure_consumed(de)?;
+    Ok(body)
+}
+
+/// Plain text of a body encrypted according to `encrypt`.
+pub fn decrypt_body<'a>(
+    encrypt: Encrypt,
+    data: &'a [u8],
+    cipher: Option<&dyn Cipher>,
+) -> Result<Cow<'a, [u8]>> {
+    if encrypt == Encrypt::None {
+        return Ok(Cow::Borrowed(data));
+    }
+    crypto::select(encrypt, cipher)
+        .and_then(|cipher| cipher.decrypt(data))
+        .map(Cow::Owned)
+        .map_err(|e| Error::from(e).positioned(HEADER_LEN, "body".into()))
+}
+
+/// Length of the packet at the start of `data`, or `None` while its header
+/// is incomplete.
+pub fn frame_len(data: &[u8]) -> Option<usize> {
+    let body_len = data.get(HEADER_LEN - 2..HEADER_LEN)?;
+    Some(HEADER_LEN + u16::from_be_bytes([body_len[0], body_len[1]]) as usize + 1)
+}
+
+/// Block check character: XOR of everything between the start marker and
+/// the check byte itself.
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, x| acc ^ x)
+}
+
+/// Verifies the trailing check byte of a complete packet.
+pub fn verify_checksum(packet: &[u8]) -> Result<()> {
+    if packet.len() < 3 {
+        return Err(serde::Error::Eof.into());
+    }
+    let (data, bcc) = packet.split_at(packet.len() - 1);
+    let expected = checksum(&data[2..]);
+    if expected != bcc[0] {
+        return Err(Error::Checksum {
+            expected,
+            actual: bcc[0],
+        });
+    }
     Ok(())
 }
+
+fn ensure_consumed(de: &serde::Deserializer<SliceRead>) -> Result<()> {
+    match de.remaining().len() {
+        0 => Ok(()),
+        n => Err(Error::TrailingBytes(n)),
+    }
+}
@@ -0,0 +1,265 @@
+//! Platform side protocol state of one terminal connection.
+//!
+//! [`PlatformSession`] does no IO: the server decodes frames, hands them to
+//! [`handle`](PlatformSession::handle) and carries out the returned
+//! [`Action`]s, and calls [`poll`](PlatformSession::poll) by the
+//! [`deadline`](PlatformSession::deadline) to notice idle connections. Times
+//! are durations since the Unix epoch, as from `SystemTime::now()`.
+//!
+//! ```
+//! use core::time::Duration;
+//! use vin::packet::parser;
+//! use vin::packet::platform::{Action, Config, PlatformSession};
+//!
+//! let login = parser::pares_hex(
+//!     "232301fe4c5a595442474257364a3130313431393401001e120a1e14233600fd\
+//!      383938363034303231303137303031373937373901005c",
+//! )
+//! .unwrap();
+//! let now = Duration::from_secs(1_540_902_954);
+//! let mut session = PlatformSession::new(Config::default(), now);
+//! let actions = session.handle(login, now);
+//! assert!(matches!(actions[0], Action::Emit(_)));
+//! assert!(matches!(actions[1], Action::Send(_)));
+//! assert_eq!(session.vin(), Some("LZYTBGBW6J1014194"));
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::packet::body::{self, Body};
+use crate::packet::serial::Issue;
+use crate::packet::{Command, Packet, Response, Time};
+use crate::serde::Serializer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Silence after which the connection is closed.
+    pub idle_timeout: Duration,
+    /// Answer real time and reissue reports, which the standard leaves to
+    /// the platform.
+    pub respond_reports: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(180),
+            respond_reports: false,
+        }
+    }
+}
+
+/// What the server should do after a packet or a poll.
+#[derive(Debug)]
+pub enum Action {
+    /// Send the packet to the terminal.
+    Send(Packet),
+    /// Pass the packet on for storage or forwarding.
+    Emit(Packet),
+    /// Close the connection; the session ignores everything after.
+    Close(Close),
+}
+
+/// Why a session closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Close {
+    Logout,
+    Idle,
+    Violation(Violation),
+}
+
+/// Break of the protocol by the terminal.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    #[error("{0:?} before vehicle login")]
+    NotLoggedIn(Command),
+
+    #[error("VIN changed from {expected} to {actual}")]
+    VinChanged { expected: String, actual: String },
+
+    #[error("{0:?} is not sent by terminals")]
+    UnexpectedCommand(Command),
+
+    #[error(transparent)]
+    Serial(#[from] Issue),
+
+    #[error("platform login of {0:?} refused")]
+    Credentials(String),
+
+    #[error("cannot answer: {0}")]
+    Encode(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    AwaitingLogin,
+    LoggedIn { sn: u16 },
+    Closed,
+}
+
+#[derive(Debug)]
+pub struct PlatformSession {
+    config: Config,
+    state: State,
+    vin: Option<String>,
+    last_seen: Duration,
+}
+
+impl PlatformSession {
+    /// Session of a connection accepted at `now`.
+    pub fn new(config: Config, now: Duration) -> Self {
+        Self {
+            config,
+            state: State::AwaitingLogin,
+            vin: None,
+            last_seen: now,
+        }
+    }
+
+    /// VIN of the logged in vehicle.
+    pub fn vin(&self) -> Option<&str> {
+        self.vin.as_deref()
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        matches!(self.state, State::LoggedIn { .. })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == State::Closed
+    }
+
+    /// Time by which [`poll`](Self::poll) should be called, none once
+    /// closed.
+    pub fn deadline(&self) -> Option<Duration> {
+        match self.state {
+            State::Closed => None,
+            _ => Some(self.last_seen + self.config.idle_timeout),
+        }
+    }
+
+    /// Closes the session if it has been idle for too long at `now`.
+    pub fn poll(&mut self, now: Duration) -> Vec<Action> {
+        match self.deadline() {
+            Some(deadline) if now >= deadline => {
+                self.state = State::Closed;
+                vec![Action::Close(Close::Idle)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Handles a packet received at `now`.
+    pub fn handle(&mut self, packet: Packet, now: Duration) -> Vec<Action> {
+        if self.state == State::Closed {
+            return Vec::new();
+        }
+        self.last_seen = now;
+        if packet.response != Response::Command {
+            // answers to platform commands need nothing further
+            return Vec::new();
+        }
+
+        let sn = match (self.state, &packet.body) {
+            (_, Body::VehicleLogin(login)) => {
+                if let Some(vin) = self.changed_vin(&packet) {
+                    return self.violate(&packet, vin);
+                }
+                self.vin = Some(packet.vin.as_str().into());
+                self.state = State::LoggedIn { sn: login.sn };
+                return self.accept(packet, true);
+            }
+            (State::LoggedIn { sn }, _) => sn,
+            _ => return self.violate(&packet, Violation::NotLoggedIn(packet.command)),
+        };
+        if let Some(vin) = self.changed_vin(&packet) {
+            return self.violate(&packet, vin);
+        }
+
+        match &packet.body {
+            Body::RealTimeReport(_) | Body::ReissueReport(_) => {
+                let respond = self.config.respond_reports;
+                self.accept(packet, respond)
+            }
+            Body::HeartBeat(_) => self.respond(&packet, Response::Success),
+            Body::Time(_) => self.time(&packet, now),
+            Body::VehicleLogout(logout) if logout.sn != sn => {
+                let issue = Issue::Mismatch {
+                    login: Some(sn),
+                    actual: logout.sn,
+                };
+                self.violate(&packet, issue.into())
+            }
+            Body::VehicleLogout(_) => {
+                let mut actions = self.accept(packet, true);
+                self.state = State::Closed;
+                actions.push(Action::Close(Close::Logout));
+                actions
+            }
+            _ => self.violate(&packet, Violation::UnexpectedCommand(packet.command)),
+        }
+    }
+
+    fn changed_vin(&self, packet: &Packet) -> Option<Violation> {
+        match &self.vin {
+            Some(vin) if vin != packet.vin.as_str() => Some(Violation::VinChanged {
+                expected: vin.clone(),
+                actual: packet.vin.as_str().into(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Passes `packet` on, answering it with success first if `respond`.
+    fn accept(&mut self, packet: Packet, respond: bool) -> Vec<Action> {
+        let mut actions = if respond {
+            self.respond(&packet, Response::Success)
+        } else {
+            Vec::new()
+        };
+        if !self.is_closed() {
+            actions.insert(0, Action::Emit(packet));
+        }
+        actions
+    }
+
+    fn respond(&mut self, packet: &Packet, response: Response) -> Vec<Action> {
+        match packet.reply(response) {
+            Ok(reply) => vec![Action::Send(reply)],
+            Err(err) => self.close(Violation::Encode(err.to_string())),
+        }
+    }
+
+    /// Answers a time request with the platform time.
+    fn time(&mut self, packet: &Packet, now: Duration) -> Vec<Action> {
+        let mut data = Vec::new();
+        let at = Time::from_unix(now.as_secs() as i64);
+        if let Err(err) = ::serde::Serialize::serialize(&at, &mut Serializer::new(&mut data)) {
+            return self.close(Violation::Encode(err.to_string()));
+        }
+        match packet.reply(Response::Success) {
+            Ok(mut reply) => {
+                reply.body = Body::Time(body::Raw { data });
+                vec![Action::Send(reply)]
+            }
+            Err(err) => self.close(Violation::Encode(err.to_string())),
+        }
+    }
+
+    /// Refuses `packet` and closes the session.
+    fn violate(&mut self, packet: &Packet, violation: Violation) -> Vec<Action> {
+        let mut actions = self.respond(packet, Response::Fail);
+        if !self.is_closed() {
+            actions.extend(self.close(violation));
+        }
+        actions
+    }
+
+    fn close(&mut self, violation: Violation) -> Vec<Action> {
+        self.state = State::Closed;
+        vec![Action::Close(Close::Violation(violation))]
+    }
+}
@@ -0,0 +1,494 @@
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use serde::{Deserialize, Serialize, Serializer as _};
+
+use crate::packet::error::{Error, Result};
+use crate::packet::types;
+use crate::serde::read::{Read, SliceRead};
+use crate::serde::write::Write;
+use crate::serde::{Deserializer, Serializer};
+
+/// Real time (0x02) and reissue (0x03) report.
+#[derive(Debug)]
+pub struct RealTimeReport {
+    pub at: types::Time,
+    pub items: Vec<Item>,
+}
+
+impl RealTimeReport {
+    /// Decodes the report, consuming all the remaining input.
+    pub fn deserialize(de: &mut Deserializer<SliceRead>) -> Result<Self> {
+        let at: types::Time = de.field("at", |de| serde::Deserialize::deserialize(de))?;
+        let mut items = Vec::new();
+        de.field("items", |de| {
+            while !de.remaining().is_empty() {
+                let item = de.element(items.len(), |de| {
+                    let kind = de.field("kind", |de| de.deserialize_u8())?;
+                    Item::deserialize(kind, de)
+                })?;
+                items.push(item);
+            }
+            Ok::<_, Error>(())
+        })?;
+        Ok(Self { at, items })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        self.at.serialize(&mut *ser)?;
+        for item in self.items.iter() {
+            ser.serialize_u8(item.kind())?;
+            item.serialize(ser)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that `len` elements of `field` fit its count of type `T`.
+fn count<T: TryFrom<usize>>(field: &'static str, len: usize) -> Result<T> {
+    T::try_from(len).map_err(|_| Error::Count { field, len })
+}
+
+pub const VEHICLE: u8 = 0x01;
+pub const MOTORS: u8 = 0x02;
+pub const FUEL_CELL: u8 = 0x03;
+pub const ENGINE: u8 = 0x04;
+pub const LOCATION: u8 = 0x05;
+pub const EXTREMES: u8 = 0x06;
+pub const ALARM: u8 = 0x07;
+pub const VOLTAGES: u8 = 0x08;
+pub const TEMPERATURES: u8 = 0x09;
+
+#[derive(Debug)]
+pub enum Item {
+    Vehicle(Vehicle),
+    Motors(Motors),
+    FuelCell(FuelCell),
+    Engine(Engine),
+    Location(Location),
+    Extremes(Extremes),
+    Alarm(Alarm),
+    Voltages(Voltages),
+    Temperatures(Temperatures),
+    /// Vendor defined item in `0x80..=0xFE`.
+    Custom {
+        kind: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl Item {
+    /// Decodes the item following its `kind` byte.
+    pub fn deserialize<'de, R: Read<'de>>(kind: u8, de: &mut Deserializer<R>) -> Result<Self> {
+        let item = match kind {
+            VEHICLE => Item::Vehicle(serde::Deserialize::deserialize(&mut *de)?),
+            MOTORS => Item::Motors(Motors::deserialize(de)?),
+            FUEL_CELL => Item::FuelCell(FuelCell::deserialize(de)?),
+            ENGINE => Item::Engine(serde::Deserialize::deserialize(&mut *de)?),
+            LOCATION => Item::Location(serde::Deserialize::deserialize(&mut *de)?),
+            EXTREMES => Item::Extremes(serde::Deserialize::deserialize(&mut *de)?),
+            ALARM => Item::Alarm(Alarm::deserialize(de)?),
+            VOLTAGES => Item::Voltages(Voltages::deserialize(de)?),
+            TEMPERATURES => Item::Temperatures(Temperatures::deserialize(de)?),
+            0x80..=0xFE => {
+                let len = de.field("len", |de| de.deserialize_u16())?;
+                let data = de.field("data", |de| de.read_bytes(len as usize))?;
+                Item::Custom { kind, data }
+            }
+            _ => return Err(Error::UnknownItem(kind)),
+        };
+        Ok(item)
+    }
+
+    /// Encodes the item without its `kind` byte.
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        match self {
+            Item::Vehicle(item) => item.serialize(&mut *ser)?,
+            Item::Motors(item) => item.serialize(ser)?,
+            Item::FuelCell(item) => item.serialize(ser)?,
+            Item::Engine(item) => item.serialize(&mut *ser)?,
+            Item::Location(item) => item.serialize(&mut *ser)?,
+            Item::Extremes(item) => item.serialize(&mut *ser)?,
+            Item::Alarm(item) => item.serialize(ser)?,
+            Item::Voltages(item) => item.serialize(ser)?,
+            Item::Temperatures(item) => item.serialize(ser)?,
+            Item::Custom { data, .. } => {
+                ser.serialize_u16(count("data", data.len())?)?;
+                ser.serialize_bytes(data)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn kind(&self) -> u8 {
+        match self {
+            Item::Vehicle(_) => VEHICLE,
+            Item::Motors(_) => MOTORS,
+            Item::FuelCell(_) => FUEL_CELL,
+            Item::Engine(_) => ENGINE,
+            Item::Location(_) => LOCATION,
+            Item::Extremes(_) => EXTREMES,
+            Item::Alarm(_) => ALARM,
+            Item::Voltages(_) => VOLTAGES,
+            Item::Temperatures(_) => TEMPERATURES,
+            Item::Custom { kind, .. } => *kind,
+        }
+    }
+}
+
+/// Whole vehicle data, values are raw as transmitted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Vehicle {
+    pub status: u8,
+    pub charging: u8,
+    pub mode: u8,
+    /// 0.1 km/h
+    pub speed: u16,
+    /// 0.1 km
+    pub mileage: u32,
+    /// 0.1 V
+    pub voltage: u16,
+    /// 0.1 A, offset -1000 A
+    pub current: u16,
+    /// %
+    pub soc: u8,
+    pub dc_status: u8,
+    pub gear: u8,
+    /// kΩ
+    pub insulation: u16,
+    /// %
+    pub accelerator: u8,
+    /// %
+    pub brake: u8,
+}
+
+#[derive(Debug)]
+pub struct Motors {
+    pub motors: Vec<Motor>,
+}
+
+impl Motors {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        let num = de.field("num", |de| de.deserialize_u8())?;
+        let motors = de.field("motors", |de| {
+            (0..num as usize)
+                .map(|i| de.element(i, |de| serde::Deserialize::deserialize(de)))
+                .collect::<crate::serde::Result<Vec<Motor>>>()
+        })?;
+        Ok(Self { motors })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u8(count("motors", self.motors.len())?)?;
+        for motor in self.motors.iter() {
+            motor.serialize(&mut *ser)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Motor {
+    pub sn: u8,
+    pub status: u8,
+    /// ℃, offset -40
+    pub controller_temperature: u8,
+    /// r/min, offset -20000
+    pub speed: u16,
+    /// 0.1 N·m, offset -2000 N·m
+    pub torque: u16,
+    /// ℃, offset -40
+    pub temperature: u8,
+    /// 0.1 V
+    pub controller_voltage: u16,
+    /// 0.1 A, offset -1000 A
+    pub controller_current: u16,
+}
+
+#[derive(Debug)]
+pub struct FuelCell {
+    /// 0.1 V
+    pub voltage: u16,
+    /// 0.1 A
+    pub current: u16,
+    /// 0.01 kg/100km
+    pub consumption: u16,
+    /// ℃, offset -40
+    pub probe_temperatures: Vec<u8>,
+    /// 0.1 ℃, offset -40 ℃
+    pub max_hydrogen_temperature: u16,
+    pub max_hydrogen_temperature_probe: u8,
+    /// mg/kg
+    pub max_hydrogen_concentration: u16,
+    pub max_hydrogen_concentration_sensor: u8,
+    /// 0.1 MPa
+    pub max_hydrogen_pressure: u16,
+    pub max_hydrogen_pressure_sensor: u8,
+    pub dc_status: u8,
+}
+
+impl FuelCell {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        let voltage = de.field("voltage", |de| de.deserialize_u16())?;
+        let current = de.field("current", |de| de.deserialize_u16())?;
+        let consumption = de.field("consumption", |de| de.deserialize_u16())?;
+        let probe_num = de.field("probe_num", |de| de.deserialize_u16())?;
+        let probe_temperatures =
+            de.field("probe_temperatures", |de| de.read_bytes(probe_num as usize))?;
+        Ok(Self {
+            voltage,
+            current,
+            consumption,
+            probe_temperatures,
+            max_hydrogen_temperature: de
+                .field("max_hydrogen_temperature", |de| de.deserialize_u16())?,
+            max_hydrogen_temperature_probe: de
+                .field("max_hydrogen_temperature_probe", |de| de.deserialize_u8())?,
+            max_hydrogen_concentration: de
+                .field("max_hydrogen_concentration", |de| de.deserialize_u16())?,
+            max_hydrogen_concentration_sensor: de
+                .field("max_hydrogen_concentration_sensor", |de| {
+                    de.deserialize_u8()
+                })?,
+            max_hydrogen_pressure: de.field("max_hydrogen_pressure", |de| de.deserialize_u16())?,
+            max_hydrogen_pressure_sensor: de
+                .field("max_hydrogen_pressure_sensor", |de| de.deserialize_u8())?,
+            dc_status: de.field("dc_status", |de| de.deserialize_u8())?,
+        })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u16(self.voltage)?;
+        ser.serialize_u16(self.current)?;
+        ser.serialize_u16(self.consumption)?;
+        let probe_num = count("probe_temperatures", self.probe_temperatures.len())?;
+        ser.serialize_u16(probe_num)?;
+        ser.serialize_bytes(&self.probe_temperatures)?;
+        ser.serialize_u16(self.max_hydrogen_temperature)?;
+        ser.serialize_u8(self.max_hydrogen_temperature_probe)?;
+        ser.serialize_u16(self.max_hydrogen_concentration)?;
+        ser.serialize_u8(self.max_hydrogen_concentration_sensor)?;
+        ser.serialize_u16(self.max_hydrogen_pressure)?;
+        ser.serialize_u8(self.max_hydrogen_pressure_sensor)?;
+        ser.serialize_u8(self.dc_status)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Engine {
+    pub status: u8,
+    /// r/min
+    pub crankshaft_speed: u16,
+    /// 0.01 L/100km
+    pub fuel_consumption: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Location {
+    /// bit 0: invalid fix, bit 1: south latitude, bit 2: west longitude
+    pub status: u8,
+    /// 0.000001 °
+    pub longitude: u32,
+    /// 0.000001 °
+    pub latitude: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Extremes {
+    pub max_voltage_subsys: u8,
+    pub max_voltage_cell: u8,
+    /// 0.001 V
+    pub max_cell_voltage: u16,
+    pub min_voltage_subsys: u8,
+    pub min_voltage_cell: u8,
+    /// 0.001 V
+    pub min_cell_voltage: u16,
+    pub max_temperature_subsys: u8,
+    pub max_temperature_probe: u8,
+    /// ℃, offset -40
+    pub max_temperature: u8,
+    pub min_temperature_subsys: u8,
+    pub min_temperature_probe: u8,
+    /// ℃, offset -40
+    pub min_temperature: u8,
+}
+
+#[derive(Debug)]
+pub struct Alarm {
+    pub level: u8,
+    pub flags: u32,
+    pub battery_faults: Vec<u32>,
+    pub motor_faults: Vec<u32>,
+    pub engine_faults: Vec<u32>,
+    pub other_faults: Vec<u32>,
+}
+
+impl Alarm {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        Ok(Self {
+            level: de.field("level", |de| de.deserialize_u8())?,
+            flags: de.field("flags", |de| de.deserialize_u32())?,
+            battery_faults: de.field("battery_faults", deserialize_faults)?,
+            motor_faults: de.field("motor_faults", deserialize_faults)?,
+            engine_faults: de.field("engine_faults", deserialize_faults)?,
+            other_faults: de.field("other_faults", deserialize_faults)?,
+        })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u8(self.level)?;
+        ser.serialize_u32(self.flags)?;
+        serialize_faults(ser, "battery_faults", &self.battery_faults)?;
+        serialize_faults(ser, "motor_faults", &self.motor_faults)?;
+        serialize_faults(ser, "engine_faults", &self.engine_faults)?;
+        serialize_faults(ser, "other_faults", &self.other_faults)
+    }
+}
+
+fn deserialize_faults<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Vec<u32>> {
+    let num = de.deserialize_u8()?;
+    let mut faults = Vec::with_capacity(num as usize);
+    for i in 0..num as usize {
+        faults.push(de.element(i, |de| de.deserialize_u32())?);
+    }
+    Ok(faults)
+}
+
+fn serialize_faults<W: Write>(
+    ser: &mut Serializer<W>,
+    field: &'static str,
+    faults: &[u32],
+) -> Result<()> {
+    ser.serialize_u8(count(field, faults.len())?)?;
+    for &fault in faults {
+        ser.serialize_u32(fault)?;
+    }
+    Ok(())
+}
+
+/// Rechargeable energy storage subsystem voltages.
+#[derive(Debug)]
+pub struct Voltages {
+    pub subsystems: Vec<SubsystemVoltage>,
+}
+
+impl Voltages {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        let num = de.field("num", |de| de.deserialize_u8())?;
+        let subsystems = de.field("subsystems", |de| {
+            (0..num as usize)
+                .map(|i| de.element(i, SubsystemVoltage::deserialize))
+                .collect::<Result<Vec<_>>>()
+        })?;
+        Ok(Self { subsystems })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u8(count("subsystems", self.subsystems.len())?)?;
+        for subsystem in self.subsystems.iter() {
+            subsystem.serialize(ser)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SubsystemVoltage {
+    pub sn: u8,
+    /// 0.1 V
+    pub voltage: u16,
+    /// 0.1 A, offset -1000 A
+    pub current: u16,
+    pub cell_count: u16,
+    /// Number of the first cell in this frame, starting from 1.
+    pub frame_start: u16,
+    /// 0.001 V
+    pub cells: Vec<u16>,
+}
+
+impl SubsystemVoltage {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        let sn = de.field("sn", |de| de.deserialize_u8())?;
+        let voltage = de.field("voltage", |de| de.deserialize_u16())?;
+        let current = de.field("current", |de| de.deserialize_u16())?;
+        let cell_count = de.field("cell_count", |de| de.deserialize_u16())?;
+        let frame_start = de.field("frame_start", |de| de.deserialize_u16())?;
+        let frame_cells = de.field("frame_cells", |de| de.deserialize_u8())?;
+        let cells = de.field("cells", |de| {
+            (0..frame_cells as usize)
+                .map(|i| de.element(i, |de| de.deserialize_u16()))
+                .collect::<crate::serde::Result<Vec<_>>>()
+        })?;
+        Ok(Self {
+            sn,
+            voltage,
+            current,
+            cell_count,
+            frame_start,
+            cells,
+        })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u8(self.sn)?;
+        ser.serialize_u16(self.voltage)?;
+        ser.serialize_u16(self.current)?;
+        ser.serialize_u16(self.cell_count)?;
+        ser.serialize_u16(self.frame_start)?;
+        ser.serialize_u8(count("cells", self.cells.len())?)?;
+        for &cell in self.cells.iter() {
+            ser.serialize_u16(cell)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rechargeable energy storage subsystem temperatures.
+#[derive(Debug)]
+pub struct Temperatures {
+    pub subsystems: Vec<SubsystemTemperature>,
+}
+
+impl Temperatures {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        let num = de.field("num", |de| de.deserialize_u8())?;
+        let subsystems = de.field("subsystems", |de| {
+            (0..num as usize)
+                .map(|i| de.element(i, SubsystemTemperature::deserialize))
+                .collect::<Result<Vec<_>>>()
+        })?;
+        Ok(Self { subsystems })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u8(count("subsystems", self.subsystems.len())?)?;
+        for subsystem in self.subsystems.iter() {
+            subsystem.serialize(ser)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SubsystemTemperature {
+    pub sn: u8,
+    /// ℃, offset -40
+    pub probes: Vec<u8>,
+}
+
+impl SubsystemTemperature {
+    pub fn deserialize<'de, R: Read<'de>>(de: &mut Deserializer<R>) -> Result<Self> {
+        let sn = de.field("sn", |de| de.deserialize_u8())?;
+        let probe_num = de.field("probe_num", |de| de.deserialize_u16())?;
+        let probes = de.field("probes", |de| de.read_bytes(probe_num as usize))?;
+        Ok(Self { sn, probes })
+    }
+
+    pub fn serialize<W: Write>(&self, ser: &mut Serializer<W>) -> Result<()> {
+        ser.serialize_u8(self.sn)?;
+        ser.serialize_u16(count("probes", self.probes.len())?)?;
+        ser.serialize_bytes(&self.probes)?;
+        Ok(())
+    }
+}
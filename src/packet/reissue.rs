@@ -0,0 +1,363 @@
+//! Frames that could not be delivered, kept for reissue reports.
+//!
+//! A [`ReissueStore`] keeps encoded frames keyed by VIN and collection time
+//! and gives them back oldest first, so that the
+//! [`TerminalSession`](crate::packet::terminal::TerminalSession) and the
+//! [`Relay`](crate::packet::relay::Relay) reissue in chronological order no
+//! matter in which order the frames were kept. Frames of the same time
+//! come in the order they were kept.
+//!
+//! [`MemoryStore`] is lost with the process; [`FileStore`] (feature `std`)
+//! appends to a file and finds its frames again after a restart.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::packet::{Encrypt, Time, HEADER_LEN};
+
+/// Time the standard requires undelivered data to be kept.
+pub const RETENTION: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Period of the rate limits of replays.
+pub const PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("reissue store: {0}")]
+    Io(String),
+
+    #[error("reissue store is corrupt at offset {0}")]
+    Corrupt(u64),
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(alloc::string::ToString::to_string(&err))
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+pub trait ReissueStore: core::fmt::Debug {
+    /// Keeps `frame` of `vin` collected `at`.
+    fn push(&mut self, vin: &str, at: Time, frame: Vec<u8>) -> Result<()>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes and returns up to `max` frames, the oldest first.
+    fn take(&mut self, max: usize) -> Result<Vec<Vec<u8>>>;
+
+    /// Drops the frames collected before `cutoff`, returning how many.
+    fn expire(&mut self, cutoff: Time) -> Result<usize>;
+
+    /// Drops the frames older than `retention` at `now` and takes the next
+    /// `rate` frames of a [`PERIOD`], or all of them if `rate` is 0.
+    fn replay(&mut self, now: Duration, retention: Duration, rate: u32) -> Result<Vec<Vec<u8>>> {
+        if let Some(cutoff) = cutoff(now, retention) {
+            self.expire(cutoff)?;
+        }
+        self.take(if rate == 0 { usize::MAX } else { rate as usize })
+    }
+}
+
+/// Collection time before which frames are dropped at `now`, unless it is
+/// before 2000 and cannot be represented.
+pub fn cutoff(now: Duration, retention: Duration) -> Option<Time> {
+    let secs = now.checked_sub(retention)?.as_secs() as i64;
+    let at = Time::from_unix(secs);
+    if at.to_unix() == secs {
+        Some(at)
+    } else {
+        None
+    }
+}
+
+/// Collection time in a plain frame, which every body with a time starts
+/// with.
+pub fn collection_time(frame: &[u8]) -> Option<Time> {
+    if frame.get(HEADER_LEN - 3) != Some(&(Encrypt::None as u8)) {
+        return None;
+    }
+    frame.get(HEADER_LEN..HEADER_LEN + 6).map(time_from_bytes)
+}
+
+fn time_from_bytes(bytes: &[u8]) -> Time {
+    Time {
+        year: bytes[0],
+        month: bytes[1],
+        day: bytes[2],
+        hour: bytes[3],
+        minute: bytes[4],
+        second: bytes[5],
+    }
+}
+
+/// Collection time as the seconds ordering it, the number of the frame in
+/// the order they were kept and VIN.
+type Key = (i64, u64, String);
+
+/// Frames in memory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    frames: BTreeMap<Key, (Time, Vec<u8>)>,
+    next: u64,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Key of the next frame kept.
+    fn key(&mut self, vin: &str, at: Time) -> Key {
+        self.next += 1;
+        (at.to_unix(), self.next - 1, vin.into())
+    }
+
+    fn take_entries(&mut self, max: usize) -> Vec<(Key, Time, Vec<u8>)> {
+        let mut taken = Vec::new();
+        while taken.len() < max {
+            match self.frames.pop_first() {
+                Some((key, (at, frame))) => taken.push((key, at, frame)),
+                None => break,
+            }
+        }
+        taken
+    }
+
+    fn expire_entries(&mut self, cutoff: Time) -> Vec<(Key, Time)> {
+        let kept = self.frames.split_off(&(cutoff.to_unix(), 0, String::new()));
+        let expired = core::mem::replace(&mut self.frames, kept);
+        expired
+            .into_iter()
+            .map(|(key, (at, _))| (key, at))
+            .collect()
+    }
+}
+
+impl ReissueStore for MemoryStore {
+    fn push(&mut self, vin: &str, at: Time, frame: Vec<u8>) -> Result<()> {
+        let key = self.key(vin, at);
+        self.frames.insert(key, (at, frame));
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn take(&mut self, max: usize) -> Result<Vec<Vec<u8>>> {
+        let taken = self.take_entries(max);
+        Ok(taken.into_iter().map(|(_, _, frame)| frame).collect())
+    }
+
+    fn expire(&mut self, cutoff: Time) -> Result<usize> {
+        Ok(self.expire_entries(cutoff).len())
+    }
+}
+
+#[cfg(feature = "std")]
+pub use file::FileStore;
+
+#[cfg(feature = "std")]
+mod file {
+    use core::convert::TryInto;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    const PUSH: u8 = b'+';
+    const REMOVE: u8 = b'-';
+
+    /// Records a file may hold beyond twice the kept frames before it is
+    /// rewritten.
+    const SLACK: usize = 1024;
+
+    /// Frames in an append-only file.
+    ///
+    /// Every kept frame and every removal is a record of a tag, `+` or `-`,
+    /// the length of the rest as a big endian `u32`, the collection time in
+    /// the six bytes of the standard, the number of the frame as a big
+    /// endian `u64`, the length of the VIN as a byte, the VIN and, for `+`,
+    /// the frame. A partial record left by a
+    /// crash is cut off when opening. The file is rewritten with only the
+    /// kept frames once removals make up most of it, and emptied once
+    /// nothing is kept.
+    #[derive(Debug)]
+    pub struct FileStore {
+        path: PathBuf,
+        file: File,
+        frames: MemoryStore,
+        records: usize,
+    }
+
+    impl FileStore {
+        /// Opens the store at `path`, creating it if needed.
+        pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+            let path = path.as_ref().to_path_buf();
+            let mut file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(&path)?;
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+
+            let mut frames = MemoryStore::new();
+            let mut records = 0;
+            let mut offset = 0;
+            while let Some((tag, key, at, frame, len)) = record(&data[offset..]) {
+                match tag {
+                    PUSH => {
+                        frames.next = frames.next.max(key.1 + 1);
+                        frames.frames.insert(key, (at, frame.to_vec()));
+                    }
+                    REMOVE => {
+                        frames.frames.remove(&key);
+                    }
+                    _ => return Err(Error::Corrupt(offset as u64)),
+                }
+                records += 1;
+                offset += len;
+            }
+            if offset < data.len() {
+                file.set_len(offset as u64)?;
+            }
+            let mut store = Self {
+                path,
+                file,
+                frames,
+                records,
+            };
+            store.compact_if_sparse()?;
+            Ok(store)
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Appends a record, cutting off whatever part of it was written if
+        /// that fails, so that no later record follows a partial one.
+        fn append(&mut self, tag: u8, key: &Key, at: Time, frame: &[u8]) -> Result<()> {
+            let len = self.file.metadata()?.len();
+            if let Err(err) = self.file.write_all(&encode_record(tag, key, at, frame)) {
+                let _ = self.file.set_len(len);
+                return Err(err.into());
+            }
+            self.records += 1;
+            Ok(())
+        }
+
+        fn compact_if_sparse(&mut self) -> Result<()> {
+            if self.frames.is_empty() {
+                if self.records > 0 {
+                    self.file.set_len(0)?;
+                    self.records = 0;
+                }
+            } else if self.records > 2 * self.frames.len() + SLACK {
+                self.compact()?;
+            }
+            Ok(())
+        }
+
+        /// Compacts after removals, which are already on disk, so that a
+        /// failure loses nothing; the next removal tries again.
+        fn compact_best_effort(&mut self) {
+            let _ = self.compact_if_sparse();
+        }
+
+        /// Rewrites the file with only the kept frames, through a temporary
+        /// file so that a crash never loses them. The store is left as it
+        /// was if that fails.
+        fn compact(&mut self) -> Result<()> {
+            let mut tmp = self.path.as_os_str().to_owned();
+            tmp.push(".tmp");
+            let written = self.write_frames(Path::new(&tmp));
+            if written.is_err() {
+                let _ = fs::remove_file(&tmp);
+            }
+            written?;
+            fs::rename(&tmp, &self.path)?;
+            self.file = OpenOptions::new().append(true).open(&self.path)?;
+            self.records = self.frames.len();
+            Ok(())
+        }
+
+        fn write_frames(&self, path: &Path) -> Result<()> {
+            let mut file = File::create(path)?;
+            for (key, (at, frame)) in &self.frames.frames {
+                file.write_all(&encode_record(PUSH, key, *at, frame))?;
+            }
+            file.sync_all()?;
+            Ok(())
+        }
+    }
+
+    fn encode_record(tag: u8, key: &Key, at: Time, frame: &[u8]) -> Vec<u8> {
+        let vin = key.2.as_bytes();
+        let vin = &vin[..vin.len().min(u8::MAX as usize)];
+        let len = 15 + vin.len() + frame.len();
+        let mut data = Vec::with_capacity(5 + len);
+        data.push(tag);
+        data.extend_from_slice(&(len as u32).to_be_bytes());
+        data.extend_from_slice(&[at.year, at.month, at.day, at.hour, at.minute, at.second]);
+        data.extend_from_slice(&key.1.to_be_bytes());
+        data.push(vin.len() as u8);
+        data.extend_from_slice(vin);
+        data.extend_from_slice(frame);
+        data
+    }
+
+    /// Splits off the first complete record of `data`.
+    fn record(data: &[u8]) -> Option<(u8, Key, Time, &[u8], usize)> {
+        let len = u32::from_be_bytes(data.get(1..5)?.try_into().ok()?) as usize;
+        let rest = data.get(5..5 + len)?;
+        let at = time_from_bytes(rest.get(..6)?);
+        let n = u64::from_be_bytes(rest.get(6..14)?.try_into().ok()?);
+        let vin_len = *rest.get(14)? as usize;
+        let vin = core::str::from_utf8(rest.get(15..15 + vin_len)?).unwrap_or_default();
+        let key = (at.to_unix(), n, vin.into());
+        Some((data[0], key, at, &rest[15 + vin_len..], 5 + len))
+    }
+
+    impl ReissueStore for FileStore {
+        fn push(&mut self, vin: &str, at: Time, frame: Vec<u8>) -> Result<()> {
+            let key = self.frames.key(vin, at);
+            self.append(PUSH, &key, at, &frame)?;
+            self.frames.frames.insert(key, (at, frame));
+            Ok(())
+        }
+
+        fn len(&self) -> usize {
+            self.frames.len()
+        }
+
+        fn take(&mut self, max: usize) -> Result<Vec<Vec<u8>>> {
+            let taken = self.frames.take_entries(max);
+            for (key, at, _) in &taken {
+                self.append(REMOVE, key, *at, &[])?;
+            }
+            self.compact_best_effort();
+            Ok(taken.into_iter().map(|(_, _, frame)| frame).collect())
+        }
+
+        fn expire(&mut self, cutoff: Time) -> Result<usize> {
+            let expired = self.frames.expire_entries(cutoff);
+            for (key, at) in &expired {
+                self.append(REMOVE, key, *at, &[])?;
+            }
+            self.compact_best_effort();
+            Ok(expired.len())
+        }
+    }
+}
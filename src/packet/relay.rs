@@ -0,0 +1,602 @@
+//! Forwarding of vehicle data to an upstream platform, such as the public
+//! platform enterprise platforms have to report to.
+//!
+//! [`Relay`] is the sans-IO client side, run like the
+//! [`TerminalSession`](crate::packet::terminal::TerminalSession): it logs the
+//! platform in with a platform login (0x05), following the same retry
+//! policy as vehicle logins, forwards frames either passed through as
+//! received or re-encoded from packets, keeps frames while the upstream is
+//! unreachable in a [`ReissueStore`] and sends them once logged in again,
+//! oldest first, at most [`Config::reissue_rate`] a second and with real
+//! time reports turned into reissue reports. With [`Config::await_acks`]
+//! frames stay pending until the upstream acknowledges them and are sent
+//! again after a reconnect otherwise.
+//!
+//! [`Upstream`] is a stand-in for the upstream platform, accepting one
+//! platform login per connection, for tests and bench setups.
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::packet::body::{self, Body};
+use crate::packet::error::Result;
+use crate::packet::platform::{Action as PlatformAction, Close, Violation};
+use crate::packet::reissue::{self, MemoryStore, ReissueStore};
+use crate::packet::serial::LoginSequence;
+use crate::packet::types::{Password, Username};
+use crate::packet::{parser, vin, Command, Encrypt, Packet, Response, Time, Vin, BEGIN};
+
+/// Offset of the command in a frame.
+const COMMAND_OFFSET: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Unique code of the platform, sent in place of a VIN.
+    pub platform_id: String,
+    pub username: String,
+    pub password: String,
+    /// Encryption the platform announces for its data.
+    pub encrypt: Encrypt,
+    /// Wait for an answer of the upstream.
+    pub response_timeout: Duration,
+    /// Silence after which a heart beat is sent.
+    pub heartbeat_interval: Duration,
+    /// Wait between failed logins.
+    pub login_interval: Duration,
+    /// Failed logins in a row before waiting `login_retry_interval`.
+    pub login_attempts: u32,
+    pub login_retry_interval: Duration,
+    /// Keep forwarded frames until the upstream answers them.
+    pub await_acks: bool,
+    /// Most frames kept while offline; the oldest are dropped first.
+    pub buffer_capacity: usize,
+    /// Most kept frames sent a second, all at once if 0.
+    pub reissue_rate: u32,
+    /// Age at which kept frames are dropped.
+    pub retention: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            platform_id: String::new(),
+            username: String::new(),
+            password: String::new(),
+            encrypt: Encrypt::None,
+            response_timeout: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(30),
+            login_interval: Duration::from_secs(60),
+            login_attempts: 3,
+            login_retry_interval: Duration::from_secs(30 * 60),
+            await_acks: false,
+            buffer_capacity: 100_000,
+            reissue_rate: 100,
+            retention: reissue::RETENTION,
+        }
+    }
+}
+
+/// What the relay should do after an event or a poll.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Send the frame upstream.
+    Send(Vec<u8>),
+    /// Drop the upstream connection, which is lost or logged out.
+    Disconnect,
+    /// The reissue store failed, frames may be lost.
+    Store(reissue::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Offline,
+    LoggingIn {
+        attempt: u32,
+        sent_at: Duration,
+    },
+    /// Waiting to try again after `attempt` failed logins.
+    LoginWait {
+        attempt: u32,
+        until: Duration,
+    },
+    LoggedIn,
+    LoggingOut {
+        sent_at: Duration,
+    },
+    LoggedOut,
+}
+
+#[derive(Debug)]
+pub struct Relay {
+    config: Config,
+    sequence: LoginSequence,
+    state: State,
+    connected: bool,
+    last_sent: Duration,
+    /// Time the unanswered heart beat was sent.
+    heartbeat_sent: Option<Duration>,
+    /// Frames to send once logged in.
+    store: Box<dyn ReissueStore + Send>,
+    /// Time the next kept frames are due while logged in.
+    next_reissue: Option<Duration>,
+    /// Frames sent but not yet acknowledged, with the time they were sent.
+    unacked: VecDeque<(Vec<u8>, Duration)>,
+}
+
+impl Relay {
+    /// Fails if the platform id, username or password do not fit their
+    /// fields of the platform login.
+    pub fn new(config: Config) -> Result<Self> {
+        let relay = Self {
+            config,
+            sequence: LoginSequence::new(),
+            state: State::Offline,
+            connected: false,
+            last_sent: Duration::ZERO,
+            heartbeat_sent: None,
+            store: Box::new(MemoryStore::new()),
+            next_reissue: None,
+            unacked: VecDeque::new(),
+        };
+        let at = Time::from_unix(0);
+        relay.packet(relay.login_body(at, 1)).encode()?;
+        Ok(relay)
+    }
+
+    /// Keeps frames in `store` instead of in memory, sending those already
+    /// in it after the next login.
+    pub fn with_store(mut self, store: Box<dyn ReissueStore + Send>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Continues the platform login serial numbers of `sequence`.
+    pub fn with_sequence(mut self, sequence: LoginSequence) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Platform login serial numbers to store after every login.
+    pub fn sequence(&self) -> &LoginSequence {
+        &self.sequence
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.state == State::LoggedIn
+    }
+
+    /// Frames waiting to be sent.
+    pub fn buffered(&self) -> usize {
+        self.store.len()
+    }
+
+    /// Frames sent but not acknowledged.
+    pub fn unacked(&self) -> usize {
+        self.unacked.len()
+    }
+
+    /// Time by which [`poll`](Self::poll) should be called, if anything is
+    /// pending.
+    pub fn deadline(&self) -> Option<Duration> {
+        let timeout = self.config.response_timeout;
+        match self.state {
+            State::LoggingIn { sent_at, .. } | State::LoggingOut { sent_at } => {
+                Some(sent_at + timeout)
+            }
+            State::LoginWait { until, .. } if self.connected => Some(until),
+            State::LoggedIn => {
+                let mut deadline = self.last_sent + self.config.heartbeat_interval;
+                let oldest = self.heartbeat_sent.into_iter();
+                for sent_at in oldest.chain(self.unacked.front().map(|(_, at)| *at)) {
+                    deadline = deadline.min(sent_at + timeout);
+                }
+                Some(self.next_reissue.map_or(deadline, |at| deadline.min(at)))
+            }
+            _ => None,
+        }
+    }
+
+    /// The upstream connection is up, log in unless waiting to.
+    pub fn connected(&mut self, now: Duration) -> Vec<Action> {
+        self.connected = true;
+        match self.state {
+            State::Offline | State::LoggedOut => self.login(1, now),
+            _ => self.poll(now),
+        }
+    }
+
+    /// The upstream connection is lost; unacknowledged frames are kept
+    /// and sent again after the next login. Only fails with
+    /// [`Action::Store`].
+    pub fn disconnected(&mut self, now: Duration) -> Vec<Action> {
+        self.connected = false;
+        self.heartbeat_sent = None;
+        self.next_reissue = None;
+        if !matches!(self.state, State::LoginWait { .. } | State::LoggedOut) {
+            self.state = State::Offline;
+        }
+        let mut actions = Vec::new();
+        while let Some((frame, _)) = self.unacked.pop_front() {
+            actions.extend(self.keep(frame, now));
+        }
+        actions
+    }
+
+    /// Forwards a frame as received from a vehicle.
+    pub fn forward_frame(&mut self, frame: Vec<u8>, now: Duration) -> Vec<Action> {
+        if self.state == State::LoggedIn {
+            return vec![self.send_data(frame, now)];
+        }
+        self.keep(frame, now).into_iter().collect()
+    }
+
+    /// Keeps `frame` until the next login, keyed by its collection time or,
+    /// if encrypted, by `now`.
+    fn keep(&mut self, frame: Vec<u8>, now: Duration) -> Option<Action> {
+        if self.config.buffer_capacity == 0 {
+            return None;
+        }
+        let vin = frame
+            .get(parser::VIN_OFFSET..parser::VIN_OFFSET + vin::LENGTH)
+            .and_then(|vin| core::str::from_utf8(vin).ok())
+            .unwrap_or_default()
+            .to_string();
+        let at = reissue::collection_time(&frame)
+            .unwrap_or_else(|| Time::from_unix(now.as_secs() as i64));
+        let mut kept = Ok(());
+        if self.store.len() >= self.config.buffer_capacity {
+            kept = self.store.take(1).map(drop);
+        }
+        kept.and_then(|()| self.store.push(&vin, at, frame))
+            .err()
+            .map(Action::Store)
+    }
+
+    /// Forwards `packet` encoded afresh, in plain text.
+    pub fn forward(&mut self, packet: &Packet, now: Duration) -> Result<Vec<Action>> {
+        Ok(self.forward_frame(packet.encode()?, now))
+    }
+
+    /// Logs the platform out, disconnecting once the upstream answered or
+    /// gave up.
+    pub fn logout(&mut self, now: Duration) -> Vec<Action> {
+        if self.state != State::LoggedIn {
+            let connected = self.connected;
+            let mut actions = self.logged_out();
+            if !connected {
+                actions.clear();
+            }
+            return actions;
+        }
+        let body = body::PlatformLogout {
+            at: Time::from_unix(now.as_secs() as i64),
+            sn: self.sequence.logout().unwrap_or(1),
+        };
+        self.state = State::LoggingOut { sent_at: now };
+        vec![self.send(Body::PlatformLogout(body), now)]
+    }
+
+    /// Handles a packet of the upstream received at `now`.
+    pub fn handle(&mut self, packet: Packet, now: Duration) -> Vec<Action> {
+        if packet.response == Response::Command {
+            return Vec::new();
+        }
+        match (self.state, packet.command) {
+            (State::LoggingIn { attempt, .. }, Command::PlatformLogin) => {
+                if packet.response == Response::Success {
+                    self.state = State::LoggedIn;
+                    self.flush(now)
+                } else {
+                    self.failed_login(attempt, now)
+                }
+            }
+            (State::LoggingOut { .. }, Command::PlatformLogout) => self.logged_out(),
+            (_, Command::HeartBeat) => {
+                self.heartbeat_sent = None;
+                Vec::new()
+            }
+            (_, command) => {
+                // answers come in order, the first frame of the vehicle and
+                // command is the one answered
+                let vin = packet.vin.as_str().as_bytes();
+                let answered = self.unacked.iter().position(|(frame, _)| {
+                    frame.get(COMMAND_OFFSET) == Some(&command.into())
+                        && frame.get(parser::VIN_OFFSET..parser::VIN_OFFSET + vin.len())
+                            == Some(vin)
+                });
+                if let Some(i) = answered {
+                    self.unacked.remove(i);
+                }
+                Vec::new()
+            }
+        }
+    }
+
+    /// Handles timeouts and sends heart beats and logins that are due.
+    pub fn poll(&mut self, now: Duration) -> Vec<Action> {
+        let timeout = self.config.response_timeout;
+        match self.state {
+            State::LoggingIn { attempt, sent_at } if now >= sent_at + timeout => {
+                self.failed_login(attempt, now)
+            }
+            State::LoginWait { attempt, until } if self.connected && now >= until => {
+                self.login(attempt + 1, now)
+            }
+            State::LoggedIn => {
+                let oldest = self.unacked.front().map(|(_, at)| *at);
+                let expired =
+                    |sent: Option<Duration>| matches!(sent, Some(at) if now >= at + timeout);
+                if expired(self.heartbeat_sent) || expired(oldest) {
+                    let mut actions = self.disconnected(now);
+                    actions.push(Action::Disconnect);
+                    return actions;
+                }
+                if matches!(self.next_reissue, Some(at) if now >= at) {
+                    return self.flush(now);
+                }
+                if now >= self.last_sent + self.config.heartbeat_interval {
+                    let action = self.send(Body::HeartBeat(body::Raw { data: Vec::new() }), now);
+                    self.heartbeat_sent.get_or_insert(now);
+                    return vec![action];
+                }
+                Vec::new()
+            }
+            State::LoggingOut { sent_at } if now >= sent_at + timeout => self.logged_out(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn login(&mut self, attempt: u32, now: Duration) -> Vec<Action> {
+        let at = Time::from_unix(now.as_secs() as i64);
+        let sn = self.sequence.next_login(&at);
+        let body = self.login_body(at, sn);
+        self.state = State::LoggingIn {
+            attempt,
+            sent_at: now,
+        };
+        vec![self.send(body, now)]
+    }
+
+    fn login_body(&self, at: Time, sn: u16) -> Body {
+        Body::PlatformLogin(body::PlatformLogin {
+            at,
+            sn,
+            username: Username::from(self.config.username.clone()),
+            password: Password::from(self.config.password.clone()),
+            encrypt: self.config.encrypt,
+        })
+    }
+
+    fn failed_login(&mut self, attempt: u32, now: Duration) -> Vec<Action> {
+        self.state = if attempt >= self.config.login_attempts {
+            State::LoginWait {
+                attempt: 0,
+                until: now + self.config.login_retry_interval,
+            }
+        } else {
+            State::LoginWait {
+                attempt,
+                until: now + self.config.login_interval,
+            }
+        };
+        Vec::new()
+    }
+
+    fn logged_out(&mut self) -> Vec<Action> {
+        self.state = State::LoggedOut;
+        self.connected = false;
+        vec![Action::Disconnect]
+    }
+
+    /// Sends the next frames kept while not logged in, real time reports
+    /// as reissue reports.
+    fn flush(&mut self, now: Duration) -> Vec<Action> {
+        let config = &self.config;
+        let frames = match self
+            .store
+            .replay(now, config.retention, config.reissue_rate)
+        {
+            Ok(frames) => frames,
+            Err(err) => {
+                self.next_reissue = None;
+                return vec![Action::Store(err)];
+            }
+        };
+        self.next_reissue = if self.store.is_empty() {
+            None
+        } else {
+            Some(now + reissue::PERIOD)
+        };
+        frames
+            .into_iter()
+            .map(|mut frame| {
+                if frame.get(COMMAND_OFFSET) == Some(&Command::RealTimeReport.into()) {
+                    frame[COMMAND_OFFSET] = Command::ReissueReport.into();
+                    let last = frame.len() - 1;
+                    frame[last] = parser::checksum(&frame[COMMAND_OFFSET..last]);
+                }
+                self.send_data(frame, now)
+            })
+            .collect()
+    }
+
+    fn send_data(&mut self, frame: Vec<u8>, now: Duration) -> Action {
+        self.last_sent = now;
+        if self.config.await_acks {
+            self.unacked.push_back((frame.clone(), now));
+        }
+        Action::Send(frame)
+    }
+
+    fn send(&mut self, body: Body, now: Duration) -> Action {
+        self.last_sent = now;
+        let frame = self.packet(body).encode();
+        // the login, the longest platform command, encoded in `new`, and
+        // the fields of the others are of fixed size
+        Action::Send(frame.expect("platform commands of a valid config encode"))
+    }
+
+    fn packet(&self, body: Body) -> Packet {
+        Packet {
+            begin: BEGIN,
+            command: body.command(),
+            response: Response::Command,
+            vin: Vin::new_unchecked(self.config.platform_id.clone()),
+            encrypt: Encrypt::None,
+            body_len: 0,
+            body,
+            bcc: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamConfig {
+    pub username: String,
+    pub password: String,
+    /// Silence after which the connection is closed.
+    pub idle_timeout: Duration,
+    /// Answer forwarded vehicle data.
+    pub respond_data: bool,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            username: String::new(),
+            password: String::new(),
+            idle_timeout: Duration::from_secs(180),
+            respond_data: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpstreamState {
+    AwaitingLogin,
+    LoggedIn,
+    Closed,
+}
+
+/// Stand-in for the upstream platform, answering one relay connection with
+/// the [`platform`](crate::packet::platform) actions.
+#[derive(Debug)]
+pub struct Upstream {
+    config: UpstreamConfig,
+    state: UpstreamState,
+    platform_id: Option<String>,
+    last_seen: Duration,
+}
+
+impl Upstream {
+    pub fn new(config: UpstreamConfig, now: Duration) -> Self {
+        Self {
+            config,
+            state: UpstreamState::AwaitingLogin,
+            platform_id: None,
+            last_seen: now,
+        }
+    }
+
+    /// Unique code of the logged in platform.
+    pub fn platform_id(&self) -> Option<&str> {
+        self.platform_id.as_deref()
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.state == UpstreamState::LoggedIn
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == UpstreamState::Closed
+    }
+
+    /// Time by which [`poll`](Self::poll) should be called, none once
+    /// closed.
+    pub fn deadline(&self) -> Option<Duration> {
+        match self.state {
+            UpstreamState::Closed => None,
+            _ => Some(self.last_seen + self.config.idle_timeout),
+        }
+    }
+
+    /// Closes the connection if it has been idle for too long at `now`.
+    pub fn poll(&mut self, now: Duration) -> Vec<PlatformAction> {
+        match self.deadline() {
+            Some(deadline) if now >= deadline => {
+                self.state = UpstreamState::Closed;
+                vec![PlatformAction::Close(Close::Idle)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Handles a packet of the relay received at `now`; vehicle data is
+    /// emitted.
+    pub fn handle(&mut self, packet: Packet, now: Duration) -> Vec<PlatformAction> {
+        if self.state == UpstreamState::Closed {
+            return Vec::new();
+        }
+        self.last_seen = now;
+        if packet.response != Response::Command {
+            return Vec::new();
+        }
+        match (self.state, &packet.body) {
+            (_, Body::PlatformLogin(login)) => {
+                let valid = login.username.message == self.config.username
+                    && login.password.message == self.config.password;
+                if !valid {
+                    let violation = Violation::Credentials(login.username.message.clone());
+                    return self.refuse(&packet, violation);
+                }
+                self.platform_id = Some(packet.vin.as_str().into());
+                self.state = UpstreamState::LoggedIn;
+                self.reply(&packet)
+            }
+            (UpstreamState::LoggedIn, Body::PlatformLogout(_)) => {
+                let mut actions = self.reply(&packet);
+                self.state = UpstreamState::Closed;
+                actions.push(PlatformAction::Close(Close::Logout));
+                actions
+            }
+            (UpstreamState::LoggedIn, Body::HeartBeat(_) | Body::Time(_)) => self.reply(&packet),
+            (UpstreamState::LoggedIn, _) => {
+                let mut actions = if self.config.respond_data {
+                    self.reply(&packet)
+                } else {
+                    Vec::new()
+                };
+                if !self.is_closed() {
+                    actions.insert(0, PlatformAction::Emit(packet));
+                }
+                actions
+            }
+            _ => self.refuse(&packet, Violation::NotLoggedIn(packet.command)),
+        }
+    }
+
+    fn reply(&mut self, packet: &Packet) -> Vec<PlatformAction> {
+        match packet.reply(Response::Success) {
+            Ok(reply) => vec![PlatformAction::Send(reply)],
+            Err(err) => {
+                self.state = UpstreamState::Closed;
+                let violation = Violation::Encode(err.to_string());
+                vec![PlatformAction::Close(Close::Violation(violation))]
+            }
+        }
+    }
+
+    fn refuse(&mut self, packet: &Packet, violation: Violation) -> Vec<PlatformAction> {
+        let mut actions = Vec::new();
+        if let Ok(reply) = packet.reply(Response::Fail) {
+            actions.push(PlatformAction::Send(reply));
+        }
+        self.state = UpstreamState::Closed;
+        actions.push(PlatformAction::Close(Close::Violation(violation)));
+        actions
+    }
+}
@@ -0,0 +1,180 @@
+//! Serial numbers of vehicle logins and logouts.
+//!
+//! The login serial number starts at 1 every day and increases by one per
+//! login, wrapping around after [`MAX`]; a logout repeats the serial number
+//! of the login it ends. [`LoginSequence`] issues them on the terminal side,
+//! [`Checker`] verifies them on the platform side.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+
+use serde::{Deserialize, Serialize};
+
+use crate::packet::Time;
+
+/// Largest serial number; the next login after it gets 1 again.
+pub const MAX: u16 = 65531;
+
+/// Serial number following `sn`.
+pub fn next(sn: u16) -> u16 {
+    if sn >= MAX {
+        1
+    } else {
+        sn + 1
+    }
+}
+
+/// Calendar day of `at`, the unit after which serial numbers reset.
+fn day(at: &Time) -> (u8, u8, u8) {
+    (at.year, at.month, at.day)
+}
+
+/// Terminal side state issuing login serial numbers.
+///
+/// The state is small enough to be stored after every login, either through
+/// serde or with [`load`](Self::load) and [`save`](Self::save).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginSequence {
+    /// Day of the last login.
+    pub year: u8,
+    pub month: u8,
+    pub day: u8,
+    /// Serial number of the last login, 0 before the first one.
+    pub sn: u16,
+}
+
+impl LoginSequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serial number for a login at `at`, which becomes the current one.
+    pub fn next_login(&mut self, at: &Time) -> u16 {
+        if day(at) != (self.year, self.month, self.day) {
+            self.year = at.year;
+            self.month = at.month;
+            self.day = at.day;
+            self.sn = 0;
+        }
+        self.sn = next(self.sn);
+        self.sn
+    }
+
+    /// Serial number for logging out of the current login.
+    pub fn logout(&self) -> Option<u16> {
+        match self.sn {
+            0 => None,
+            sn => Some(sn),
+        }
+    }
+
+    /// Reads the state saved at `path`, starting afresh if there is none.
+    #[cfg(feature = "std")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> crate::serde::Result<Self> {
+        match std::fs::read(path) {
+            Ok(data) => crate::serde::from_bytes(&data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replaces the state at `path`, going through a temporary file so that
+    /// a crash never leaves a partial state behind.
+    #[cfg(feature = "std")]
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> crate::serde::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, crate::serde::to_bytes(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Problem with the serial number of a login or logout.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    #[error("login serial number {0} was already used today")]
+    Reused(u16),
+
+    #[error("login serial number is {actual} but should be {expected}")]
+    OutOfOrder { expected: u16, actual: u16 },
+
+    #[error("logout serial number {actual} does not match login serial number {login:?}")]
+    Mismatch { login: Option<u16>, actual: u16 },
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    day: (u8, u8, u8),
+    /// Serial numbers of the day's accepted logins.
+    used: BTreeSet<u16>,
+    last: u16,
+    logged_in: Option<u16>,
+}
+
+/// Platform side verification of serial numbers per VIN.
+///
+/// Only logins of the same day are compared: the first login the checker
+/// sees on a day is accepted, since earlier ones may have gone to another
+/// platform instance. Rejected logins leave the state as it was.
+#[derive(Debug, Default)]
+pub struct Checker {
+    vehicles: BTreeMap<String, State>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a login of `vin`, reporting whether its serial number fits
+    /// the previous logins of the day.
+    pub fn login(&mut self, vin: &str, at: &Time, sn: u16) -> Result<(), Issue> {
+        match self.vehicles.get_mut(vin) {
+            Some(state) if state.day == day(at) => {
+                if state.used.contains(&sn) {
+                    return Err(Issue::Reused(sn));
+                }
+                if next(state.last) != sn {
+                    return Err(Issue::OutOfOrder {
+                        expected: next(state.last),
+                        actual: sn,
+                    });
+                }
+                state.used.insert(sn);
+                state.last = sn;
+                state.logged_in = Some(sn);
+            }
+            _ => {
+                let state = State {
+                    day: day(at),
+                    used: BTreeSet::from([sn]),
+                    last: sn,
+                    logged_in: Some(sn),
+                };
+                self.vehicles.insert(vin.into(), state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a logout of `vin`, which must repeat the serial number of the
+    /// current login.
+    pub fn logout(&mut self, vin: &str, sn: u16) -> Result<(), Issue> {
+        let login = self
+            .vehicles
+            .get_mut(vin)
+            .and_then(|state| state.logged_in.take());
+        if login == Some(sn) {
+            Ok(())
+        } else {
+            Err(Issue::Mismatch { login, actual: sn })
+        }
+    }
+
+    /// Forgets everything about `vin`.
+    pub fn remove(&mut self, vin: &str) {
+        self.vehicles.remove(vin);
+    }
+}
@@ -0,0 +1,117 @@
+//! Frames of a byte stream, such as a TCP connection.
+//!
+//! [`Framer`] collects received bytes and splits off complete frames by the
+//! body length of their header; bytes that cannot start a frame are handed
+//! out as garbage so that the stream resynchronizes on the next `##`.
+//!
+//! Only a `##` followed by a plausible header, with a known command, a valid
+//! encryption flag and a printable VIN, starts a frame. Frames that fail
+//! their check byte anyway should be handed back through
+//! [`Framer::resync`], so that a `##` within them is not lost.
+
+use alloc::vec::Vec;
+
+use crate::packet::parser;
+use crate::packet::HEADER_LEN;
+
+const COMMAND_OFFSET: usize = 2;
+const VIN_OFFSET: usize = 4;
+const ENCRYPT_OFFSET: usize = 21;
+
+/// Piece of a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    /// Complete frame, not yet decoded or checked.
+    Frame(Vec<u8>),
+    /// Bytes before the next start marker.
+    Garbage(Vec<u8>),
+}
+
+#[derive(Debug, Default)]
+pub struct Framer {
+    buffer: Vec<u8>,
+    /// Whether the `##` at the start of `buffer` is known not to start a
+    /// frame.
+    resync: bool,
+}
+
+impl Framer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends received bytes.
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Bytes waiting for the rest of their frame.
+    pub fn pending(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Puts back `frame`, which failed its check byte or otherwise turned
+    /// out not to be a frame. Only its start marker is then skipped as
+    /// garbage, so that a frame starting within it is still found.
+    pub fn resync(&mut self, frame: Vec<u8>) {
+        self.buffer.splice(..0, frame);
+        self.resync = true;
+    }
+
+    /// Splits off the bytes up to the next `##` after the one at the start.
+    fn skip_marker(&mut self) -> Chunk {
+        self.resync = false;
+        let end = match find_marker(&self.buffer[1..]) {
+            Some(i) => i + 1,
+            None if self.buffer.last() == Some(&b'#') => self.buffer.len() - 1,
+            None => self.buffer.len(),
+        };
+        Chunk::Garbage(self.buffer.drain(..end).collect())
+    }
+}
+
+fn find_marker(data: &[u8]) -> Option<usize> {
+    data.windows(2).position(|w| w == b"##")
+}
+
+/// Whether the bytes of a header received so far could start a frame.
+fn plausible(header: &[u8]) -> bool {
+    let header = &header[..header.len().min(HEADER_LEN)];
+    header.iter().enumerate().all(|(i, &b)| match i {
+        // commands of the standard, terminal data and platform defined ones
+        COMMAND_OFFSET => matches!(b, 0x01..=0x08 | 0x80..=0x82 | 0xc0..=0xfe),
+        // printable, padded with NULs
+        VIN_OFFSET..=20 => (0x20..0x7f).contains(&b) || b == 0x00,
+        ENCRYPT_OFFSET => matches!(b, 0x01..=0x03 | 0xfe | 0xff),
+        _ => true,
+    })
+}
+
+/// Splits off complete frames and garbage; `None` only means that more
+/// bytes are needed.
+impl Iterator for Framer {
+    type Item = Chunk;
+
+    fn next(&mut self) -> Option<Chunk> {
+        let start = find_marker(&self.buffer);
+        match start {
+            Some(0) => {}
+            Some(start) => return Some(Chunk::Garbage(self.buffer.drain(..start).collect())),
+            // keep a trailing '#' that may start a marker
+            None if self.buffer.last() == Some(&b'#') => {
+                let end = self.buffer.len() - 1;
+                return (end > 0).then(|| Chunk::Garbage(self.buffer.drain(..end).collect()));
+            }
+            None if self.buffer.is_empty() => return None,
+            None => return Some(Chunk::Garbage(core::mem::take(&mut self.buffer))),
+        }
+        if self.resync || !plausible(&self.buffer) {
+            return Some(self.skip_marker());
+        }
+        let len = parser::frame_len(&self.buffer)?;
+        if self.buffer.len() < len {
+            return None;
+        }
+        Some(Chunk::Frame(self.buffer.drain(..len).collect()))
+    }
+}
@@ -0,0 +1,402 @@
+//! Terminal side protocol state of one vehicle.
+//!
+//! [`TerminalSession`] does no IO, like the
+//! [`PlatformSession`](crate::packet::platform::PlatformSession): the client
+//! tells it about the connection, hands it reports and the platform's
+//! packets, carries out the returned [`Action`]s and calls
+//! [`poll`](TerminalSession::poll) by the
+//! [`deadline`](TerminalSession::deadline). Times are durations since the
+//! Unix epoch.
+//!
+//! Logins follow the retry policy of the standard: an unanswered or refused
+//! login is repeated after [`Config::login_interval`], and after
+//! [`Config::login_attempts`] failures in a row only after
+//! [`Config::login_retry_interval`]. Reports made while not logged in are
+//! kept in a [`ReissueStore`] and sent as reissue reports after the next
+//! login, oldest first and at most [`Config::reissue_rate`] a second.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::packet::body::{self, Body};
+use crate::packet::error::Error;
+use crate::packet::parser;
+use crate::packet::realtime::RealTimeReport;
+use crate::packet::reissue::{self, MemoryStore, ReissueStore};
+use crate::packet::serial::LoginSequence;
+use crate::packet::types::SubsysCode;
+use crate::packet::{Command, Encrypt, Iccid, Packet, Response, Time, Vin, BEGIN};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Wait for an answer of the platform.
+    pub response_timeout: Duration,
+    /// Silence after which a heart beat is sent.
+    pub heartbeat_interval: Duration,
+    /// Wait between failed logins.
+    pub login_interval: Duration,
+    /// Failed logins in a row before waiting `login_retry_interval`.
+    pub login_attempts: u32,
+    pub login_retry_interval: Duration,
+    /// Most reports kept while offline; the oldest are dropped first.
+    pub buffer_capacity: usize,
+    /// Most reissue reports sent a second, all at once if 0.
+    pub reissue_rate: u32,
+    /// Age at which kept reports are dropped.
+    pub retention: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            response_timeout: Duration::from_secs(60),
+            heartbeat_interval: Duration::from_secs(30),
+            login_interval: Duration::from_secs(60),
+            login_attempts: 3,
+            login_retry_interval: Duration::from_secs(30 * 60),
+            // a day of reports every 10 s
+            buffer_capacity: 8640,
+            reissue_rate: 10,
+            retention: reissue::RETENTION,
+        }
+    }
+}
+
+/// What the client should do after an event or a poll.
+#[derive(Debug)]
+pub enum Action {
+    /// Send the packet to the platform.
+    Send(Packet),
+    /// Drop the connection, which is lost or logged out.
+    Disconnect,
+    /// The reissue store failed, reports may be lost.
+    Store(reissue::Error),
+    /// A report was dropped: it could not be encoded for the store, or what
+    /// the store kept no longer decodes.
+    Drop(Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Offline,
+    LoggingIn {
+        attempt: u32,
+        sent_at: Duration,
+    },
+    /// Waiting to try again after `attempt` failed logins.
+    LoginWait {
+        attempt: u32,
+        until: Duration,
+    },
+    LoggedIn,
+    LoggingOut {
+        sent_at: Duration,
+    },
+    LoggedOut,
+}
+
+#[derive(Debug)]
+pub struct TerminalSession {
+    config: Config,
+    vin: String,
+    iccid: String,
+    subsys_codes: Vec<String>,
+    sequence: LoginSequence,
+    state: State,
+    connected: bool,
+    last_sent: Duration,
+    /// Time the unanswered heart beat was sent.
+    heartbeat_sent: Option<Duration>,
+    store: Box<dyn ReissueStore + Send>,
+    /// Time the next reissue reports are due while logged in.
+    next_reissue: Option<Duration>,
+}
+
+impl TerminalSession {
+    pub fn new(vin: &str, iccid: &str, config: Config) -> Self {
+        Self {
+            config,
+            vin: vin.into(),
+            iccid: iccid.into(),
+            subsys_codes: Vec::new(),
+            sequence: LoginSequence::new(),
+            state: State::Offline,
+            connected: false,
+            last_sent: Duration::ZERO,
+            heartbeat_sent: None,
+            store: Box::new(MemoryStore::new()),
+            next_reissue: None,
+        }
+    }
+
+    /// Keeps reports in `store` instead of in memory, reissuing those
+    /// already in it after the next login.
+    pub fn with_store(mut self, store: Box<dyn ReissueStore + Send>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Codes of the rechargeable energy storage subsystems sent on login.
+    pub fn with_subsystems(mut self, codes: Vec<String>) -> Self {
+        self.subsys_codes = codes;
+        self
+    }
+
+    /// Continues the login serial numbers of `sequence`, as stored after
+    /// the last login.
+    pub fn with_sequence(mut self, sequence: LoginSequence) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Login serial numbers to store after every login.
+    pub fn sequence(&self) -> &LoginSequence {
+        &self.sequence
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.state == State::LoggedIn
+    }
+
+    /// Reports waiting to be reissued.
+    pub fn buffered(&self) -> usize {
+        self.store.len()
+    }
+
+    /// Time by which [`poll`](Self::poll) should be called, if anything is
+    /// pending.
+    pub fn deadline(&self) -> Option<Duration> {
+        let timeout = self.config.response_timeout;
+        match self.state {
+            State::LoggingIn { sent_at, .. } | State::LoggingOut { sent_at } => {
+                Some(sent_at + timeout)
+            }
+            State::LoginWait { until, .. } if self.connected => Some(until),
+            State::LoggedIn => {
+                let mut deadline = self.last_sent + self.config.heartbeat_interval;
+                for at in self
+                    .next_reissue
+                    .into_iter()
+                    .chain(self.heartbeat_sent.map(|at| at + timeout))
+                {
+                    deadline = deadline.min(at);
+                }
+                Some(deadline)
+            }
+            _ => None,
+        }
+    }
+
+    /// The connection to the platform is up, log in unless waiting to.
+    pub fn connected(&mut self, now: Duration) -> Vec<Action> {
+        self.connected = true;
+        match self.state {
+            State::Offline | State::LoggedOut => self.login(1, now),
+            _ => self.poll(now),
+        }
+    }
+
+    /// The connection is lost; reports are kept until the next login.
+    pub fn disconnected(&mut self) {
+        self.connected = false;
+        self.heartbeat_sent = None;
+        self.next_reissue = None;
+        if !matches!(self.state, State::LoginWait { .. } | State::LoggedOut) {
+            self.state = State::Offline;
+        }
+    }
+
+    /// Sends `report`, or keeps it for a reissue if not logged in.
+    pub fn report(&mut self, report: RealTimeReport, now: Duration) -> Vec<Action> {
+        if self.state == State::LoggedIn {
+            return vec![self.send(Body::RealTimeReport(report), now)];
+        }
+        if self.config.buffer_capacity == 0 {
+            return Vec::new();
+        }
+        let at = report.at;
+        let frame = match self.packet(Body::ReissueReport(report)).encode() {
+            Ok(frame) => frame,
+            Err(err) => return vec![Action::Drop(err)],
+        };
+        let mut kept = Ok(());
+        if self.store.len() >= self.config.buffer_capacity {
+            kept = self.store.take(1).map(drop);
+        }
+        match kept.and_then(|()| self.store.push(&self.vin, at, frame)) {
+            Ok(()) => Vec::new(),
+            Err(err) => vec![Action::Store(err)],
+        }
+    }
+
+    /// Logs out, disconnecting once the platform answered or gave up.
+    pub fn logout(&mut self, now: Duration) -> Vec<Action> {
+        if self.state != State::LoggedIn {
+            let connected = self.connected;
+            let mut actions = self.logged_out();
+            if !connected {
+                actions.clear();
+            }
+            return actions;
+        }
+        let body = body::VehicleLogout {
+            at: Time::from_unix(now.as_secs() as i64),
+            sn: self.sequence.logout().unwrap_or(1),
+        };
+        self.state = State::LoggingOut { sent_at: now };
+        vec![self.send(Body::VehicleLogout(body), now)]
+    }
+
+    /// Handles a packet of the platform received at `now`.
+    pub fn handle(&mut self, packet: Packet, now: Duration) -> Vec<Action> {
+        if packet.response == Response::Command || packet.vin.as_str() != self.vin {
+            return Vec::new();
+        }
+        match (self.state, packet.command) {
+            (State::LoggingIn { attempt, .. }, Command::VehicleLogin) => {
+                if packet.response == Response::Success {
+                    self.state = State::LoggedIn;
+                    self.reissue(now)
+                } else {
+                    self.failed_login(attempt, now)
+                }
+            }
+            (State::LoggingOut { .. }, Command::VehicleLogout) => self.logged_out(),
+            (_, Command::HeartBeat) => {
+                self.heartbeat_sent = None;
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Handles timeouts and sends heart beats and logins that are due.
+    pub fn poll(&mut self, now: Duration) -> Vec<Action> {
+        let timeout = self.config.response_timeout;
+        match self.state {
+            State::LoggingIn { attempt, sent_at } if now >= sent_at + timeout => {
+                self.failed_login(attempt, now)
+            }
+            State::LoginWait { attempt, until } if self.connected && now >= until => {
+                self.login(attempt + 1, now)
+            }
+            State::LoggedIn => {
+                if matches!(self.heartbeat_sent, Some(sent_at) if now >= sent_at + timeout) {
+                    self.disconnected();
+                    return vec![Action::Disconnect];
+                }
+                if matches!(self.next_reissue, Some(at) if now >= at) {
+                    return self.reissue(now);
+                }
+                if now >= self.last_sent + self.config.heartbeat_interval {
+                    let action = self.send(Body::HeartBeat(body::Raw { data: Vec::new() }), now);
+                    self.heartbeat_sent.get_or_insert(now);
+                    return vec![action];
+                }
+                Vec::new()
+            }
+            State::LoggingOut { sent_at } if now >= sent_at + timeout => self.logged_out(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn login(&mut self, attempt: u32, now: Duration) -> Vec<Action> {
+        let at = Time::from_unix(now.as_secs() as i64);
+        let subsys_codes: Vec<SubsysCode> = self
+            .subsys_codes
+            .iter()
+            .map(|code| SubsysCode::from(code.clone()))
+            .collect();
+        let subsys_len = subsys_codes
+            .iter()
+            .filter_map(|code| code.encode().ok())
+            .map(|bytes| bytes.len())
+            .max()
+            .unwrap_or(0)
+            .min(body::MAX_SUBSYS_CODE_LEN as usize) as u8;
+        let body = body::VehicleLogin {
+            at,
+            sn: self.sequence.next_login(&at),
+            iccid: Iccid::new_unchecked(self.iccid.clone()),
+            subsys_len,
+            subsys_codes,
+        };
+        self.state = State::LoggingIn {
+            attempt,
+            sent_at: now,
+        };
+        vec![self.send(Body::VehicleLogin(body), now)]
+    }
+
+    fn failed_login(&mut self, attempt: u32, now: Duration) -> Vec<Action> {
+        self.state = if attempt >= self.config.login_attempts {
+            State::LoginWait {
+                attempt: 0,
+                until: now + self.config.login_retry_interval,
+            }
+        } else {
+            State::LoginWait {
+                attempt,
+                until: now + self.config.login_interval,
+            }
+        };
+        Vec::new()
+    }
+
+    fn logged_out(&mut self) -> Vec<Action> {
+        self.state = State::LoggedOut;
+        self.connected = false;
+        vec![Action::Disconnect]
+    }
+
+    /// Sends the next kept reports in the order they were made.
+    fn reissue(&mut self, now: Duration) -> Vec<Action> {
+        let config = &self.config;
+        let frames = match self
+            .store
+            .replay(now, config.retention, config.reissue_rate)
+        {
+            Ok(frames) => frames,
+            Err(err) => {
+                self.next_reissue = None;
+                return vec![Action::Store(err)];
+            }
+        };
+        self.next_reissue = if self.store.is_empty() {
+            None
+        } else {
+            Some(now + reissue::PERIOD)
+        };
+        if !frames.is_empty() {
+            self.last_sent = now;
+        }
+        frames
+            .iter()
+            .map(|frame| match parser::parse_bytes(frame) {
+                Ok(packet) => Action::Send(packet),
+                Err(err) => Action::Drop(err),
+            })
+            .collect()
+    }
+
+    fn send(&mut self, body: Body, now: Duration) -> Action {
+        self.last_sent = now;
+        Action::Send(self.packet(body))
+    }
+
+    fn packet(&self, body: Body) -> Packet {
+        Packet {
+            begin: BEGIN,
+            command: body.command(),
+            response: Response::Command,
+            vin: Vin::new_unchecked(self.vin.clone()),
+            encrypt: Encrypt::None,
+            body_len: 0,
+            body,
+            bcc: 0,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Collection times of packets.
+//!
+//! The standard sends local time of China (UTC+08:00) with a two digit year,
+//! so the representable range is 2000 to 2099. The `chrono` and `time`
+//! features convert from and to the date types of those crates.
+
+#[cfg(any(feature = "chrono", feature = "time"))]
+use core::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+/// Offset of packet times from UTC, in seconds.
+pub const UTC_OFFSET: i32 = 8 * 3600;
+
+/// Year of the two digit year `00`.
+pub const BASE_YEAR: i32 = 2000;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Time {
+    pub year: u8,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("{field} {value} is out of range")]
+    Range { field: &'static str, value: u8 },
+
+    #[error("year {0} is outside 2000 to 2099")]
+    Year(i32),
+}
+
+impl Time {
+    /// Current time, wrapping around after 2099.
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Self::from_unix(secs)
+    }
+
+    /// Time `secs` seconds after 1970-01-01 00:00:00 UTC, wrapping around
+    /// after 2099.
+    pub fn from_unix(secs: i64) -> Self {
+        Self::from_local_seconds(secs + UTC_OFFSET as i64)
+    }
+
+    /// Seconds since 1970-01-01 00:00:00 UTC, the reverse of
+    /// [`from_unix`](Self::from_unix) for valid times.
+    pub fn to_unix(&self) -> i64 {
+        // days_from_civil of Howard Hinnant's date algorithms
+        let (month, day) = (self.month as i64, self.day as i64);
+        let year = self.full_year() as i64 - if month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        let secs = self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        days * 86400 + secs - UTC_OFFSET as i64
+    }
+
+    /// Four digit year.
+    pub fn full_year(&self) -> i32 {
+        BASE_YEAR + self.year as i32
+    }
+
+    /// Checks every field against its range, including the number of days
+    /// of the month.
+    pub fn validate(&self) -> Result<(), Error> {
+        check("year", self.year, 0, 99)?;
+        check("month", self.month, 1, 12)?;
+        let days = days_in_month(self.full_year(), self.month);
+        check("day", self.day, 1, days)?;
+        check("hour", self.hour, 0, 23)?;
+        check("minute", self.minute, 0, 59)?;
+        check("second", self.second, 0, 59)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Splits seconds since 1970-01-01 00:00:00 local time into fields.
+    fn from_local_seconds(secs: i64) -> Self {
+        let days = secs.div_euclid(86400);
+        let secs = secs.rem_euclid(86400);
+        // civil_from_days of Howard Hinnant's date algorithms
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self {
+            year: (year - BASE_YEAR as i64).rem_euclid(100) as u8,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    /// Builds a time from a four digit year and the other fields.
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    fn from_fields(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Result<Self, Error> {
+        if !(BASE_YEAR..BASE_YEAR + 100).contains(&year) {
+            return Err(Error::Year(year));
+        }
+        // every other field is known to be in range already
+        Ok(Self {
+            year: (year - BASE_YEAR) as u8,
+            month: month as u8,
+            day: day as u8,
+            hour: hour as u8,
+            minute: minute as u8,
+            // leap seconds
+            second: second.min(59) as u8,
+        })
+    }
+}
+
+fn check(field: &'static str, value: u8, min: u8, max: u8) -> Result<(), Error> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::Range { field, value })
+    }
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_offset() -> chrono::FixedOffset {
+    chrono::FixedOffset::east_opt(UTC_OFFSET).expect("offset within a day")
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Time> for chrono::NaiveDateTime {
+    type Error = Error;
+
+    fn try_from(t: Time) -> Result<Self, Error> {
+        t.validate()?;
+        let date = chrono::NaiveDate::from_ymd_opt(t.full_year(), t.month.into(), t.day.into())
+            .ok_or(Error::Range {
+                field: "day",
+                value: t.day,
+            })?;
+        let time = chrono::NaiveTime::from_hms_opt(t.hour.into(), t.minute.into(), t.second.into())
+            .ok_or(Error::Range {
+                field: "second",
+                value: t.second,
+            })?;
+        Ok(chrono::NaiveDateTime::new(date, time))
+    }
+}
+
+/// The time at +08:00.
+#[cfg(feature = "chrono")]
+impl TryFrom<Time> for chrono::DateTime<chrono::FixedOffset> {
+    type Error = Error;
+
+    fn try_from(t: Time) -> Result<Self, Error> {
+        let naive = chrono::NaiveDateTime::try_from(t)?;
+        let dt = naive.and_local_timezone(chrono_offset()).single();
+        Ok(dt.expect("fixed offsets are unambiguous"))
+    }
+}
+
+/// Takes the date time as local time of China.
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::NaiveDateTime> for Time {
+    type Error = Error;
+
+    fn try_from(dt: chrono::NaiveDateTime) -> Result<Self, Error> {
+        use chrono::{Datelike, Timelike};
+        Time::from_fields(
+            dt.year(),
+            dt.month(),
+            dt.day(),
+            dt.hour(),
+            dt.minute(),
+            dt.second(),
+        )
+    }
+}
+
+/// Converts the date time to +08:00 first.
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> TryFrom<chrono::DateTime<Tz>> for Time {
+    type Error = Error;
+
+    fn try_from(dt: chrono::DateTime<Tz>) -> Result<Self, Error> {
+        Time::try_from(dt.with_timezone(&chrono_offset()).naive_local())
+    }
+}
+
+#[cfg(feature = "time")]
+fn time_offset() -> ::time::UtcOffset {
+    ::time::UtcOffset::from_whole_seconds(UTC_OFFSET).expect("offset within a day")
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Time> for ::time::PrimitiveDateTime {
+    type Error = Error;
+
+    fn try_from(t: Time) -> Result<Self, Error> {
+        t.validate()?;
+        let range = |field, value| move |_| Error::Range { field, value };
+        let month = ::time::Month::try_from(t.month).map_err(range("month", t.month))?;
+        let date = ::time::Date::from_calendar_date(t.full_year(), month, t.day)
+            .map_err(range("day", t.day))?;
+        let time = ::time::Time::from_hms(t.hour, t.minute, t.second)
+            .map_err(range("second", t.second))?;
+        Ok(::time::PrimitiveDateTime::new(date, time))
+    }
+}
+
+/// The time at +08:00.
+#[cfg(feature = "time")]
+impl TryFrom<Time> for ::time::OffsetDateTime {
+    type Error = Error;
+
+    fn try_from(t: Time) -> Result<Self, Error> {
+        Ok(::time::PrimitiveDateTime::try_from(t)?.assume_offset(time_offset()))
+    }
+}
+
+/// Takes the date time as local time of China.
+#[cfg(feature = "time")]
+impl TryFrom<::time::PrimitiveDateTime> for Time {
+    type Error = Error;
+
+    fn try_from(dt: ::time::PrimitiveDateTime) -> Result<Self, Error> {
+        Time::from_fields(
+            dt.year(),
+            u8::from(dt.month()).into(),
+            dt.day().into(),
+            dt.hour().into(),
+            dt.minute().into(),
+            dt.second().into(),
+        )
+    }
+}
+
+/// Converts the date time to +08:00 first.
+#[cfg(feature = "time")]
+impl TryFrom<::time::OffsetDateTime> for Time {
+    type Error = Error;
+
+    fn try_from(dt: ::time::OffsetDateTime) -> Result<Self, Error> {
+        let dt = dt.to_offset(time_offset());
+        Time::try_from(::time::PrimitiveDateTime::new(dt.date(), dt.time()))
+    }
+}
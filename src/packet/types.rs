@@ -1,21 +1,12 @@
-use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::packet::error;
 use crate::serde::gbk;
 
-#[repr(C)]
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Time {
-    pub year: u8,
-    pub month: u8,
-    pub day: u8,
-    pub hour: u8,
-    pub minute: u8,
-    pub second: u8,
-}
+pub use crate::packet::time::Time;
 
-#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize_repr, Deserialize_repr,
+)]
 #[repr(u8)]
 pub enum Encrypt {
     None = 0x01,
@@ -23,13 +14,25 @@ pub enum Encrypt {
     Aes128 = 0x03,
 }
 
+/// How to treat decoded fields that fail validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Fail decoding.
+    Reject,
+    /// Decode, but keep the problem for inspection.
+    #[default]
+    Warn,
+    /// Do not validate.
+    Accept,
+}
+
 pub struct VinOpts {}
 
 impl gbk::Options for VinOpts {
     const LENGTH: usize = 17;
 }
 
-pub type Vin = gbk::GBKString<VinOpts>;
+pub use crate::packet::vin::Vin;
 
 pub struct IccidOpts {}
 
@@ -37,6 +40,32 @@ impl gbk::Options for IccidOpts {
     const LENGTH: usize = 20;
 }
 
-pub type Iccid = gbk::GBKString<IccidOpts>;
+pub use crate::packet::iccid::Iccid;
+
+/// Code of a rechargeable energy storage subsystem; the length of the codes
+/// is given by the vehicle login, up to this maximum.
+pub struct SubsysCodeOpts {}
+
+impl gbk::Options for SubsysCodeOpts {
+    const LENGTH: usize = 50;
+}
+
+pub type SubsysCode = gbk::GBKString<SubsysCodeOpts>;
+
+pub struct UsernameOpts {}
+
+impl gbk::Options for UsernameOpts {
+    const LENGTH: usize = 12;
+}
+
+pub type Username = gbk::GBKString<UsernameOpts>;
+
+pub struct PasswordOpts {}
+
+impl gbk::Options for PasswordOpts {
+    const LENGTH: usize = 20;
+}
+
+pub type Password = gbk::GBKString<PasswordOpts>;
 
 pub mod info {}
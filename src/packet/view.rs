@@ -0,0 +1,308 @@
+//! Borrowed views over an encoded packet.
+//!
+//! [`Packet`] only validates the framing and keeps the body as a slice of the
+//! input; bodies are decoded on request and cell voltages of real time
+//! reports are not decoded until they are accessed.
+
+use alloc::borrow::Cow;
+use core::str;
+
+use crate::packet::body::Body;
+use crate::packet::crypto::Cipher;
+use crate::packet::error::{Error, Result};
+use crate::packet::keys::KeyProvider;
+use crate::packet::realtime::{self, VOLTAGES};
+use crate::packet::{
+    parser, types, vin, Command, Encrypt, Response, Strictness, BEGIN, HEADER_LEN,
+};
+use crate::serde::read::SliceRead;
+use crate::serde::{self, gbk, Deserializer, Positioned};
+
+/// Length of the VIN field in the header.
+const VIN_LENGTH: usize = 17;
+
+/// Length of the collection time preceding real time items.
+const TIME_LENGTH: usize = 6;
+
+#[derive(Debug, Clone)]
+pub struct Packet<'a> {
+    pub command: Command,
+    pub response: Response,
+    /// Borrowed from the input unless decoding changed it.
+    pub vin: Cow<'a, str>,
+    /// Validation problem of the VIN when parsed with [`Strictness::Warn`].
+    pub vin_issue: Option<vin::Error>,
+    pub encrypt: Encrypt,
+    pub body: &'a [u8],
+    pub bcc: u8,
+}
+
+impl<'a> Packet<'a> {
+    /// Checks the framing of `data` without decoding the body.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        Self::parse_with(data, &parser::Options::default())
+    }
+
+    pub fn parse_with(data: &'a [u8], options: &parser::Options) -> Result<Self> {
+        let mut de = Deserializer::from_slice(data);
+        let begin = de.deserialize_u16()?;
+        if begin != BEGIN {
+            return Err(Error::Begin(begin));
+        }
+        let command = Command::from(de.deserialize_u8()?);
+        let response: Response = ::serde::Deserialize::deserialize(&mut de)?;
+        let vin = de.field("Header", |de| de.field("vin", decode_vin))?;
+        let vin_issue = match options.vin {
+            Strictness::Reject => {
+                vin::validate(&vin).map_err(|e| {
+                    Error::from(e).positioned(parser::VIN_OFFSET, "Header.vin".into())
+                })?;
+                None
+            }
+            Strictness::Warn => vin::validate(&vin).err(),
+            Strictness::Accept => None,
+        };
+        let encrypt: Encrypt = ::serde::Deserialize::deserialize(&mut de)?;
+        let body_len = de.deserialize_u16()?;
+        let body = de.borrow_bytes(body_len as usize)?;
+        let bcc = de.deserialize_u8()?;
+        if !de.remaining().is_empty() {
+            return Err(Error::TrailingBytes(de.remaining().len()));
+        }
+        parser::verify_checksum(data)?;
+        Ok(Self {
+            command,
+            response,
+            vin,
+            vin_issue,
+            encrypt,
+            body,
+            bcc,
+        })
+    }
+
+    /// Decodes the whole body, which must not be encrypted.
+    pub fn decode_body(&self) -> Result<Body> {
+        self.decode_body_with(&parser::Options::default())
+    }
+
+    pub fn decode_body_with(&self, options: &parser::Options) -> Result<Body> {
+        self.decode_body_with_cipher(options, None)
+    }
+
+    /// Decrypts the body with `cipher` unless the header says it is plain.
+    pub fn decode_body_with_cipher(
+        &self,
+        options: &parser::Options,
+        cipher: Option<&dyn Cipher>,
+    ) -> Result<Body> {
+        let body = parser::decrypt_body(self.encrypt, self.body, cipher)?;
+        parser::parse_body_with(self.command, &body, options)
+    }
+
+    /// Decrypts the body with the key `keys` have for the VIN unless the
+    /// header says it is plain.
+    pub fn decode_body_with_keys(
+        &self,
+        options: &parser::Options,
+        keys: &dyn KeyProvider,
+    ) -> Result<Body> {
+        let cipher = match self.encrypt {
+            Encrypt::None => None,
+            encrypt => keys.cipher(&self.vin, encrypt),
+        };
+        self.decode_body_with_cipher(options, cipher.as_deref())
+    }
+
+    /// Lazily decoded body of real time and reissue reports; encrypted
+    /// bodies have to go through
+    /// [`decode_body_with_cipher`](Self::decode_body_with_cipher) instead.
+    pub fn real_time_report(&self) -> Result<RealTimeReport<'a>> {
+        parser::decrypt_body(self.encrypt, self.body, None)?;
+        match self.command {
+            Command::RealTimeReport | Command::ReissueReport => RealTimeReport::parse(self.body),
+            _ => Err(Error::Unimplemented),
+        }
+    }
+}
+
+/// Decodes the VIN like the parser does, borrowing it from the input when
+/// the decoded text is the same as the bytes.
+fn decode_vin<'a>(de: &mut Deserializer<SliceRead<'a>>) -> serde::Result<Cow<'a, str>> {
+    let bytes = de.borrow_bytes(VIN_LENGTH)?;
+    let (text, _) = gbk::Layout::of::<types::VinOpts>().decode(bytes)?;
+    let raw = bytes
+        .get(..text.len())
+        .and_then(|raw| str::from_utf8(raw).ok());
+    Ok(match raw {
+        Some(raw) if raw == text => Cow::Borrowed(raw),
+        _ => Cow::Owned(text),
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RealTimeReport<'a> {
+    pub at: types::Time,
+    items: &'a [u8],
+}
+
+impl<'a> RealTimeReport<'a> {
+    pub fn parse(body: &'a [u8]) -> Result<Self> {
+        let mut de = Deserializer::from_slice(body);
+        let at: types::Time = ::serde::Deserialize::deserialize(&mut de)?;
+        Ok(Self {
+            at,
+            items: de.remaining(),
+        })
+    }
+
+    /// Items in their order in the packet; offsets in errors assume the
+    /// report was the body of a packet.
+    pub fn items(&self) -> Items<'a> {
+        Items {
+            de: Deserializer::from_slice(self.items).with_offset(HEADER_LEN + TIME_LENGTH),
+            index: 0,
+        }
+    }
+}
+
+/// Iterator over the items of a [`RealTimeReport`], stopping at the first
+/// error.
+pub struct Items<'a> {
+    de: Deserializer<SliceRead<'a>>,
+    index: usize,
+}
+
+fn next_item<'a>(de: &mut Deserializer<SliceRead<'a>>) -> Result<Item<'a>> {
+    let kind = de.deserialize_u8()?;
+    if kind != VOLTAGES {
+        return realtime::Item::deserialize(kind, de).map(Item::Decoded);
+    }
+
+    let data = de.remaining();
+    let count = de.deserialize_u8()?;
+    for i in 0..count as usize {
+        de.element(i, SubsystemVoltage::parse)?;
+    }
+    let len = data.len() - de.remaining().len();
+    Ok(Item::Voltages(Voltages {
+        data: &data[1..len],
+        count,
+    }))
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = Result<Item<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.de.remaining().is_empty() {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        let item = self.de.field("RealTimeReport", |de| {
+            de.field("items", |de| de.element(index, next_item))
+        });
+        if item.is_err() {
+            self.de = Deserializer::from_slice(&[]);
+        }
+        Some(item)
+    }
+}
+
+#[derive(Debug)]
+pub enum Item<'a> {
+    Voltages(Voltages<'a>),
+    Decoded(realtime::Item),
+}
+
+/// Cell voltages of all subsystems, still in their encoded form.
+#[derive(Debug, Clone, Copy)]
+pub struct Voltages<'a> {
+    data: &'a [u8],
+    count: u8,
+}
+
+impl<'a> Voltages<'a> {
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn subsystems(&self) -> impl Iterator<Item = SubsystemVoltage<'a>> {
+        let mut de = Deserializer::from_slice(self.data);
+        // the layout has been checked when the item was split off
+        (0..self.count).filter_map(move |_| SubsystemVoltage::parse(&mut de).ok())
+    }
+
+    pub fn decode(&self) -> realtime::Voltages {
+        realtime::Voltages {
+            subsystems: self.subsystems().map(|s| s.decode()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SubsystemVoltage<'a> {
+    pub sn: u8,
+    pub voltage: u16,
+    pub current: u16,
+    pub cell_count: u16,
+    pub frame_start: u16,
+    cells: &'a [u8],
+}
+
+impl<'a> SubsystemVoltage<'a> {
+    fn parse(de: &mut Deserializer<SliceRead<'a>>) -> Result<Self> {
+        let sn = de.deserialize_u8()?;
+        let voltage = de.deserialize_u16()?;
+        let current = de.deserialize_u16()?;
+        let cell_count = de.deserialize_u16()?;
+        let frame_start = de.deserialize_u16()?;
+        let frame_cells = de.deserialize_u8()?;
+        let cells = de.borrow_bytes(frame_cells as usize * 2)?;
+        Ok(Self {
+            sn,
+            voltage,
+            current,
+            cell_count,
+            frame_start,
+            cells,
+        })
+    }
+
+    /// Number of cells in this frame.
+    pub fn len(&self) -> usize {
+        self.cells.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Voltage of the `index`th cell in this frame, in 0.001 V.
+    pub fn cell(&self, index: usize) -> Option<u16> {
+        let raw = self.cells.get(index * 2..index * 2 + 2)?;
+        Some(u16::from_be_bytes([raw[0], raw[1]]))
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = u16> + 'a {
+        self.cells
+            .chunks_exact(2)
+            .map(|raw| u16::from_be_bytes([raw[0], raw[1]]))
+    }
+
+    pub fn decode(&self) -> realtime::SubsystemVoltage {
+        realtime::SubsystemVoltage {
+            sn: self.sn,
+            voltage: self.voltage,
+            current: self.current,
+            cell_count: self.cell_count,
+            frame_start: self.frame_start,
+            cells: self.cells().collect(),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Vehicle identification numbers per ISO 3779 and GB 16735.
+//!
+//! | positions | part                                  |
+//! |-----------|---------------------------------------|
+//! | 1-3       | world manufacturer identifier (WMI)   |
+//! | 4-8       | vehicle descriptor section (VDS)      |
+//! | 9         | check digit                           |
+//! | 10        | model year                            |
+//! | 11        | plant code                            |
+//! | 12-17     | serial number                         |
+
+use alloc::string::String;
+use core::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::packet::types::{Strictness, VinOpts};
+use crate::serde::gbk::GBKString;
+
+pub const LENGTH: usize = 17;
+
+/// Weight of every position in the check digit sum.
+const WEIGHTS: [u32; LENGTH] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+
+/// Model year codes starting from 2010; the cycle repeats every 30 years.
+const YEARS: &[u8; 30] = b"ABCDEFGHJKLMNPRSTVWXY123456789";
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    #[error("VIN has {0} characters instead of 17")]
+    Length(usize),
+
+    #[error("invalid character {ch:?} at position {position} of VIN")]
+    Char { position: usize, ch: char },
+
+    #[error("invalid model year code {0:?} in VIN")]
+    ModelYear(char),
+
+    #[error("VIN check digit is {actual:?} but should be {expected:?}")]
+    CheckDigit { expected: char, actual: char },
+}
+
+pub struct Vin {
+    inner: GBKString<VinOpts>,
+    issue: Option<Error>,
+}
+
+impl Vin {
+    /// Validates `vin` before accepting it.
+    pub fn new(vin: &str) -> Result<Self, Error> {
+        validate(vin)?;
+        Ok(Self::new_unchecked(String::from(vin)))
+    }
+
+    pub fn new_unchecked(vin: String) -> Self {
+        Self {
+            inner: GBKString::from(vin),
+            issue: None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.inner.message.as_str()
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        validate(self.as_str())
+    }
+
+    /// Applies `strictness` to the decoded VIN: rejecting it, remembering
+    /// the problem in [`issue`](Self::issue), or not looking at all.
+    pub fn check(&mut self, strictness: Strictness) -> Result<(), Error> {
+        match strictness {
+            Strictness::Reject => self.validate(),
+            Strictness::Warn => {
+                self.issue = self.validate().err();
+                Ok(())
+            }
+            Strictness::Accept => Ok(()),
+        }
+    }
+
+    /// Problem found by [`check`](Self::check) with [`Strictness::Warn`].
+    pub fn issue(&self) -> Option<Error> {
+        self.issue
+    }
+
+    /// World manufacturer identifier.
+    pub fn wmi(&self) -> Option<&str> {
+        self.part(0, 3)
+    }
+
+    /// Vehicle descriptor section, including the check digit.
+    pub fn vds(&self) -> Option<&str> {
+        self.part(3, 9)
+    }
+
+    /// Vehicle indicator section.
+    pub fn vis(&self) -> Option<&str> {
+        self.part(9, 17)
+    }
+
+    pub fn check_digit(&self) -> Option<char> {
+        self.char_at(8)
+    }
+
+    pub fn model_year_code(&self) -> Option<char> {
+        self.char_at(9)
+    }
+
+    /// Model year, assuming the 2010 to 2039 cycle.
+    pub fn model_year(&self) -> Option<u16> {
+        let code = self.model_year_code()?;
+        model_year(code)
+    }
+
+    pub fn plant_code(&self) -> Option<char> {
+        self.char_at(10)
+    }
+
+    pub fn serial(&self) -> Option<&str> {
+        self.part(11, 17)
+    }
+
+    fn part(&self, start: usize, end: usize) -> Option<&str> {
+        if self.as_str().len() != LENGTH {
+            return None;
+        }
+        self.as_str().get(start..end)
+    }
+
+    fn char_at(&self, index: usize) -> Option<char> {
+        self.part(index, index + 1)?.chars().next()
+    }
+}
+
+/// Checks length, alphabet, model year and check digit of `vin`.
+pub fn validate(vin: &str) -> Result<(), Error> {
+    let len = vin.chars().count();
+    if len != LENGTH {
+        return Err(Error::Length(len));
+    }
+    for (i, ch) in vin.chars().enumerate() {
+        if transliterate(ch).is_none() {
+            return Err(Error::Char {
+                position: i + 1,
+                ch,
+            });
+        }
+    }
+    let year = vin.as_bytes()[9] as char;
+    if model_year(year).is_none() {
+        return Err(Error::ModelYear(year));
+    }
+    let expected = compute_check_digit(vin).ok_or(Error::Length(len))?;
+    let actual = vin.as_bytes()[8] as char;
+    if expected != actual {
+        return Err(Error::CheckDigit { expected, actual });
+    }
+    Ok(())
+}
+
+/// Check digit for the 17 character `vin`, ignoring its current one.
+pub fn compute_check_digit(vin: &str) -> Option<char> {
+    if vin.chars().count() != LENGTH {
+        return None;
+    }
+    let mut sum = 0;
+    for (ch, weight) in vin.chars().zip(WEIGHTS.iter()) {
+        sum += transliterate(ch)? * weight;
+    }
+    match sum % 11 {
+        10 => Some('X'),
+        n => core::char::from_digit(n, 10),
+    }
+}
+
+/// Numeric value of a VIN character; `I`, `O` and `Q` are not allowed.
+fn transliterate(ch: char) -> Option<u32> {
+    let value = match ch {
+        '0'..='9' => ch as u32 - '0' as u32,
+        'A' | 'J' => 1,
+        'B' | 'K' | 'S' => 2,
+        'C' | 'L' | 'T' => 3,
+        'D' | 'M' | 'U' => 4,
+        'E' | 'N' | 'V' => 5,
+        'F' | 'W' => 6,
+        'G' | 'P' | 'X' => 7,
+        'H' | 'Y' => 8,
+        'R' | 'Z' => 9,
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn model_year(code: char) -> Option<u16> {
+    let index = YEARS.iter().position(|&c| c as char == code)?;
+    Some(2010 + index as u16)
+}
+
+impl Serialize for Vin {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Vin {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let inner = GBKString::deserialize(deserializer)?;
+        Ok(Self { inner, issue: None })
+    }
+}
+
+impl fmt::Display for Vin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Vin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
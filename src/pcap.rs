@@ -0,0 +1,564 @@
+//! Frames in captured traffic (feature `pcap`).
+//!
+//! [`Frames`] reads a pcap or pcapng capture, reassembles every TCP
+//! connection in both directions and splits the streams into frames with
+//! the [`Framer`], handing them out with the time of the segment that
+//! completed them and the addresses of sender and receiver. Frames that
+//! fail their check byte are taken for a false start marker and skipped.
+//!
+//! Ethernet (with VLAN tags), Linux cooked (v1 and v2), raw IP and loopback
+//! captures are understood; packets of other link types, other transports
+//! and fragmented IP packets are skipped. Segments arriving out of order are
+//! put back in order; data lost from the capture is skipped once too many
+//! segments wait for it, and the framer resynchronizes on the next frame.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::packet::error::Error as DecodeError;
+use crate::packet::parser;
+use crate::packet::stream::{Chunk, Framer};
+use crate::packet::Packet;
+
+/// Segments kept waiting for lost data before it is skipped.
+const MAX_PENDING: usize = 256;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("not a pcap or pcapng capture")]
+    Format,
+
+    #[error("corrupt block at offset {0}")]
+    Block(usize),
+
+    #[error("capture is cut off at offset {0}")]
+    Truncated(usize),
+
+    #[error("{src} > {dst}: {source}")]
+    Decode {
+        at: Duration,
+        src: SocketAddr,
+        dst: SocketAddr,
+        source: Box<DecodeError>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Frame sent from `src` to `dst`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Capture time of the segment completing the frame, since the Unix
+    /// epoch.
+    pub at: Duration,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+/// Frames of a capture in the order they were completed.
+#[derive(Debug)]
+pub struct Frames {
+    reader: Reader,
+    streams: HashMap<(SocketAddr, SocketAddr), Stream>,
+    ready: VecDeque<Frame>,
+    done: bool,
+    error: Option<Error>,
+}
+
+impl Frames {
+    /// Reads the capture at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(std::fs::read(path)?)
+    }
+
+    /// Reads the capture in `data`.
+    pub fn new(data: Vec<u8>) -> Result<Self> {
+        Ok(Self {
+            reader: Reader::new(data)?,
+            streams: HashMap::new(),
+            ready: VecDeque::new(),
+            done: false,
+            error: None,
+        })
+    }
+
+    /// Decodes the frames, as `(time, src, dst, packet)`.
+    pub fn packets(
+        self,
+        options: parser::Options,
+    ) -> impl Iterator<Item = Result<(Duration, SocketAddr, SocketAddr, Packet)>> {
+        self.map(move |frame| {
+            let frame = frame?;
+            match parser::parse_bytes_with(&frame.data, &options) {
+                Ok(packet) => Ok((frame.at, frame.src, frame.dst, packet)),
+                Err(source) => Err(Error::Decode {
+                    at: frame.at,
+                    src: frame.src,
+                    dst: frame.dst,
+                    source: Box::new(source),
+                }),
+            }
+        })
+    }
+
+    fn segment(&mut self, at: Duration, segment: Segment) {
+        let stream = self.streams.entry((segment.src, segment.dst)).or_default();
+        for (at, data) in stream.receive(at, &segment) {
+            self.ready.push_back(Frame {
+                at,
+                src: segment.src,
+                dst: segment.dst,
+                data,
+            });
+        }
+    }
+
+    /// Hands out what waits behind data lost at the end of the capture.
+    fn finish(&mut self) {
+        for (&(src, dst), stream) in &mut self.streams {
+            for (at, data) in stream.finish() {
+                self.ready.push_back(Frame { at, src, dst, data });
+            }
+        }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Result<Frame>> {
+        loop {
+            if let Some(frame) = self.ready.pop_front() {
+                return Some(Ok(frame));
+            }
+            if self.done {
+                // frames completed before a broken packet come first
+                return self.error.take().map(Err);
+            }
+            match self.reader.next_packet() {
+                Ok(Some((at, link, range))) => {
+                    if let Some(segment) = decode_link(link, &self.reader.data[range]) {
+                        self.segment(at, segment);
+                    }
+                }
+                Ok(None) => {
+                    self.done = true;
+                    self.finish();
+                }
+                Err(err) => {
+                    self.done = true;
+                    self.finish();
+                    self.error = Some(err);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    link: u32,
+    /// Time stamp units in a second.
+    resolution: u64,
+}
+
+#[derive(Debug)]
+enum Format {
+    Pcap {
+        big: bool,
+        interface: Interface,
+    },
+    PcapNg {
+        big: bool,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// Packets of a capture with their time and link type.
+#[derive(Debug)]
+struct Reader {
+    data: Vec<u8>,
+    pos: usize,
+    format: Format,
+}
+
+const PCAPNG_SECTION: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+impl Reader {
+    fn new(data: Vec<u8>) -> Result<Self> {
+        let magic = data.get(..4).ok_or(Error::Format)?;
+        let magic = u32::from_le_bytes(magic.try_into().expect("four bytes"));
+        let (big, resolution) = match magic {
+            0xa1b2_c3d4 => (false, 1_000_000),
+            0xa1b2_3c4d => (false, 1_000_000_000),
+            0xd4c3_b2a1 => (true, 1_000_000),
+            0x4d3c_b2a1 => (true, 1_000_000_000),
+            PCAPNG_SECTION => {
+                return Ok(Self {
+                    data,
+                    pos: 0,
+                    format: Format::PcapNg {
+                        big: false,
+                        interfaces: Vec::new(),
+                    },
+                })
+            }
+            _ => return Err(Error::Format),
+        };
+        let link = u32_at(&data, 20, big).ok_or(Error::Format)? & 0xffff;
+        Ok(Self {
+            data,
+            pos: 24,
+            format: Format::Pcap {
+                big,
+                interface: Interface { link, resolution },
+            },
+        })
+    }
+
+    /// Next packet as its time, link type and place in the data.
+    fn next_packet(&mut self) -> Result<Option<(Duration, u32, std::ops::Range<usize>)>> {
+        match self.format {
+            Format::Pcap { big, interface } => {
+                let pos = self.pos;
+                if pos == self.data.len() {
+                    return Ok(None);
+                }
+                let header = |offset| u32_at(&self.data, pos + offset, big);
+                let (secs, frac, len) = match (header(0), header(4), header(8)) {
+                    (Some(secs), Some(frac), Some(len)) => (secs, frac, len as usize),
+                    _ => return Err(Error::Truncated(pos)),
+                };
+                let start = pos + 16;
+                if start + len > self.data.len() {
+                    return Err(Error::Truncated(pos));
+                }
+                self.pos = start + len;
+                let ticks = secs as u64 * interface.resolution + frac as u64;
+                let at = time(ticks, interface.resolution);
+                Ok(Some((at, interface.link, start..start + len)))
+            }
+            Format::PcapNg { .. } => self.next_block(),
+        }
+    }
+
+    fn next_block(&mut self) -> Result<Option<(Duration, u32, std::ops::Range<usize>)>> {
+        loop {
+            let pos = self.pos;
+            if pos == self.data.len() {
+                return Ok(None);
+            }
+            let kind = u32_at(&self.data, pos, false).ok_or(Error::Truncated(pos))?;
+            if kind == PCAPNG_SECTION {
+                let order = u32_at(&self.data, pos + 8, false).ok_or(Error::Truncated(pos))?;
+                let big = match order {
+                    PCAPNG_BYTE_ORDER => false,
+                    _ if order.swap_bytes() == PCAPNG_BYTE_ORDER => true,
+                    _ => return Err(Error::Block(pos)),
+                };
+                self.format = Format::PcapNg {
+                    big,
+                    interfaces: Vec::new(),
+                };
+            }
+            let big = match self.format {
+                Format::PcapNg { big, .. } => big,
+                Format::Pcap { .. } => unreachable!("pcapng blocks in a pcap capture"),
+            };
+            let kind = u32_at(&self.data, pos, big).ok_or(Error::Truncated(pos))?;
+            let len = u32_at(&self.data, pos + 4, big).ok_or(Error::Truncated(pos))? as usize;
+            if len < 12 || !len.is_multiple_of(4) {
+                return Err(Error::Block(pos));
+            }
+            if pos + len > self.data.len() {
+                return Err(Error::Truncated(pos));
+            }
+            self.pos = pos + len;
+            let body = pos + 8..pos + len - 4;
+            match kind {
+                // interface description
+                1 => {
+                    let interface = self.interface(body).ok_or(Error::Block(pos))?;
+                    if let Format::PcapNg { interfaces, .. } = &mut self.format {
+                        interfaces.push(interface);
+                    }
+                }
+                // enhanced packet
+                6 => {
+                    let field = |offset| u32_at(&self.data, body.start + offset, big);
+                    let (id, high, low, len) = match (field(0), field(4), field(8), field(12)) {
+                        (Some(id), Some(high), Some(low), Some(len)) => (id, high, low, len),
+                        _ => return Err(Error::Block(pos)),
+                    };
+                    let start = body.start + 20;
+                    let end = start + len as usize;
+                    if end > body.end {
+                        return Err(Error::Block(pos));
+                    }
+                    let interface = self.interface_at(id).ok_or(Error::Block(pos))?;
+                    let ticks = (high as u64) << 32 | low as u64;
+                    let at = time(ticks, interface.resolution);
+                    return Ok(Some((at, interface.link, start..end)));
+                }
+                // simple packet, without a time
+                3 => {
+                    let len = u32_at(&self.data, body.start, big).ok_or(Error::Block(pos))?;
+                    let start = body.start + 4;
+                    if start > body.end {
+                        return Err(Error::Block(pos));
+                    }
+                    let end = (start + len as usize).min(body.end);
+                    let interface = self.interface_at(0).ok_or(Error::Block(pos))?;
+                    return Ok(Some((Duration::ZERO, interface.link, start..end)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn interface_at(&self, id: u32) -> Option<Interface> {
+        match &self.format {
+            Format::PcapNg { interfaces, .. } => interfaces.get(id as usize).copied(),
+            Format::Pcap { interface, .. } => Some(*interface),
+        }
+    }
+
+    fn interface(&self, body: std::ops::Range<usize>) -> Option<Interface> {
+        let big = matches!(self.format, Format::PcapNg { big: true, .. });
+        let data = self.data.get(body)?;
+        let link = u16_at(data, 0, big)? as u32;
+        let mut resolution = 1_000_000;
+        let mut pos = 8;
+        while let (Some(code), Some(len)) = (u16_at(data, pos, big), u16_at(data, pos + 2, big)) {
+            let value = data.get(pos + 4..pos + 4 + len as usize)?;
+            match code {
+                0 => break,
+                // if_tsresol: a negative power of ten, or of two if the
+                // high bit is set
+                9 => {
+                    let exp = *value.first()? as u32;
+                    resolution = if exp & 0x80 == 0 {
+                        10u64.checked_pow(exp)?
+                    } else {
+                        1u64.checked_shl(exp & 0x7f)?
+                    };
+                }
+                _ => {}
+            }
+            pos += 4 + (len as usize).div_ceil(4) * 4;
+        }
+        Some(Interface { link, resolution })
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize, big: bool) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?.try_into().ok()?;
+    Some(if big {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn u32_at(data: &[u8], pos: usize, big: bool) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?.try_into().ok()?;
+    Some(if big {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn time(ticks: u64, resolution: u64) -> Duration {
+    let nanos = (ticks % resolution) as u128 * 1_000_000_000 / resolution as u128;
+    Duration::new(ticks / resolution, nanos as u32)
+}
+
+const SYN: u8 = 0x02;
+const RST: u8 = 0x04;
+
+/// TCP segment of a captured packet.
+#[derive(Debug)]
+struct Segment {
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    flags: u8,
+    payload: Vec<u8>,
+}
+
+/// Finds the TCP segment in a packet of link type `link`.
+fn decode_link(link: u32, data: &[u8]) -> Option<Segment> {
+    match link {
+        // Ethernet
+        1 => {
+            let mut pos = 12;
+            let mut ether_type = u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?);
+            // VLAN tags
+            while ether_type == 0x8100 || ether_type == 0x88a8 {
+                pos += 4;
+                ether_type = u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?);
+            }
+            decode_ip(data.get(pos + 2..)?)
+        }
+        // Linux cooked capture v1 and v2
+        113 => decode_ip(data.get(16..)?),
+        276 => decode_ip(data.get(20..)?),
+        // BSD loopback, with the address family in four bytes
+        0 | 108 => decode_ip(data.get(4..)?),
+        // raw IP
+        12 | 14 | 101 | 228 | 229 => decode_ip(data),
+        _ => None,
+    }
+}
+
+fn decode_ip(data: &[u8]) -> Option<Segment> {
+    match data.first()? >> 4 {
+        4 => {
+            let header_len = (data[0] & 0x0f) as usize * 4;
+            let total_len = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?) as usize;
+            let fragment = u16::from_be_bytes(data.get(6..8)?.try_into().ok()?);
+            // more fragments or a fragment offset
+            if fragment & 0x3fff != 0 || *data.get(9)? != 6 {
+                return None;
+            }
+            let src: [u8; 4] = data.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = data.get(16..20)?.try_into().ok()?;
+            // Ethernet pads short packets
+            let end = total_len.min(data.len());
+            let tcp = data.get(header_len..end)?;
+            decode_tcp(Ipv4Addr::from(src).into(), Ipv4Addr::from(dst).into(), tcp)
+        }
+        6 => {
+            let payload_len = u16::from_be_bytes(data.get(4..6)?.try_into().ok()?) as usize;
+            let src: [u8; 16] = data.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = data.get(24..40)?.try_into().ok()?;
+            let mut next = *data.get(6)?;
+            let end = (40 + payload_len).min(data.len());
+            let mut pos = 40;
+            // hop by hop, routing and destination options headers
+            while matches!(next, 0 | 43 | 60) {
+                next = *data.get(pos)?;
+                pos += (*data.get(pos + 1)? as usize + 1) * 8;
+            }
+            if next != 6 {
+                return None;
+            }
+            let tcp = data.get(pos..end)?;
+            decode_tcp(Ipv6Addr::from(src).into(), Ipv6Addr::from(dst).into(), tcp)
+        }
+        _ => None,
+    }
+}
+
+fn decode_tcp(src: IpAddr, dst: IpAddr, data: &[u8]) -> Option<Segment> {
+    let src_port = u16::from_be_bytes(data.get(0..2)?.try_into().ok()?);
+    let dst_port = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?);
+    let seq = u32::from_be_bytes(data.get(4..8)?.try_into().ok()?);
+    let header_len = (*data.get(12)? >> 4) as usize * 4;
+    let flags = *data.get(13)?;
+    Some(Segment {
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        seq,
+        flags,
+        payload: data.get(header_len..)?.to_vec(),
+    })
+}
+
+/// One direction of a TCP connection.
+#[derive(Debug, Default)]
+struct Stream {
+    /// Sequence number of the next byte, once known.
+    next: Option<u32>,
+    /// Segments ahead of `next`.
+    pending: Vec<(u32, Duration, Vec<u8>)>,
+    framer: Framer,
+}
+
+impl Stream {
+    /// Takes in `segment` captured `at`, returning the completed frames.
+    fn receive(&mut self, at: Duration, segment: &Segment) -> Vec<(Duration, Vec<u8>)> {
+        if segment.flags & (SYN | RST) != 0 {
+            // a new connection, or the end of the old one
+            *self = Stream::default();
+            if segment.flags & SYN != 0 {
+                self.next = Some(segment.seq.wrapping_add(1));
+            }
+            return Vec::new();
+        }
+        if segment.payload.is_empty() {
+            return Vec::new();
+        }
+        // a capture started in the middle of the connection
+        self.next.get_or_insert(segment.seq);
+        self.pending
+            .push((segment.seq, at, segment.payload.clone()));
+        self.reassemble(false)
+    }
+
+    /// Everything still waiting, skipping lost data.
+    fn finish(&mut self) -> Vec<(Duration, Vec<u8>)> {
+        let mut frames = Vec::new();
+        while !self.pending.is_empty() {
+            frames.extend(self.reassemble(true));
+        }
+        frames
+    }
+
+    /// Feeds the framer the segments that are next in order; with `skip`,
+    /// or when too many wait, moves on over lost data. Frames come with the
+    /// time of the segment that completed them, the latest one fed so far.
+    fn reassemble(&mut self, skip: bool) -> Vec<(Duration, Vec<u8>)> {
+        let mut frames = Vec::new();
+        let mut latest = Duration::ZERO;
+        while let Some(next) = self.next {
+            // distance of every segment from `next`, negative when it
+            // starts before
+            let ahead = |seq: u32| seq.wrapping_sub(next) as i32;
+            let due = self.pending.iter().position(|(seq, ..)| ahead(*seq) <= 0);
+            let (i, from) = match due {
+                Some(i) => (i, next),
+                None if self.pending.is_empty() => break,
+                None if skip || self.pending.len() > MAX_PENDING => {
+                    let (i, _) = self
+                        .pending
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, (seq, ..))| ahead(*seq))
+                        .expect("pending segments");
+                    // the frame in the making lost its middle
+                    self.framer = Framer::new();
+                    (i, self.pending[i].0)
+                }
+                None => break,
+            };
+            let (seq, at, payload) = self.pending.swap_remove(i);
+            latest = latest.max(at);
+            let overlap = from.wrapping_sub(seq) as usize;
+            if overlap < payload.len() {
+                self.framer.extend(&payload[overlap..]);
+                self.next = Some(seq.wrapping_add(payload.len() as u32));
+            }
+            while let Some(chunk) = self.framer.next() {
+                match chunk {
+                    Chunk::Frame(frame) if parser::verify_checksum(&frame).is_err() => {
+                        self.framer.resync(frame)
+                    }
+                    Chunk::Frame(frame) => frames.push((latest, frame)),
+                    Chunk::Garbage(_) => {}
+                }
+            }
+        }
+        frames
+    }
+}
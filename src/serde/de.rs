@@ -1,59 +1,289 @@
-use std::io::Read;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str;
+#[cfg(feature = "std")]
+use std::io;
 
-use byteorder::{BigEndian, ReadBytesExt};
-use encoding::all::GBK;
-use encoding::{DecoderTrap, Encoding};
 use serde::{de, Deserialize};
 
-use crate::serde::error::{Error, Result};
+use crate::serde::error::{Error, Positioned, Result};
 use crate::serde::gbk;
+#[cfg(feature = "std")]
+use crate::serde::read::IoRead;
+use crate::serde::read::{self, Reference, SliceRead};
 
-pub fn from_str<'de, T: de::Deserialize<'de>>(s: &str) -> Result<T> {
+pub fn from_str<T: de::DeserializeOwned>(s: &str) -> Result<T> {
     let buff = hex::decode(s).map_err(Error::from)?;
     from_bytes(buff.as_slice())
 }
 
-pub fn from_bytes<'de, T: Deserialize<'de>>(buff: &[u8]) -> Result<T> {
-    let mut deserializer = Deserializer { reader: buff };
+/// Deserializes `T` directly from `buff`, lending out borrowed strings and
+/// bytes instead of copying them.
+pub fn from_bytes<'de, T: Deserialize<'de>>(buff: &'de [u8]) -> Result<T> {
+    let mut deserializer = Deserializer::from_slice(buff);
     T::deserialize(&mut deserializer)
 }
 
-pub struct Deserializer<R: Read> {
-    reader: R,
+#[cfg(feature = "std")]
+pub fn from_reader<R: io::Read, T: de::DeserializeOwned>(reader: R) -> Result<T> {
+    let mut deserializer = Deserializer::from_reader(reader);
+    T::deserialize(&mut deserializer)
+}
+
+pub struct Deserializer<R> {
+    read: R,
+    scratch: Vec<u8>,
+    offset: usize,
+    path: Vec<Segment>,
+    spans: Option<Vec<Span>>,
+}
+
+/// Bytes read for one field by an annotating deserializer, see
+/// [`Deserializer::annotated`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    /// Path of the field, such as `RealTimeReport.items[1].motors[0].speed`.
+    pub path: String,
+    pub raw: Vec<u8>,
+    /// Decoded value: numbers in decimal, text quoted and other bytes in hex.
+    pub value: String,
+}
+
+impl Span {
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    pub fn end(&self) -> usize {
+        self.offset + self.raw.len()
+    }
+}
+
+/// Records a span if `spans` is collecting them; empty fields have none.
+fn record<F: FnOnce() -> String>(
+    spans: &mut Option<Vec<Span>>,
+    path: &[Segment],
+    offset: usize,
+    raw: &[u8],
+    value: F,
+) {
+    if let Some(spans) = spans.as_mut().filter(|_| !raw.is_empty()) {
+        spans.push(Span {
+            offset,
+            path: path_string(path),
+            raw: raw.to_vec(),
+            value: value(),
+        });
+    }
+}
+
+fn path_string(path: &[Segment]) -> String {
+    let mut string = String::new();
+    for segment in path.iter() {
+        match segment {
+            Segment::Field(name) if string.is_empty() => string.push_str(name),
+            Segment::Field(name) => {
+                string.push('.');
+                string.push_str(name);
+            }
+            Segment::Index(index) => string.push_str(&format!("[{}]", index)),
+        }
+    }
+    string
 }
 
-impl<R: Read> Deserializer<R> {
+/// Step in the path from the decoded value to the failing field.
+enum Segment {
+    Field(&'static str),
+    Index(usize),
+}
+
+impl<'de, R: read::Read<'de>> Deserializer<R> {
     pub fn new(read: R) -> Self {
-        Self { reader: read }
+        Self {
+            read,
+            scratch: Vec::new(),
+            offset: 0,
+            path: Vec::new(),
+            spans: None,
+        }
+    }
+
+    /// Records a [`Span`] for every number and fixed length field read.
+    pub fn annotated(mut self) -> Self {
+        self.spans = Some(Vec::new());
+        self
+    }
+
+    /// Spans recorded so far, leaving the deserializer recording afresh.
+    pub fn take_spans(&mut self) -> Vec<Span> {
+        self.spans.as_mut().map(core::mem::take).unwrap_or_default()
+    }
+
+    /// Replaces the decoded value of the last recorded span.
+    fn annotate_last(&mut self, value: String) {
+        if let Some(span) = self.spans.as_mut().and_then(|spans| spans.last_mut()) {
+            span.value = value;
+        }
+    }
+
+    /// Reports error offsets as if the input started `offset` bytes later,
+    /// for decoding a part of a larger buffer.
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
     }
+
+    /// Offset of the next byte to read.
+    pub fn position(&self) -> usize {
+        self.offset + self.read.position()
+    }
+
+    /// Runs `f` to decode the field `name`, tagging errors with its position.
+    pub fn field<T, E, F>(&mut self, name: &'static str, f: F) -> core::result::Result<T, E>
+    where
+        E: Positioned,
+        F: FnOnce(&mut Self) -> core::result::Result<T, E>,
+    {
+        self.scope(Segment::Field(name), f)
+    }
+
+    /// Runs `f` to decode the `index`th element of a sequence, tagging errors
+    /// with its position.
+    pub fn element<T, E, F>(&mut self, index: usize, f: F) -> core::result::Result<T, E>
+    where
+        E: Positioned,
+        F: FnOnce(&mut Self) -> core::result::Result<T, E>,
+    {
+        self.scope(Segment::Index(index), f)
+    }
+
+    fn scope<T, E, F>(&mut self, segment: Segment, f: F) -> core::result::Result<T, E>
+    where
+        E: Positioned,
+        F: FnOnce(&mut Self) -> core::result::Result<T, E>,
+    {
+        let start = self.position();
+        self.path.push(segment);
+        let ret = match f(self) {
+            Err(e) if !e.is_positioned() => Err(e.positioned(start, path_string(&self.path))),
+            ret => ret,
+        };
+        self.path.pop();
+        ret
+    }
+
+    fn visit_struct<V: de::Visitor<'de>>(
+        &mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access {
+            deserializer: self,
+            fields: Some(fields),
+            index: 0,
+            len: fields.len(),
+        })
+    }
+
     pub fn deserialize_u8(&mut self) -> Result<u8> {
-        self.reader.read_u8().map_err(Error::from)
+        let offset = self.position();
+        let value = self.read.read_u8()?;
+        record(&mut self.spans, &self.path, offset, &[value], || {
+            value.to_string()
+        });
+        Ok(value)
     }
     pub fn deserialize_u16(&mut self) -> Result<u16> {
-        self.reader.read_u16::<BigEndian>().map_err(Error::from)
+        let offset = self.position();
+        let mut buff = [0u8; 2];
+        self.read.read_exact(&mut buff)?;
+        let value = u16::from_be_bytes(buff);
+        record(&mut self.spans, &self.path, offset, &buff, || {
+            value.to_string()
+        });
+        Ok(value)
     }
     pub fn deserialize_u32(&mut self) -> Result<u32> {
-        self.reader.read_u32::<BigEndian>().map_err(Error::from)
+        let offset = self.position();
+        let mut buff = [0u8; 4];
+        self.read.read_exact(&mut buff)?;
+        let value = u32::from_be_bytes(buff);
+        record(&mut self.spans, &self.path, offset, &buff, || {
+            value.to_string()
+        });
+        Ok(value)
     }
 
     pub fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
-        let mut buff: Vec<u8> = Vec::with_capacity(len);
-        buff.resize(len, 0);
-        self.reader.read_exact(buff.as_mut_slice())?;
-        Ok(buff)
-    }
-
-    fn read_until_string_end(&mut self) -> Result<Vec<u8>> {
-        let mut ret = Vec::new();
-        loop {
-            let d = self.reader.read_u8().map_err(Error::from)?;
-            if d == 0x00 {
-                break;
-            } else {
-                ret.push(d)
-            }
+        self.read_slice(len).map(|data| data.to_vec())
+    }
+
+    /// Reads `len` bytes, borrowing them from the input when possible.
+    pub fn read_slice(&mut self, len: usize) -> Result<Reference<'de, '_, [u8]>> {
+        let offset = self.position();
+        let data = self.read.read_bytes(len, &mut self.scratch)?;
+        record(&mut self.spans, &self.path, offset, &data, || {
+            hex::encode(&*data)
+        });
+        Ok(data)
+    }
+
+    /// Reads a fixed length string in `O::CHARSET`, keeping the typed
+    /// decoding error.
+    pub fn deserialize_gbk_string<O: gbk::Options>(&mut self) -> Result<gbk::GBKString<O>> {
+        let data = self.read_slice(O::LENGTH)?;
+        let string = gbk::GBKString::decode(&data).map_err(Error::from)?;
+        if O::LENGTH > 0 {
+            self.annotate_last(format!("{:?}", string.message));
         }
-        Ok(ret)
+        Ok(string)
+    }
+
+    /// Reads a string of `len` bytes instead of `O::LENGTH`.
+    pub fn deserialize_gbk_string_len<O: gbk::Options>(
+        &mut self,
+        len: usize,
+    ) -> Result<gbk::GBKString<O>> {
+        let data = self.read_slice(len)?;
+        let string = gbk::GBKString::decode_len(&data).map_err(Error::from)?;
+        if len > 0 {
+            self.annotate_last(format!("{:?}", string.message));
+        }
+        Ok(string)
+    }
+
+    fn read_until_string_end(&mut self) -> Result<Reference<'de, '_, [u8]>> {
+        self.read.read_until(0x00, &mut self.scratch)
+    }
+}
+
+impl<'de> Deserializer<SliceRead<'de>> {
+    pub fn from_slice(slice: &'de [u8]) -> Self {
+        Self::new(SliceRead::new(slice))
+    }
+
+    /// Borrows the next `len` bytes of the input for the whole `'de` lifetime.
+    pub fn borrow_bytes(&mut self, len: usize) -> Result<&'de [u8]> {
+        self.read.borrow_bytes(len)
+    }
+
+    /// The part of the input that has not been consumed yet.
+    pub fn remaining(&self) -> &'de [u8] {
+        self.read.remaining()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Deserializer<IoRead<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(IoRead::new(reader))
     }
 }
 
@@ -86,40 +316,37 @@ macro_rules! deserialize_unsupported_3 {
     };
 }
 
-macro_rules! deserialize_unsupported_4 {
-    ($deserialize:ident ) => {
-        fn $deserialize<V: de::Visitor<'de>>(
-            self,
-            _name: &'static str,
-            _len: usize,
-            _visitor: V,
-        ) -> Result<V::Value> {
-            Err(Error::Unsupported)
-        }
-    };
-}
-
-impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
+impl<'de, R: read::Read<'de>> de::Deserializer<'de> for &mut Deserializer<R> {
     type Error = Error;
 
     fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_string(visitor)
+        match self.read_until_string_end()? {
+            Reference::Borrowed(b) if b.is_ascii() => {
+                visitor.visit_borrowed_str(str::from_utf8(b).map_err(Error::from)?)
+            }
+            Reference::Copied(c) if c.is_ascii() => {
+                visitor.visit_str(str::from_utf8(c).map_err(Error::from)?)
+            }
+            buff => {
+                let (value, _) = gbk::decode(gbk::Charset::Gb18030, &buff, false)?;
+                visitor.visit_string(value)
+            }
+        }
     }
 
     fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        let buff = self.read_until_string_end()?;
-        let gbk = GBK
-            .decode(buff.as_slice(), DecoderTrap::Strict)
-            .map_err(|_| Error::GBK)?;
-        visitor.visit_string(gbk)
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_string(visitor)
+        match self.read_until_string_end()? {
+            Reference::Borrowed(b) => visitor.visit_borrowed_bytes(b),
+            Reference::Copied(c) => visitor.visit_bytes(c),
+        }
     }
 
     fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_string(visitor)
+        self.deserialize_bytes(visitor)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
@@ -134,12 +361,35 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
         }
     }
 
+    /// Fixed length strings announce themselves with a [`gbk::TOKEN`] name
+    /// carrying their packed [`gbk::Layout`]. They are decoded here, keeping
+    /// the typed error, and handed to the visitor as text, or as raw bytes
+    /// if lossy decoding damaged them.
     fn deserialize_newtype_struct<V: de::Visitor<'de>>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value> {
-        visitor.visit_newtype_struct(self)
+        let layout = match gbk::Layout::from_name(name) {
+            Some(layout) => layout,
+            None => return visitor.visit_newtype_struct(self),
+        };
+        let offset = self.position();
+        let data = self.read.read_bytes(layout.length, &mut self.scratch)?;
+        record(&mut self.spans, &self.path, offset, &data, || {
+            let lossy = gbk::Layout {
+                lossy: true,
+                ..layout
+            };
+            match lossy.decode(&data) {
+                Ok((text, _)) => format!("{:?}", text),
+                Err(_) => hex::encode(&*data),
+            }
+        });
+        match layout.decode(&data)? {
+            (text, false) => visitor.visit_string(text),
+            (_, true) => visitor.visit_bytes(&data),
+        }
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
@@ -154,44 +404,38 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     where
         V: de::Visitor<'de>,
     {
-        struct Access<'a, R: 'a + Read> {
-            deserializer: &'a mut Deserializer<R>,
-            len: usize,
-        }
-
-        impl<'de, 'a, R: Read + 'a> de::SeqAccess<'de> for Access<'a, R> {
-            type Error = Error;
-            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
-            where
-                T: de::DeserializeSeed<'de>,
-            {
-                if self.len > 0 {
-                    self.len -= 1;
-                    let value = de::DeserializeSeed::deserialize(seed, &mut *self.deserializer)?;
-                    Ok(Some(value))
-                } else {
-                    Ok(None)
-                }
-            }
-
-            fn size_hint(&self) -> Option<usize> {
-                Some(self.len)
-            }
-        }
-
         visitor.visit_seq(Access {
             deserializer: self,
+            fields: None,
+            index: 0,
             len,
         })
     }
 
-    fn deserialize_struct<V: de::Visitor<'de>>(
+    fn deserialize_tuple_struct<V>(
         self,
         _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    /// The outermost struct names the root of error paths.
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value> {
-        self.deserialize_tuple(fields.len(), visitor)
+        if self.path.is_empty() {
+            self.field(name, |de| de.visit_struct(fields, visitor))
+        } else {
+            self.visit_struct(fields, visitor)
+        }
     }
 
     fn deserialize_enum<V>(
@@ -226,13 +470,9 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     deserialize_unsupported!(deserialize_identifier);
 
     deserialize_unsupported_3!(deserialize_unit_struct);
-    deserialize_unsupported_4!(deserialize_tuple_struct);
 }
 
-impl<'de, 'a, R> serde::de::VariantAccess<'de> for &'a mut Deserializer<R>
-where
-    R: Read,
-{
+impl<'de, R: read::Read<'de>> serde::de::VariantAccess<'de> for &mut Deserializer<R> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
@@ -261,25 +501,56 @@ where
     }
 }
 
-struct SeqAccess<'a, R: 'a + Read> {
+struct Access<'a, R> {
+    deserializer: &'a mut Deserializer<R>,
+    fields: Option<&'static [&'static str]>,
+    index: usize,
+    len: usize,
+}
+
+impl<'de, R: read::Read<'de>> de::SeqAccess<'de> for Access<'_, R> {
+    type Error = Error;
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let segment = match self.fields {
+            Some(fields) => Segment::Field(fields[self.index]),
+            None => Segment::Index(self.index),
+        };
+        self.index += 1;
+        self.deserializer
+            .scope(segment, |de| de::DeserializeSeed::deserialize(seed, de))
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
+}
+
+struct SeqAccess<'a, R> {
     de: &'a mut Deserializer<R>,
+    index: usize,
 }
 
-impl<'a, R: 'a + Read> SeqAccess<'a, R> {
+impl<'a, R> SeqAccess<'a, R> {
     pub fn new(de: &'a mut Deserializer<R>) -> Self {
-        Self { de }
+        Self { de, index: 0 }
     }
 }
 
-impl<'a, 'de, R: 'a + Read> serde::de::SeqAccess<'de> for SeqAccess<'a, R> {
+impl<'de, R: read::Read<'de>> serde::de::SeqAccess<'de> for SeqAccess<'_, R> {
     type Error = Error;
-    fn next_element_seed<T: de::DeserializeSeed<'de>>(
-        &mut self,
-        seed: T,
-    ) -> Result<Option<T::Value>>
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de).map(Some)
+        let index = self.index;
+        self.index += 1;
+        self.de.element(index, |de| seed.deserialize(de)).map(Some)
     }
 }
@@ -1,8 +1,14 @@
-use std::fmt::Display;
-use std::{io, str, string};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use core::fmt::Display;
+use core::str;
+#[cfg(feature = "std")]
+use std::io;
 
 use serde::{de, ser};
 
+use crate::serde::gbk;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("failed with reason: {0}")]
@@ -11,23 +17,101 @@ pub enum Error {
     #[error("unsupported type for serde")]
     Unsupported,
 
+    #[error("unexpected end of input")]
+    Eof,
+
+    #[cfg(feature = "std")]
     #[error(transparent)]
-    Io(#[from] io::Error),
+    Io(io::Error),
 
     #[error(transparent)]
     Utf8(#[from] str::Utf8Error),
 
     #[error(transparent)]
-    FromUtf8(#[from] string::FromUtf8Error),
+    FromUtf8(#[from] alloc::string::FromUtf8Error),
 
-    #[error("GBK decode/encode")]
-    GBK,
+    #[error(transparent)]
+    Gbk(#[from] gbk::Error),
 
-    #[error("GBK string too large")]
-    GbkTooLarge,
+    #[error("invalid hex string: {0}")]
+    HexString(hex::FromHexError),
 
-    #[error(transparent)]
-    HexString(#[from] hex::FromHexError),
+    #[error("{source} at offset {offset} while decoding {path}")]
+    At {
+        offset: usize,
+        path: String,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Offset of the field that failed to decode.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::At { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Path of the field that failed to decode, such as `Header.vin`.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::At { path, .. } => Some(path.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Short snake case name of the kind of error, such as `eof`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Custom(_) => "custom",
+            Error::Unsupported => "unsupported",
+            Error::Eof => "eof",
+            #[cfg(feature = "std")]
+            Error::Io(_) => "io",
+            Error::Utf8(_) | Error::FromUtf8(_) => "utf8",
+            Error::Gbk(_) => "gbk",
+            Error::HexString(_) => "hex_string",
+            Error::At { source, .. } => source.kind(),
+        }
+    }
+}
+
+/// Errors that can be tagged with the position at which decoding failed.
+pub trait Positioned: Sized {
+    fn is_positioned(&self) -> bool;
+
+    fn positioned(self, offset: usize, path: String) -> Self;
+}
+
+impl Positioned for Error {
+    fn is_positioned(&self) -> bool {
+        matches!(self, Error::At { .. })
+    }
+
+    fn positioned(self, offset: usize, path: String) -> Self {
+        Error::At {
+            offset,
+            path,
+            source: Box::new(self),
+        }
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(err: hex::FromHexError) -> Self {
+        Error::HexString(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::Eof,
+            _ => Error::Io(err),
+        }
+    }
 }
 
 impl ser::Error for Error {
@@ -48,4 +132,4 @@ impl de::Error for Error {
     }
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
@@ -1,33 +1,375 @@
-use std::fmt;
-use std::marker::PhantomData;
+#[cfg(feature = "gbk")]
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::str;
 
-use encoding::all::GB18030;
-use encoding::{DecoderTrap, EncoderTrap, Encoding};
 use serde::de::{SeqAccess, Visitor};
 use serde::{Deserializer, Serializer};
 
+/// Prefix of the newtype struct name under which [`GBKString`] hands its
+/// packed [`Layout`], in hex, to the crate's serializer and deserializer.
+/// Other formats see a newtype struct around a plain string.
+pub const TOKEN: &str = "$vin::private::GBKString";
+
+/// Newtype struct name of `GBKString<O>`: [`TOKEN`] and the layout of `O`.
+struct Name<O>(PhantomData<O>);
+
+impl<O: Options> Name<O> {
+    const BYTES: [u8; TOKEN.len() + 8] = {
+        let mut bytes = [0; TOKEN.len() + 8];
+        let mut i = 0;
+        while i < TOKEN.len() {
+            bytes[i] = TOKEN.as_bytes()[i];
+            i += 1;
+        }
+        let layout = Layout::of::<O>().pack();
+        while i < bytes.len() {
+            let shift = 4 * (bytes.len() - 1 - i);
+            bytes[i] = b"0123456789abcdef"[(layout >> shift) as usize & 0xf];
+            i += 1;
+        }
+        bytes
+    };
+
+    const NAME: &'static str = match str::from_utf8(&Self::BYTES) {
+        Ok(name) => name,
+        Err(_) => panic!("the name is ASCII"),
+    };
+}
+
+/// Character set of the encoded bytes.
+///
+/// Without the `gbk` feature, GBK and GB18030 are limited to their ASCII
+/// subset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Gbk,
+    Gb18030,
+    /// Only 7 bit ASCII is accepted.
+    Ascii,
+    /// UTF-8 bytes written and read without transcoding.
+    Raw,
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Charset::Gbk => "GBK",
+            Charset::Gb18030 => "GB18030",
+            Charset::Ascii => "ASCII",
+            Charset::Raw => "UTF-8",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What to do with strings longer than the field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Error,
+    /// Drop whole characters from the end until the string fits.
+    Truncate,
+}
+
+/// Layout of a fixed length string field, shared by every code path that
+/// reads or writes it.
 pub trait Options {
     const LENGTH: usize;
+
+    const CHARSET: Charset = Charset::Gb18030;
+
+    /// Fills the field after the string; decoding strips it again.
+    const PADDING: u8 = 0x00;
+
+    const OVERFLOW: Overflow = Overflow::Error;
+
+    /// Replace undecodable bytes instead of failing, marking the string as
+    /// damaged.
+    const LOSSY: bool = false;
+}
+
+/// Same as `O`, but decoding lossily.
+pub struct Lossy<O>(PhantomData<O>);
+
+impl<O: Options> Options for Lossy<O> {
+    const LENGTH: usize = O::LENGTH;
+    const CHARSET: Charset = O::CHARSET;
+    const PADDING: u8 = O::PADDING;
+    const OVERFLOW: Overflow = O::OVERFLOW;
+    const LOSSY: bool = true;
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("{value:?} cannot be encoded as {charset}")]
+    Encode { charset: Charset, value: String },
+
+    #[error("bytes {} are not valid {charset}", hex::encode_upper(.bytes))]
+    Decode { charset: Charset, bytes: Vec<u8> },
+
+    #[error("expected {expected} bytes of string, got {actual}")]
+    Length { expected: usize, actual: usize },
+
+    #[error("{value:?} needs {len} bytes, more than the {max} available")]
+    TooLong {
+        value: String,
+        len: usize,
+        max: usize,
+    },
+}
+
+/// Encodes `value` in `charset`, without any padding.
+pub fn encode(charset: Charset, value: &str) -> Result<Vec<u8>, Error> {
+    let encoded = match charset {
+        // ASCII is the same in every supported charset
+        _ if value.is_ascii() => Some(value.as_bytes().to_vec()),
+        Charset::Raw => Some(value.as_bytes().to_vec()),
+        #[cfg(feature = "gbk")]
+        Charset::Gbk | Charset::Gb18030 => {
+            let (encoded, _, errors) = table(charset).encode(value);
+            if errors {
+                None
+            } else {
+                Some(encoded.into_owned())
+            }
+        }
+        _ => None,
+    };
+    encoded.ok_or_else(|| Error::Encode {
+        charset,
+        value: value.to_string(),
+    })
+}
+
+/// Decodes `bytes` in `charset`. Lossy decoding replaces invalid sequences
+/// with U+FFFD and reports whether it had to.
+pub fn decode(charset: Charset, bytes: &[u8], lossy: bool) -> Result<(String, bool), Error> {
+    let strict = match charset {
+        _ if bytes.is_ascii() => str::from_utf8(bytes).ok().map(String::from),
+        Charset::Raw => str::from_utf8(bytes).ok().map(String::from),
+        #[cfg(feature = "gbk")]
+        Charset::Gbk | Charset::Gb18030 => table(charset)
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(Cow::into_owned),
+        _ => None,
+    };
+    if let Some(value) = strict {
+        return Ok((value, false));
+    }
+    if !lossy {
+        return Err(Error::Decode {
+            charset,
+            bytes: bytes.to_vec(),
+        });
+    }
+    let replaced = match charset {
+        Charset::Raw => String::from_utf8_lossy(bytes).into_owned(),
+        #[cfg(feature = "gbk")]
+        Charset::Gbk | Charset::Gb18030 => table(charset)
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned(),
+        _ => bytes
+            .iter()
+            .map(|&b| if b.is_ascii() { b as char } else { '\u{fffd}' })
+            .collect(),
+    };
+    Ok((replaced, true))
+}
+
+#[cfg(feature = "gbk")]
+fn table(charset: Charset) -> &'static encoding_rs::Encoding {
+    match charset {
+        Charset::Gbk => encoding_rs::GBK,
+        _ => encoding_rs::GB18030,
+    }
 }
 
 pub struct GBKString<O> {
     pub message: String,
+    damaged: bool,
     _marker: PhantomData<O>,
 }
 
+impl<O: Options> Default for GBKString<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<O: Options> GBKString<O> {
     pub fn new() -> Self {
-        Self {
-            message: String::new(),
-            _marker: PhantomData,
-        }
+        Self::from(String::new())
     }
     pub fn from(src: String) -> Self {
         Self {
             message: src,
+            damaged: false,
             _marker: PhantomData,
         }
     }
+
+    /// Whether invalid bytes were replaced while decoding lossily.
+    pub fn is_damaged(&self) -> bool {
+        self.damaged
+    }
+
+    /// Encodes the string into exactly `O::LENGTH` bytes.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        Layout::of::<O>().encode(self.message.as_str())
+    }
+
+    /// Encodes the string into exactly `len` bytes, for fields whose length
+    /// is given by the packet rather than by `O::LENGTH`.
+    pub fn encode_len(&self, len: usize) -> Result<Vec<u8>, Error> {
+        Layout::of::<O>()
+            .with_length(len)
+            .encode(self.message.as_str())
+    }
+
+    /// Decodes `O::LENGTH` bytes, ignoring everything from the first NUL on
+    /// as well as trailing padding.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != O::LENGTH {
+            return Err(Error::Length {
+                expected: O::LENGTH,
+                actual: bytes.len(),
+            });
+        }
+        Self::decode_len(bytes)
+    }
+
+    /// Decodes all of `bytes`, for fields whose length is given by the
+    /// packet rather than by `O::LENGTH`.
+    pub fn decode_len(bytes: &[u8]) -> Result<Self, Error> {
+        let (message, damaged) = Layout::of::<O>().decode(bytes)?;
+        Ok(Self {
+            damaged,
+            ..Self::from(message)
+        })
+    }
+}
+
+/// [`Options`] of a field as a value. [`GBKString`] packs it into its
+/// [`TOKEN`] name so that the crate's serializer and deserializer code the
+/// field themselves and keep the typed [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub length: usize,
+    pub charset: Charset,
+    pub padding: u8,
+    pub overflow: Overflow,
+    pub lossy: bool,
+}
+
+impl Layout {
+    pub const fn of<O: Options>() -> Self {
+        Self {
+            length: O::LENGTH,
+            charset: O::CHARSET,
+            padding: O::PADDING,
+            overflow: O::OVERFLOW,
+            lossy: O::LOSSY,
+        }
+    }
+
+    pub fn with_length(self, length: usize) -> Self {
+        Self { length, ..self }
+    }
+
+    /// Encodes `value` into exactly `self.length` bytes.
+    pub fn encode(&self, value: &str) -> Result<Vec<u8>, Error> {
+        let mut buff = encode(self.charset, value)?;
+        if buff.len() > self.length {
+            match self.overflow {
+                Overflow::Error => {
+                    return Err(Error::TooLong {
+                        value: value.to_string(),
+                        len: buff.len(),
+                        max: self.length,
+                    })
+                }
+                Overflow::Truncate => buff = self.truncated(value)?,
+            }
+        }
+        buff.resize(self.length, self.padding);
+        Ok(buff)
+    }
+
+    /// Encoding of the longest prefix of whole characters that fits.
+    fn truncated(&self, value: &str) -> Result<Vec<u8>, Error> {
+        let mut buff = Vec::with_capacity(self.length);
+        let mut c = [0u8; 4];
+        for ch in value.chars() {
+            let encoded = encode(self.charset, ch.encode_utf8(&mut c))?;
+            if buff.len() + encoded.len() > self.length {
+                break;
+            }
+            buff.extend_from_slice(&encoded);
+        }
+        Ok(buff)
+    }
+
+    /// Decodes all of `bytes`, ignoring everything from the first NUL on as
+    /// well as trailing padding, and tells whether it was damaged.
+    pub fn decode(&self, bytes: &[u8]) -> Result<(String, bool), Error> {
+        let mut end = bytes.iter().position(|&x| x == 0x00).unwrap_or(bytes.len());
+        while end > 0 && bytes[end - 1] == self.padding {
+            end -= 1;
+        }
+        decode(self.charset, &bytes[..end], self.lossy)
+    }
+
+    /// Packs the layout into the 32 bits of a length or variant index; the
+    /// length takes the low 16 of them.
+    pub(crate) const fn pack(&self) -> u32 {
+        debug_assert!(self.length <= 0xffff, "string fields are short");
+        let charset = match self.charset {
+            Charset::Gbk => 0,
+            Charset::Gb18030 => 1,
+            Charset::Ascii => 2,
+            Charset::Raw => 3,
+        };
+        let overflow = match self.overflow {
+            Overflow::Error => 0,
+            Overflow::Truncate => 1,
+        };
+        self.length as u32
+            | (self.padding as u32) << 16
+            | charset << 24
+            | overflow << 26
+            | (self.lossy as u32) << 27
+    }
+
+    /// Layout packed into a [`TOKEN`] name, if `name` is one.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let hex = name.strip_prefix(TOKEN)?;
+        u32::from_str_radix(hex, 16).ok().map(Self::unpack)
+    }
+
+    fn unpack(packed: u32) -> Self {
+        let charset = match (packed >> 24) & 0b11 {
+            0 => Charset::Gbk,
+            1 => Charset::Gb18030,
+            2 => Charset::Ascii,
+            _ => Charset::Raw,
+        };
+        let overflow = match (packed >> 26) & 1 {
+            0 => Overflow::Error,
+            _ => Overflow::Truncate,
+        };
+        Self {
+            length: (packed & 0xffff) as usize,
+            charset,
+            padding: (packed >> 16) as u8,
+            overflow,
+            lossy: (packed >> 27) & 1 == 1,
+        }
+    }
 }
 
 impl<O> serde::Serialize for GBKString<O>
@@ -38,11 +380,7 @@ where
     where
         S: Serializer,
     {
-        let mut buff = GB18030
-            .encode(self.message.as_str(), EncoderTrap::Strict)
-            .unwrap();
-        buff.resize(O::LENGTH, 0);
-        serializer.serialize_bytes(buff.as_slice())
+        serializer.serialize_newtype_struct(Name::<O>::NAME, self.message.as_str())
     }
 }
 
@@ -55,7 +393,7 @@ where
         D: Deserializer<'de>,
     {
         let visitor = StringVisitor::new();
-        deserializer.deserialize_seq(visitor)
+        deserializer.deserialize_newtype_struct(Name::<O>::NAME, visitor)
     }
 }
 
@@ -77,31 +415,44 @@ where
 {
     type Value = GBKString<O>;
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("gbk string visitor")
+        write!(formatter, "{} bytes of {} string", O::LENGTH, O::CHARSET)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: ::serde::de::Error,
+    {
+        Ok(GBKString::from(v.to_string()))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: ::serde::de::Error,
+    {
+        if v.len() != O::LENGTH {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        GBKString::decode(v).map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_string(self)
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
-        let mut buff: Vec<u8> = Vec::with_capacity(O::LENGTH);
-        buff.resize(O::LENGTH, 0);
-        for i in 0..O::LENGTH {
-            buff[i] = seq
+        let mut buff: Vec<u8> = vec![0; O::LENGTH];
+        for (i, d) in buff.iter_mut().enumerate() {
+            *d = seq
                 .next_element()?
-                .ok_or_else(|| ::serde::de::Error::custom("hello"))?;
+                .ok_or_else(|| ::serde::de::Error::invalid_length(i, &self))?;
         }
-
-        let p = buff.iter().position(|&x| x == 0x00);
-        if p.is_some() {
-            let p = p.unwrap();
-            buff.resize(p, 0)
-        }
-
-        let message = GB18030
-            .decode(buff.as_slice(), DecoderTrap::Strict)
-            .unwrap();
-        Ok(GBKString::from(message))
+        self.visit_bytes(buff.as_slice())
     }
 }
 
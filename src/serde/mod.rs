@@ -1,8 +1,14 @@
-pub use de::{from_str, Deserializer};
-pub use error::{Error, Result};
-pub use ser::{to_string, Serializer};
+#[cfg(feature = "std")]
+pub use de::from_reader;
+pub use de::{from_bytes, from_str, Deserializer, Span};
+pub use error::{Error, Positioned, Result};
+#[cfg(feature = "std")]
+pub use ser::to_writer;
+pub use ser::{to_bytes, to_string, Serializer};
 
 mod de;
 mod error;
 pub mod gbk;
+pub mod read;
 mod ser;
+pub mod write;
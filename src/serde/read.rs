@@ -0,0 +1,183 @@
+use alloc::vec::Vec;
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::serde::error::{Error, Result};
+
+/// Input source of the [`Deserializer`](super::Deserializer).
+///
+/// Implemented by [`SliceRead`], which can lend out parts of the input for
+/// the whole `'de` lifetime, and, with the `std` feature, by `IoRead`, which
+/// copies everything it reads into a caller supplied scratch buffer.
+pub trait Read<'de> {
+    /// Number of bytes consumed so far.
+    fn position(&self) -> usize;
+
+    fn read_u8(&mut self) -> Result<u8>;
+
+    fn read_exact(&mut self, buff: &mut [u8]) -> Result<()>;
+
+    /// Reads exactly `len` bytes.
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>>;
+
+    /// Reads up to and including `end`, which is not part of the result.
+    fn read_until<'s>(
+        &'s mut self,
+        end: u8,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>>;
+}
+
+/// Bytes either borrowed from the input or copied into the scratch buffer.
+pub enum Reference<'b, 'c, T: ?Sized> {
+    Borrowed(&'b T),
+    Copied(&'c T),
+}
+
+impl<'b, 'c, T: ?Sized> Deref for Reference<'b, 'c, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            Reference::Borrowed(b) => b,
+            Reference::Copied(c) => c,
+        }
+    }
+}
+
+pub struct SliceRead<'a> {
+    slice: &'a [u8],
+    index: usize,
+}
+
+impl<'a> SliceRead<'a> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        Self { slice, index: 0 }
+    }
+
+    /// Borrows the next `len` bytes of the input.
+    pub fn borrow_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.slice.len() - self.index < len {
+            self.index = self.slice.len();
+            return Err(Error::Eof);
+        }
+        let data = &self.slice[self.index..self.index + len];
+        self.index += len;
+        Ok(data)
+    }
+
+    /// The part of the input that has not been consumed yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.slice[self.index..]
+    }
+}
+
+impl<'a> Read<'a> for SliceRead<'a> {
+    fn position(&self) -> usize {
+        self.index
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let data = self.borrow_bytes(1)?;
+        Ok(data[0])
+    }
+
+    fn read_exact(&mut self, buff: &mut [u8]) -> Result<()> {
+        let data = self.borrow_bytes(buff.len())?;
+        buff.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'a, 's, [u8]>> {
+        self.borrow_bytes(len).map(Reference::Borrowed)
+    }
+
+    fn read_until<'s>(
+        &'s mut self,
+        end: u8,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'a, 's, [u8]>> {
+        let rest = self.remaining();
+        match rest.iter().position(|&x| x == end) {
+            Some(p) => {
+                self.index += p + 1;
+                Ok(Reference::Borrowed(&rest[..p]))
+            }
+            None => {
+                self.index = self.slice.len();
+                Err(Error::Eof)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct IoRead<R: io::Read> {
+    reader: R,
+    position: usize,
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> IoRead<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            position: 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, R: io::Read> Read<'de> for IoRead<R> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let mut buff = [0u8; 1];
+        self.read_exact(&mut buff)?;
+        Ok(buff[0])
+    }
+
+    fn read_exact(&mut self, buff: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(buff).map_err(Error::from)?;
+        self.position += buff.len();
+        Ok(())
+    }
+
+    fn read_bytes<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>> {
+        scratch.clear();
+        scratch.resize(len, 0);
+        self.read_exact(scratch.as_mut_slice())?;
+        Ok(Reference::Copied(scratch.as_slice()))
+    }
+
+    fn read_until<'s>(
+        &'s mut self,
+        end: u8,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<Reference<'de, 's, [u8]>> {
+        scratch.clear();
+        loop {
+            let d = self.read_u8()?;
+            if d == end {
+                break;
+            }
+            scratch.push(d);
+        }
+        Ok(Reference::Copied(scratch.as_slice()))
+    }
+}
@@ -1,11 +1,14 @@
-use std::io::Write;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
 
-use byteorder::{BigEndian, WriteBytesExt};
-use encoding::all::GBK;
-use encoding::{EncoderTrap, Encoding};
 use serde::ser::{self, Impossible, Serialize};
 
 use crate::serde::gbk;
+#[cfg(feature = "std")]
+use crate::serde::write::IoWrite;
+use crate::serde::write::Write;
 
 use super::error::{Error, Result};
 
@@ -16,33 +19,58 @@ pub fn to_string<T: Serialize>(input: &T) -> Result<String> {
 
 pub fn to_bytes<T: Serialize>(input: &T) -> Result<Vec<u8>> {
     let mut buff = Vec::new();
-    input.serialize(&mut Serializer { writer: &mut buff })?;
+    input.serialize(&mut Serializer::new(&mut buff))?;
     Ok(buff)
 }
 
-pub struct Serializer<W: Write> {
+#[cfg(feature = "std")]
+pub fn to_writer<W: io::Write, T: Serialize>(writer: W, input: &T) -> Result<()> {
+    input.serialize(&mut Serializer::from_writer(writer))
+}
+
+pub struct Serializer<W> {
     writer: W,
+    /// Layout of the [`gbk::TOKEN`] string being written.
+    layout: Option<gbk::Layout>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Serializer<IoWrite<W>> {
+    pub fn from_writer(writer: W) -> Self {
+        Self::new(IoWrite::new(writer))
+    }
 }
 
 impl<W: Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            layout: None,
+        }
+    }
+
+    /// Writes `message` exactly like its own `Serialize` implementation,
+    /// keeping the typed error.
     pub fn serialize_gbk_string<O: gbk::Options>(
         &mut self,
         message: &gbk::GBKString<O>,
     ) -> Result<()> {
-        let mut buff = GBK
-            .encode(message.message.as_str(), EncoderTrap::Strict)
-            .map_err(|_| Error::GBK)?;
-
-        if buff.len() > O::LENGTH {
-            return Err(Error::GbkTooLarge);
-        }
+        let buff = message.encode()?;
+        self.writer.write_all(buff.as_slice())
+    }
 
-        buff.resize(O::LENGTH, 0);
-        self.writer.write_all(buff.as_slice()).map_err(Error::from)
+    /// Writes `message` into `len` bytes instead of `O::LENGTH`.
+    pub fn serialize_gbk_string_len<O: gbk::Options>(
+        &mut self,
+        message: &gbk::GBKString<O>,
+        len: usize,
+    ) -> Result<()> {
+        let buff = message.encode_len(len)?;
+        self.writer.write_all(buff.as_slice())
     }
 }
 
-impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+impl<W: Write> ser::Serializer for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -76,17 +104,17 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.writer.write_u8(v).map_err(Error::from)
+        self.writer.write_all(&[v])
     }
 
     #[inline]
     fn serialize_u16(self, v: u16) -> Result<()> {
-        self.writer.write_u16::<BigEndian>(v).map_err(Error::from)
+        self.writer.write_all(&v.to_be_bytes())
     }
 
     #[inline]
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.writer.write_u32::<BigEndian>(v).map_err(Error::from)
+        self.writer.write_all(&v.to_be_bytes())
     }
 
     fn serialize_u64(self, _: u64) -> Result<()> {
@@ -106,10 +134,11 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        let data = GBK
-            .encode(&v, EncoderTrap::Strict)
-            .map_err(|_| Error::GBK)?;
-        self.writer.write(&data)?;
+        if let Some(layout) = self.layout.take() {
+            return self.writer.write_all(&layout.encode(v)?);
+        }
+        let data = gbk::encode(gbk::Charset::Gb18030, v)?;
+        self.writer.write_all(&data)?;
         self.serialize_u8(0)?;
         Ok(())
     }
@@ -123,9 +152,9 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         Err(Error::Unsupported)
     }
 
-    fn serialize_some<T: ?Sized>(self, _: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, _: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::Unsupported)
     }
@@ -142,14 +171,20 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         Err(Error::Unsupported)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, _: &'static str, _: &T) -> Result<Self::Ok>
+    /// Fixed length strings announce themselves with a [`gbk::TOKEN`] name
+    /// carrying their packed [`gbk::Layout`].
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        Err(Error::Unsupported)
+        let layout = gbk::Layout::from_name(name).ok_or(Error::Unsupported)?;
+        self.layout = Some(layout);
+        let result = value.serialize(&mut *self);
+        self.layout = None;
+        result
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _: &'static str,
         _: u32,
@@ -157,7 +192,7 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
         _: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::Unsupported)
     }
@@ -207,14 +242,14 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 }
 
-impl<'a, W: Write> ser::SerializeStruct for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
@@ -225,14 +260,14 @@ impl<'a, W: Write> ser::SerializeStruct for &'a mut Serializer<W> {
     }
 }
 
-impl<'a, W: Write> ser::SerializeStructVariant for &'a mut Serializer<W> {
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, _: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(&mut **self)
     }
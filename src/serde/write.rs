@@ -0,0 +1,45 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::serde::error::Result;
+
+/// Output sink of the [`Serializer`](super::Serializer).
+///
+/// Implemented for `Vec<u8>` and, with the `std` feature, by [`IoWrite`]
+/// for any [`std::io::Write`].
+pub trait Write {
+    fn write_all(&mut self, buff: &[u8]) -> Result<()>;
+}
+
+impl Write for Vec<u8> {
+    fn write_all(&mut self, buff: &[u8]) -> Result<()> {
+        self.extend_from_slice(buff);
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write_all(&mut self, buff: &[u8]) -> Result<()> {
+        (**self).write_all(buff)
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct IoWrite<W: io::Write> {
+    writer: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> IoWrite<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> Write for IoWrite<W> {
+    fn write_all(&mut self, buff: &[u8]) -> Result<()> {
+        self.writer.write_all(buff).map_err(Into::into)
+    }
+}
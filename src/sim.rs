@@ -0,0 +1,592 @@
+//! Terminal simulator generating plausible traffic for load tests.
+//!
+//! Every vehicle logs in, drives along the route of its [`Profile`] in a
+//! stop-and-go cycle, sends real time reports with speed, mileage, position,
+//! a draining SOC and cell voltages to match, heart beats and the occasional
+//! alarm, and logs out once the profile's duration has passed. The
+//! [`Simulator`] yields the frames of all vehicles in time order; the output
+//! only depends on the seed.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::packet::body::{self, Body};
+use crate::packet::realtime::{self, Item, RealTimeReport};
+use crate::packet::serial::LoginSequence;
+use crate::packet::{iccid, vin, Encrypt, Iccid, Packet, Response, Time, Vin, BEGIN};
+
+/// Mean earth radius in km.
+const EARTH_RADIUS: f64 = 6371.0;
+
+/// Alarm flags raised by the simulator.
+pub const ALARM_HIGH_TEMPERATURE: u32 = 1 << 1;
+pub const ALARM_LOW_SOC: u32 = 1 << 4;
+pub const ALARM_INSULATION: u32 = 1 << 11;
+
+/// How the simulated vehicles drive and report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Seconds between real time reports.
+    pub report_interval: u32,
+    /// Seconds between heart beats, none if 0.
+    pub heartbeat_interval: u32,
+    /// Seconds from login to logout.
+    pub duration: u32,
+    /// Speed between stops, in km/h.
+    pub cruise_speed: f64,
+    /// Change of speed, in km/h per second.
+    pub acceleration: f64,
+    /// Mean seconds of driving between stops.
+    pub mean_drive: f64,
+    /// Mean seconds of a stop.
+    pub mean_stop: f64,
+    /// Waypoints as (longitude, latitude), driven back and forth.
+    pub route: Vec<(f64, f64)>,
+    /// SOC at login, in %.
+    pub soc: f64,
+    /// SOC used per km, in %.
+    pub consumption: f64,
+    /// Cells of the single battery pack.
+    pub cells: u16,
+    /// Temperature probes of the battery pack.
+    pub probes: u16,
+    /// Chance of a fault alarm per report.
+    pub alarm_chance: f64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::city()
+    }
+}
+
+impl Profile {
+    /// Stop-and-go traffic on a loop through Hangzhou.
+    pub fn city() -> Self {
+        Self {
+            report_interval: 10,
+            heartbeat_interval: 60,
+            duration: 3600,
+            cruise_speed: 45.0,
+            acceleration: 6.0,
+            mean_drive: 90.0,
+            mean_stop: 30.0,
+            route: vec![
+                (120.1551, 30.2741),
+                (120.1690, 30.2741),
+                (120.1690, 30.2840),
+                (120.1551, 30.2840),
+                (120.1551, 30.2741),
+            ],
+            soc: 90.0,
+            consumption: 0.6,
+            cells: 96,
+            probes: 16,
+            alarm_chance: 0.005,
+        }
+    }
+
+    /// Long stretches at motorway speed from Hangzhou to Shanghai.
+    pub fn highway() -> Self {
+        Self {
+            cruise_speed: 100.0,
+            acceleration: 4.0,
+            mean_drive: 1800.0,
+            mean_stop: 60.0,
+            route: vec![
+                (120.1551, 30.2741),
+                (120.7555, 30.7469),
+                (121.4737, 31.2304),
+            ],
+            consumption: 0.8,
+            ..Self::city()
+        }
+    }
+}
+
+/// Frame of one vehicle, sent at `at` seconds since the Unix epoch.
+#[derive(Debug)]
+pub struct Frame {
+    pub at: i64,
+    pub packet: Packet,
+}
+
+/// Small deterministic generator (xorshift64*), good enough for noise.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64 of the seed, never 0
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Exponentially distributed around `mean`.
+    fn exp(&mut self, mean: f64) -> f64 {
+        -mean * (1.0 - self.unit()).ln()
+    }
+
+    /// Uniform in `[-amplitude, amplitude]`.
+    fn noise(&mut self, amplitude: f64) -> f64 {
+        (self.unit() * 2.0 - 1.0) * amplitude
+    }
+}
+
+/// VINs with valid check digits for `count` simulated vehicles.
+pub fn vins(count: usize, seed: u64) -> Vec<Vin> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|i| {
+            let plant = b"ABCDEFGH"[rng.below(8) as usize] as char;
+            // model year P is 2023, the check digit replaces the 0 at 9
+            let mut text = format!("LZYEVS000P{}{:06}", plant, i % 1_000_000);
+            let check = vin::compute_check_digit(&text).expect("valid characters");
+            text.replace_range(8..9, check.encode_utf8(&mut [0; 4]));
+            Vin::new_unchecked(text)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Drive {
+    Stopped { until: i64 },
+    Driving { until: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Next {
+    Login,
+    Report,
+    HeartBeat,
+    Logout,
+    Done,
+}
+
+/// State of one simulated vehicle.
+#[derive(Debug)]
+struct Vehicle {
+    vin: Vin,
+    iccid: Iccid,
+    rng: Rng,
+    logins: LoginSequence,
+    /// Time the physical state was last advanced to.
+    now: i64,
+    end: i64,
+    next_report: i64,
+    next_heartbeat: i64,
+    logged_in: bool,
+    done: bool,
+    drive: Drive,
+    /// km/h
+    speed: f64,
+    /// km
+    mileage: f64,
+    /// %
+    soc: f64,
+    /// Segment of the route and km driven along it.
+    segment: usize,
+    along: f64,
+    /// Driving from the last waypoint back to the first.
+    reverse: bool,
+    /// Active fault alarm flags and the reports left to send them.
+    fault: u32,
+    fault_reports: u32,
+}
+
+impl Vehicle {
+    fn new(vin: Vin, index: usize, start: i64, profile: &Profile, seed: u64) -> Self {
+        let mut rng = Rng::new(seed ^ (index as u64).wrapping_mul(0x9e37_79b9));
+        let mut digits = std::string::String::from("898604");
+        for _ in 0..13 {
+            digits.push(char::from(b'0' + rng.below(10) as u8));
+        }
+        let check = iccid::compute_check_digit(&digits).expect("digits only");
+        digits.push(check);
+        // stagger logins over the first report interval
+        let start = start + rng.below(profile.report_interval.max(1) as u64) as i64;
+        let stop = rng.exp(profile.mean_stop).max(1.0) as i64;
+        Self {
+            vin,
+            iccid: Iccid::new_unchecked(digits),
+            logins: LoginSequence::new(),
+            now: start,
+            end: start + profile.duration as i64,
+            next_report: start,
+            next_heartbeat: start + profile.heartbeat_interval as i64,
+            logged_in: false,
+            done: false,
+            drive: Drive::Stopped {
+                until: start + stop,
+            },
+            speed: 0.0,
+            mileage: 1000.0 + rng.below(50_000) as f64,
+            soc: (profile.soc + rng.noise(5.0)).clamp(0.0, 100.0),
+            segment: 0,
+            along: 0.0,
+            reverse: false,
+            fault: 0,
+            fault_reports: 0,
+            rng,
+        }
+    }
+
+    fn next(&self, profile: &Profile) -> (i64, Next) {
+        if self.done {
+            return (i64::MAX, Next::Done);
+        }
+        if !self.logged_in {
+            return (self.now, Next::Login);
+        }
+        let mut next = (self.next_report, Next::Report);
+        if profile.heartbeat_interval > 0 && self.next_heartbeat < next.0 {
+            next = (self.next_heartbeat, Next::HeartBeat);
+        }
+        if self.end <= next.0 {
+            next = (self.end, Next::Logout);
+        }
+        next
+    }
+
+    /// Emits the next frame, which is due at `at`.
+    fn step(&mut self, profile: &Profile, at: i64, next: Next) -> Packet {
+        self.advance(profile, at);
+        let time = Time::from_unix(at);
+        let body = match next {
+            Next::Login => {
+                self.logged_in = true;
+                Body::VehicleLogin(body::VehicleLogin {
+                    at: time,
+                    sn: self.logins.next_login(&time),
+                    iccid: Iccid::new_unchecked(self.iccid.as_str().into()),
+                    subsys_len: 0,
+                    subsys_codes: Vec::new(),
+                })
+            }
+            Next::Report => {
+                self.next_report += profile.report_interval.max(1) as i64;
+                Body::RealTimeReport(self.report(profile, time))
+            }
+            Next::HeartBeat => {
+                self.next_heartbeat += profile.heartbeat_interval as i64;
+                Body::HeartBeat(body::Raw { data: Vec::new() })
+            }
+            Next::Logout | Next::Done => {
+                self.done = true;
+                Body::VehicleLogout(body::VehicleLogout {
+                    at: time,
+                    sn: self.logins.logout().unwrap_or(1),
+                })
+            }
+        };
+        Packet {
+            begin: BEGIN,
+            command: body.command(),
+            response: Response::Command,
+            vin: Vin::new_unchecked(self.vin.as_str().into()),
+            encrypt: Encrypt::None,
+            body_len: 0,
+            body,
+            bcc: 0,
+        }
+    }
+
+    /// Drives second by second up to `to`.
+    fn advance(&mut self, profile: &Profile, to: i64) {
+        while self.now < to {
+            self.now += 1;
+            let target = match self.drive {
+                Drive::Stopped { until } if self.now >= until => {
+                    let drive = self.rng.exp(profile.mean_drive).max(10.0) as i64;
+                    self.drive = Drive::Driving {
+                        until: self.now + drive,
+                    };
+                    profile.cruise_speed
+                }
+                Drive::Driving { until } if self.now >= until => {
+                    let stop = self.rng.exp(profile.mean_stop).max(1.0) as i64;
+                    self.drive = Drive::Stopped {
+                        until: self.now + stop,
+                    };
+                    0.0
+                }
+                Drive::Stopped { .. } => 0.0,
+                Drive::Driving { .. } => profile.cruise_speed + self.rng.noise(5.0),
+            };
+            let step = profile.acceleration.max(0.1);
+            self.speed = if self.speed < target {
+                (self.speed + step).min(target)
+            } else {
+                (self.speed - step).max(target)
+            }
+            .max(0.0);
+            let km = self.speed / 3600.0;
+            self.mileage += km;
+            self.soc = (self.soc - km * profile.consumption).max(0.0);
+            self.move_along(&profile.route, km);
+        }
+    }
+
+    fn move_along(&mut self, route: &[(f64, f64)], mut km: f64) {
+        // a route of one place keeps the vehicle there
+        let length: f64 = route.windows(2).map(|w| distance(w[0], w[1])).sum();
+        if route.len() < 2 || length <= 0.0 {
+            return;
+        }
+        while km > 0.0 {
+            let (from, to) = self.waypoints(route);
+            let left = distance(from, to) - self.along;
+            if km < left {
+                self.along += km;
+                return;
+            }
+            km -= left;
+            self.along = 0.0;
+            self.segment += 1;
+            if self.segment == route.len() - 1 {
+                self.segment = 0;
+                self.reverse = !self.reverse;
+            }
+        }
+    }
+
+    fn waypoints(&self, route: &[(f64, f64)]) -> ((f64, f64), (f64, f64)) {
+        let n = route.len() - 1;
+        if self.reverse {
+            (route[n - self.segment], route[n - self.segment - 1])
+        } else {
+            (route[self.segment], route[self.segment + 1])
+        }
+    }
+
+    fn position(&self, route: &[(f64, f64)]) -> (f64, f64) {
+        match route.len() {
+            0 => (0.0, 0.0),
+            1 => route[0],
+            _ => {
+                let (from, to) = self.waypoints(route);
+                let total = distance(from, to);
+                let t = if total > 0.0 { self.along / total } else { 0.0 };
+                (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+            }
+        }
+    }
+
+    fn report(&mut self, profile: &Profile, at: Time) -> RealTimeReport {
+        let (lon, lat) = self.position(&profile.route);
+        let speed = self.speed;
+        let soc = self.soc;
+        let rng = &mut self.rng;
+        if self.fault_reports == 0 && rng.unit() < profile.alarm_chance {
+            self.fault = if rng.below(2) == 0 {
+                ALARM_HIGH_TEMPERATURE
+            } else {
+                ALARM_INSULATION
+            };
+            self.fault_reports = 3 + rng.below(6) as u32;
+        }
+        let fault = if self.fault_reports > 0 {
+            self.fault_reports -= 1;
+            self.fault
+        } else {
+            0
+        };
+        let flags = fault | if soc < 20.0 { ALARM_LOW_SOC } else { 0 };
+        let level = match fault {
+            ALARM_INSULATION => 3,
+            ALARM_HIGH_TEMPERATURE => 2,
+            _ if flags != 0 => 1,
+            _ => 0,
+        };
+
+        let moving = speed > 0.0;
+        // cells from 3.2 V empty to 4.1 V full, in mV
+        let cell = 3200.0 + 9.0 * soc;
+        let cells: Vec<u16> = (0..profile.cells)
+            .map(|_| (cell + rng.noise(8.0)) as u16)
+            .collect();
+        // volts of the cells in series
+        let pack = cells.iter().map(|&c| c as f64).sum::<f64>() / 1000.0;
+        // amperes drawn, regenerating a little when braking
+        let current = speed * 1.6 + rng.noise(3.0);
+        let base = if fault == ALARM_HIGH_TEMPERATURE {
+            58.0
+        } else {
+            28.0
+        };
+        let probes: Vec<u8> = (0..profile.probes)
+            .map(|_| (base + speed / 20.0 + rng.noise(2.0) + 40.0) as u8)
+            .collect();
+        let (max_cell, max_cell_at) = extreme(&cells, |a, b| a > b);
+        let (min_cell, min_cell_at) = extreme(&cells, |a, b| a < b);
+        let (max_probe, max_probe_at) = extreme(&probes, |a, b| a > b);
+        let (min_probe, min_probe_at) = extreme(&probes, |a, b| a < b);
+
+        let items = vec![
+            Item::Vehicle(realtime::Vehicle {
+                status: 1,
+                charging: 3,
+                mode: 1,
+                speed: (speed * 10.0) as u16,
+                mileage: (self.mileage * 10.0) as u32,
+                voltage: (pack * 10.0) as u16,
+                current: ((current + 1000.0) * 10.0) as u16,
+                soc: soc.round() as u8,
+                dc_status: 1,
+                // drive without gear information
+                gear: if moving { 0x0e } else { 0x00 },
+                insulation: if fault == ALARM_INSULATION { 80 } else { 5000 },
+                accelerator: if moving { (speed / 2.0) as u8 } else { 0 },
+                brake: if moving { 0 } else { 20 },
+            }),
+            Item::Motors(realtime::Motors {
+                motors: vec![realtime::Motor {
+                    sn: 1,
+                    status: if moving { 1 } else { 3 },
+                    controller_temperature: (35.0 + rng.noise(2.0) + 40.0) as u8,
+                    speed: (speed * 60.0 + 20000.0) as u16,
+                    torque: ((speed * 1.5 + 2000.0) * 10.0) as u16,
+                    temperature: (45.0 + rng.noise(3.0) + 40.0) as u8,
+                    controller_voltage: (pack * 10.0) as u16,
+                    controller_current: ((current + 1000.0) * 10.0) as u16,
+                }],
+            }),
+            Item::Location(realtime::Location {
+                status: 0,
+                longitude: (lon.abs() * 1e6) as u32,
+                latitude: (lat.abs() * 1e6) as u32,
+            }),
+            Item::Extremes(realtime::Extremes {
+                max_voltage_subsys: 1,
+                max_voltage_cell: max_cell_at,
+                max_cell_voltage: max_cell,
+                min_voltage_subsys: 1,
+                min_voltage_cell: min_cell_at,
+                min_cell_voltage: min_cell,
+                max_temperature_subsys: 1,
+                max_temperature_probe: max_probe_at,
+                max_temperature: max_probe,
+                min_temperature_subsys: 1,
+                min_temperature_probe: min_probe_at,
+                min_temperature: min_probe,
+            }),
+            Item::Alarm(realtime::Alarm {
+                level,
+                flags,
+                battery_faults: Vec::new(),
+                motor_faults: Vec::new(),
+                engine_faults: Vec::new(),
+                other_faults: Vec::new(),
+            }),
+            Item::Voltages(realtime::Voltages {
+                subsystems: cells
+                    .chunks(200)
+                    .enumerate()
+                    .map(|(i, frame)| realtime::SubsystemVoltage {
+                        sn: 1,
+                        voltage: (pack * 10.0) as u16,
+                        current: ((current + 1000.0) * 10.0) as u16,
+                        cell_count: profile.cells,
+                        frame_start: (i * 200 + 1) as u16,
+                        cells: frame.to_vec(),
+                    })
+                    .collect(),
+            }),
+            Item::Temperatures(realtime::Temperatures {
+                subsystems: vec![realtime::SubsystemTemperature { sn: 1, probes }],
+            }),
+        ];
+        RealTimeReport { at, items }
+    }
+}
+
+/// Most extreme value by `better` and its number, starting from 1.
+fn extreme<T: Copy + Default>(values: &[T], better: impl Fn(T, T) -> bool) -> (T, u8) {
+    let mut best = (T::default(), 0);
+    for (i, &value) in values.iter().enumerate() {
+        if best.1 == 0 || better(value, best.0) {
+            best = (value, (i + 1).min(u8::MAX as usize) as u8);
+        }
+    }
+    best
+}
+
+/// Distance in km between two (longitude, latitude) points.
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let lat = ((a.1 + b.1) / 2.0).to_radians();
+    let x = (b.0 - a.0).to_radians() * lat.cos();
+    let y = (b.1 - a.1).to_radians();
+    (x * x + y * y).sqrt() * EARTH_RADIUS
+}
+
+/// Frames of many simulated vehicles in time order.
+#[derive(Debug)]
+pub struct Simulator {
+    profile: Profile,
+    vehicles: Vec<Vehicle>,
+    /// Next frame time and kind of every vehicle, by index.
+    queue: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl Simulator {
+    /// Simulates `vins` from `start` seconds since the Unix epoch.
+    pub fn new(vins: Vec<Vin>, profile: Profile, start: i64, seed: u64) -> Self {
+        let vehicles: Vec<Vehicle> = vins
+            .into_iter()
+            .enumerate()
+            .map(|(i, vin)| Vehicle::new(vin, i, start, &profile, seed))
+            .collect();
+        let queue = vehicles
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Reverse((v.next(&profile).0, i)))
+            .collect();
+        Self {
+            profile,
+            vehicles,
+            queue,
+        }
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+}
+
+impl Iterator for Simulator {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        let Reverse((at, index)) = self.queue.pop()?;
+        let vehicle = &mut self.vehicles[index];
+        let (_, next) = vehicle.next(&self.profile);
+        let packet = vehicle.step(&self.profile, at, next);
+        let (due, next) = vehicle.next(&self.profile);
+        if next != Next::Done {
+            self.queue.push(Reverse((due, index)));
+        }
+        Some(Frame { at, packet })
+    }
+}
@@ -0,0 +1,140 @@
+//! Destinations for decoded packets received by a server (feature `json`).
+//!
+//! Every packet becomes one JSON line holding the time it was received, the
+//! address of the terminal and the packet as mapped by
+//! [`packet::json`](crate::packet::json):
+//!
+//! ```json
+//! {"received_ms":1540902954000,"peer":"127.0.0.1:50212","packet":{...}}
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::packet::{json, Packet};
+
+/// Packet received from a terminal.
+#[derive(Debug)]
+pub struct Record<'a> {
+    /// Time of receipt since the Unix epoch.
+    pub received: Duration,
+    pub peer: &'a str,
+    pub packet: &'a Packet,
+}
+
+impl Record<'_> {
+    pub fn to_value(&self) -> Value {
+        json!({
+            "received_ms": self.received.as_millis() as u64,
+            "peer": self.peer,
+            "packet": json::to_value(self.packet),
+        })
+    }
+}
+
+/// Destination of received packets, shared by all connections.
+pub trait Sink: Send {
+    fn write(&mut self, record: &Record) -> io::Result<()>;
+}
+
+/// JSON lines on a writer such as stdout, flushed after every record.
+#[derive(Debug)]
+pub struct JsonLines<W> {
+    writer: W,
+}
+
+impl<W: Write + Send> JsonLines<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> Sink for JsonLines<W> {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        writeln!(self.writer, "{}", record.to_value())?;
+        self.writer.flush()
+    }
+}
+
+/// JSON lines in a file that is rotated once it reaches a size.
+///
+/// A full `packets.jsonl` becomes `packets.jsonl.1`, an older
+/// `packets.jsonl.1` becomes `packets.jsonl.2` and so on; only the `keep`
+/// newest rotated files are kept.
+#[derive(Debug)]
+pub struct RotatingFiles {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFiles {
+    /// Appends to the file at `path`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = append(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            keep,
+            file,
+            written,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the `n`th newest rotated file.
+    pub fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(from, self.rotated(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = append(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Sink for RotatingFiles {
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = record.to_value().to_string();
+        line.push('\n');
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
@@ -0,0 +1,98 @@
+#![allow(dead_code)]
+
+use vin::packet::parser;
+
+pub const LOGIN: &str = "232301fe4c5a595442474257364a3130313431393401001e120a1e14233600fd383938363034303231303137303031373937373901005c";
+
+/// Wraps `body` into a complete packet for the test VIN.
+pub fn frame(command: u8, body: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x23, 0x23, command, 0xfe];
+    data.extend_from_slice(b"LZYTBGBW6J1014194");
+    data.push(0x01);
+    data.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    data.extend_from_slice(body);
+    data.push(parser::checksum(&data[2..]));
+    data
+}
+
+/// Real time report body with one item of every kind but fuel cell and
+/// engine, and a custom item.
+pub fn real_time_body() -> Vec<u8> {
+    let mut body = vec![0x12, 0x0a, 0x1e, 0x14, 0x23, 0x36];
+    // vehicle
+    body.extend_from_slice(&[
+        0x01, 0x01, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x12, 0x34, 0x0e, 0x10, 0x27, 0x10, 0x50,
+        0x01, 0x0e, 0x00, 0x64, 0x00, 0x00,
+    ]);
+    // motors
+    body.extend_from_slice(&[
+        0x02, 0x01, 0x01, 0x01, 0x50, 0x4e, 0x20, 0x4e, 0x20, 0x50, 0x0e, 0x10, 0x27, 0x10,
+    ]);
+    // location
+    body.extend_from_slice(&[0x05, 0x00, 0x07, 0x2b, 0x6e, 0x10, 0x01, 0xc9, 0xc3, 0x80]);
+    // extremes
+    body.extend_from_slice(&[
+        0x06, 0x01, 0x02, 0x0c, 0xe6, 0x01, 0x01, 0x0c, 0xe4, 0x01, 0x03, 0x41, 0x01, 0x01, 0x3f,
+    ]);
+    // alarm: one battery fault
+    body.extend_from_slice(&[
+        0x07, 0x01, 0x00, 0x00, 0x00, 0x04, 0x01, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,
+    ]);
+    // voltages and temperatures of one subsystem
+    body.extend_from_slice(&[
+        0x08, 0x01, 0x01, 0x0e, 0x10, 0x27, 0x10, 0x00, 0x60, 0x00, 0x01, 0x02, 0x0c, 0xe4, 0x0c,
+        0xe5,
+    ]);
+    body.extend_from_slice(&[0x09, 0x01, 0x01, 0x00, 0x03, 0x41, 0x40, 0x3f]);
+    // custom
+    body.extend_from_slice(&[0x80, 0x00, 0x02, 0xab, 0xcd]);
+    body
+}
+
+/// Ethernet frame of an IPv4 TCP segment from port `src` to port `dst`;
+/// the higher port is the terminal at 10.0.0.1, the other the platform at
+/// 10.0.0.2.
+pub fn tcp_packet(src: u16, dst: u16, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; 12];
+    data.extend_from_slice(&[0x08, 0x00]);
+    let total_len = (20 + 20 + payload.len()) as u16;
+    data.extend_from_slice(&[0x45, 0x00]);
+    data.extend_from_slice(&total_len.to_be_bytes());
+    data.extend_from_slice(&[0, 0, 0x40, 0x00, 64, 6, 0, 0]);
+    let (terminal, platform) = ([10, 0, 0, 1], [10, 0, 0, 2]);
+    let (from, to) = if src > dst {
+        (terminal, platform)
+    } else {
+        (platform, terminal)
+    };
+    data.extend_from_slice(&from);
+    data.extend_from_slice(&to);
+    data.extend_from_slice(&src.to_be_bytes());
+    data.extend_from_slice(&dst.to_be_bytes());
+    data.extend_from_slice(&seq.to_be_bytes());
+    data.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Classic little endian pcap capture of Ethernet `packets` with their
+/// times in microseconds.
+pub fn pcap(packets: &[(u64, Vec<u8>)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for word in [0xa1b2_c3d4u32, 0x0004_0002, 0, 0, 65535, 1] {
+        data.extend_from_slice(&word.to_le_bytes());
+    }
+    for (micros, packet) in packets {
+        let len = packet.len() as u32;
+        for word in [
+            (micros / 1_000_000) as u32,
+            (micros % 1_000_000) as u32,
+            len,
+            len,
+        ] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(packet);
+    }
+    data
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::path::Path;
+
+use common::{frame, LOGIN};
+use vin::batch::{self, LineFormat, Options, Report};
+use vin::packet::Command;
+
+const VIN: &str = "LZYTBGBW6J1014194";
+
+fn logout() -> String {
+    hex::encode(frame(
+        0x04,
+        &[0x12, 0x0a, 0x1e, 0x14, 0x30, 0x00, 0x00, 0xfd],
+    ))
+}
+
+#[test]
+fn test_decode_text_counts_commands_errors_and_vins() {
+    let bad_checksum = format!("{}00", &LOGIN[..LOGIN.len() - 2]);
+    let text = [
+        format!("2018-10-30T20:35:54 {} {}", VIN, LOGIN),
+        String::new(),
+        logout(),
+        format!("2018-10-30T20:35:55 {} {}", VIN, bad_checksum),
+        "2018-10-30T20:35:56 LZYTBGBW6J1014195 232301zz".into(),
+        format!("2018-10-30T20:35:57 {} {}", VIN, &LOGIN[..40]),
+    ]
+    .join("\n");
+    let options = Options {
+        format: LineFormat {
+            vin: Some(1),
+            ..LineFormat::default()
+        },
+        ..Options::default()
+    };
+
+    let report = batch::decode_text(Path::new("day.log"), &text, &options);
+    let stats = &report.stats;
+    assert_eq!((stats.lines, stats.frames, stats.failed()), (5, 2, 3));
+    assert_eq!(stats.commands["VehicleLogin"], 1);
+    assert_eq!(stats.commands["VehicleLogout"], 1);
+    assert_eq!(stats.errors["checksum"], 1);
+    assert_eq!(stats.errors["hex_string"], 1);
+    assert_eq!(stats.errors["eof"], 1);
+    assert_eq!(stats.vins[VIN], 4);
+    assert_eq!(stats.vins["LZYTBGBW6J1014195"], 1);
+
+    let lines: Vec<_> = report.failures.iter().map(|f| f.line).collect();
+    assert_eq!(lines, [4, 5, 6]);
+    assert_eq!(report.failures[0].kind, "checksum");
+    assert!(report.failures[0].text.ends_with(&bad_checksum));
+}
+
+#[test]
+fn test_decode_files_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = dir.path().join("2018-10-30.csv");
+    let second = dir.path().join("2018-10-31.csv");
+    let lines: Vec<String> = (0..1000).map(|_| format!("{},1540902954", LOGIN)).collect();
+    std::fs::write(&first, lines.join("\n")).unwrap();
+    std::fs::write(&second, format!("{},1540989354\n,1540989355\n", logout())).unwrap();
+
+    let options = Options {
+        format: LineFormat {
+            separator: Some(','),
+            frame: Some(0),
+            vin: None,
+        },
+        ..Options::default()
+    };
+    let report = batch::decode_files(&[&first, &second], &options).unwrap();
+    assert_eq!(report.stats.frames, 1001);
+    assert_eq!(report.stats.commands["VehicleLogin"], 1000);
+    assert_eq!(report.stats.vins[VIN], 1001);
+    assert_eq!(report.stats.errors["eof"], 1);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(
+        (&report.failures[0].file, report.failures[0].line),
+        (&second, 2)
+    );
+
+    let missing = dir.path().join("2018-11-01.csv");
+    assert!(batch::decode_files(&[&first, &missing], &options).is_err());
+}
+
+#[test]
+fn test_decode_text_with_hands_out_packets_in_order() {
+    let mut lines: Vec<String> = (0..5000).map(|_| LOGIN.to_string()).collect();
+    lines.push(logout());
+    lines.push("2323".into());
+    let mut report = Report::default();
+    let mut commands = Vec::new();
+    batch::decode_text_with(
+        Path::new("day.log"),
+        &lines.join("\n"),
+        &Options::default(),
+        &mut report,
+        |packet| {
+            commands.push(packet.command);
+            Ok::<_, ()>(())
+        },
+    )
+    .unwrap();
+    assert_eq!(commands.len(), 5001);
+    assert_eq!(commands.last(), Some(&Command::VehicleLogout));
+    assert_eq!(report.stats.frames, 5001);
+    assert_eq!(report.failures[0].line, 5002);
+}
@@ -0,0 +1,71 @@
+extern crate vin;
+
+mod common;
+
+use common::{frame, real_time_body, LOGIN};
+use vin::packet::body::Body;
+use vin::packet::error::Error;
+use vin::packet::realtime::Item;
+use vin::packet::{parser, Command};
+
+#[test]
+fn test_match_and_encode_login() {
+    let data = hex::decode(LOGIN).unwrap();
+    let packet = parser::parse_bytes(&data).unwrap();
+    assert_eq!(packet.body.command(), Command::VehicleLogin);
+    match &packet.body {
+        Body::VehicleLogin(login) => assert_eq!(login.sn, 253),
+        other => panic!("unexpected body {:?}", other),
+    }
+    assert_eq!(packet.encode().unwrap(), data);
+}
+
+#[test]
+fn test_encode_real_time_report() {
+    let body = real_time_body();
+    let data = frame(0x02, &body);
+    let packet = parser::parse_bytes(&data).unwrap();
+    let report = match &packet.body {
+        Body::RealTimeReport(report) => report,
+        other => panic!("unexpected body {:?}", other),
+    };
+    let kinds: Vec<u8> = report.items.iter().map(Item::kind).collect();
+    assert_eq!(kinds, [1, 2, 5, 6, 7, 8, 9, 0x80]);
+    assert_eq!(packet.body.encode().unwrap(), body);
+    assert_eq!(packet.encode().unwrap(), data);
+}
+
+#[test]
+fn test_heart_beat_keeps_data() {
+    let data = frame(0x07, &[0x01, 0x02]);
+    let packet = parser::parse_bytes(&data).unwrap();
+    match &packet.body {
+        Body::HeartBeat(raw) => assert_eq!(raw.data, [0x01, 0x02]),
+        other => panic!("unexpected body {:?}", other),
+    }
+    assert_eq!(packet.encode().unwrap(), data);
+}
+
+#[test]
+fn test_vendor_commands_are_kept() {
+    let data = frame(0xc1, &[0x0a, 0x0b, 0x0c]);
+    let mut packet = parser::parse_bytes(&data).unwrap();
+    assert_eq!(packet.command, Command::Unknown(0xc1));
+    match &packet.body {
+        Body::Unknown { command, data } => {
+            assert_eq!(*command, 0xc1);
+            assert_eq!(data, &[0x0a, 0x0b, 0x0c]);
+        }
+        other => panic!("unexpected body {:?}", other),
+    }
+    assert_eq!(packet.encode().unwrap(), data);
+
+    packet.command = Command::HeartBeat;
+    assert!(matches!(
+        packet.encode().unwrap_err(),
+        Error::CommandMismatch {
+            command: Command::HeartBeat,
+            body: Command::Unknown(0xc1),
+        }
+    ));
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use common::{pcap, tcp_packet, LOGIN};
+
+fn decode(args: &[&str], stdin: &str) -> (bool, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vin-decode"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_decode_tree() {
+    let (ok, out) = decode(&[LOGIN], "");
+    assert!(ok);
+    assert!(out.contains("vin       LZYTBGBW6J1014194"));
+    assert!(out.contains("sn: 253"));
+}
+
+#[test]
+fn test_decode_stream_as_json() {
+    let input = format!("{}\n{}2323ab\n", LOGIN, LOGIN.to_uppercase());
+    let (ok, out) = decode(&["--stream", "--format", "json"], &input);
+    assert!(!ok);
+    let lines: Vec<serde_json::Value> = out
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1]["offset"], 55);
+    assert_eq!(lines[1]["packet"]["body"]["sn"], 253);
+    assert_eq!(lines[2]["hex"], "2323ab");
+}
+
+#[test]
+fn test_checksum_failure_still_decodes() {
+    let frame = format!("{}00", &LOGIN[..LOGIN.len() - 2]);
+    let (ok, out) = decode(&["--format", "json"], &frame);
+    assert!(!ok);
+    let value: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+    assert_eq!(value["packet"]["bcc"], 0);
+    assert_eq!(
+        value["warnings"][0],
+        "checksum mismatch, expected 0x5c but got 0x00"
+    );
+}
+
+#[test]
+fn test_dump() {
+    let (ok, out) = decode(&["--format", "dump", LOGIN], "");
+    assert!(ok);
+    assert!(out.contains("001e  00 fd                    VehicleLogin.sn"));
+}
+
+#[test]
+fn test_decode_pcap() {
+    let login = hex::decode(LOGIN).unwrap();
+    let (a, b) = login.split_at(30);
+    let capture = pcap(&[
+        (1_000, tcp_packet(50000, 32960, 1, 0x18, a)),
+        (2_000, tcp_packet(50000, 32960, 31, 0x18, b)),
+    ]);
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&capture).unwrap();
+
+    let path = file.path().to_str().unwrap();
+    let (ok, out) = decode(&["--pcap", path], "");
+    assert!(ok);
+    let value: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+    assert_eq!(value["time_ms"], 2);
+    assert_eq!(value["src"], "10.0.0.1:50000");
+    assert_eq!(value["dst"], "10.0.0.2:32960");
+    assert_eq!(value["packet"]["command"], "vehicle_login");
+}
+
+#[test]
+fn test_decode_batch() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("day.log");
+    let failed = dir.path().join("failed.tsv");
+    std::fs::write(
+        &log,
+        format!(
+            "1540902954 LZYTBGBW6J1014194 {}\n1540902955 LZYTBGBW6J1014195 2323\n",
+            LOGIN
+        ),
+    )
+    .unwrap();
+
+    let log = log.to_str().unwrap();
+    let args = ["--batch", log, "--vin-field", "2", "--format", "json"];
+    let (ok, out) = decode(
+        &[&args[..], &["--failed", failed.to_str().unwrap()]].concat(),
+        "",
+    );
+    assert!(!ok);
+    let stats: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+    assert_eq!(stats["frames"], 1);
+    assert_eq!(stats["commands"]["VehicleLogin"], 1);
+    assert_eq!(stats["errors"]["eof"], 1);
+    assert_eq!(stats["vins"]["LZYTBGBW6J1014195"], 1);
+
+    let failed = std::fs::read_to_string(failed).unwrap();
+    let fields: Vec<_> = failed.trim_end().split('\t').collect();
+    assert_eq!(fields[..3], [log, "2", "eof"]);
+    assert_eq!(fields[4], "1540902955 LZYTBGBW6J1014195 2323");
+}
+
+#[test]
+fn test_export_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("day.log");
+    let report = hex::encode(common::frame(0x02, &common::real_time_body()));
+    std::fs::write(&log, format!("{}\n{}\n", LOGIN, report)).unwrap();
+
+    let out = dir.path().join("tables");
+    let status = Command::new(env!("CARGO_BIN_EXE_vin-export"))
+        .args(["--out", out.to_str().unwrap(), log.to_str().unwrap()])
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let motors = std::fs::read_to_string(out.join("motors.csv")).unwrap();
+    assert_eq!(motors.lines().count(), 2);
+    assert!(out.join("faults.csv").exists());
+}
+
+#[test]
+fn test_export_writes_failed_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("day.log");
+    let failed = dir.path().join("failed.tsv");
+    let report = hex::encode(common::frame(0x02, &common::real_time_body()));
+    std::fs::write(&log, format!("{}\n2323zz\n", report)).unwrap();
+
+    let out = dir.path().join("tables");
+    let status = Command::new(env!("CARGO_BIN_EXE_vin-export"))
+        .args(["--out", out.to_str().unwrap()])
+        .args(["--failed", failed.to_str().unwrap()])
+        .arg(&log)
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    let motors = std::fs::read_to_string(out.join("motors.csv")).unwrap();
+    assert_eq!(motors.lines().count(), 2);
+    let failed = std::fs::read_to_string(failed).unwrap();
+    let fields: Vec<_> = failed.trim_end().split('\t').collect();
+    assert_eq!(fields[1..3], ["2", "hex_string"]);
+    assert_eq!(fields[4], "2323zz");
+}
@@ -0,0 +1,105 @@
+extern crate vin;
+
+mod common;
+
+use common::LOGIN;
+use vin::packet::crypto;
+use vin::packet::error::Error;
+use vin::packet::{parser, view, Encrypt};
+
+/// Marks `data` as encrypted with `encrypt` without touching the body.
+fn flag(mut data: Vec<u8>, encrypt: Encrypt) -> Vec<u8> {
+    data[21] = encrypt as u8;
+    let last = data.len() - 1;
+    data[last] = parser::checksum(&data[2..last]);
+    data
+}
+
+#[test]
+fn test_missing_key() {
+    let data = flag(hex::decode(LOGIN).unwrap(), Encrypt::Aes128);
+    let err = parser::parse_bytes(&data).unwrap_err();
+    assert_eq!(err.path(), Some("body"));
+    assert!(matches!(
+        err,
+        Error::At { ref source, .. }
+            if matches!(**source, Error::Crypto(crypto::Error::MissingKey(Encrypt::Aes128)))
+    ));
+
+    let packet = view::Packet::parse(&data).unwrap();
+    assert!(packet.decode_body().is_err());
+    assert!(packet.real_time_report().is_err());
+
+    let mut packet = parser::parse_bytes(&hex::decode(LOGIN).unwrap()).unwrap();
+    packet.encrypt = Encrypt::Rsa;
+    assert!(matches!(
+        packet.encode().unwrap_err(),
+        Error::Crypto(crypto::Error::MissingKey(Encrypt::Rsa))
+    ));
+}
+
+#[cfg(feature = "aes")]
+#[test]
+fn test_aes128() {
+    use vin::packet::crypto::{Aes128, Cipher};
+
+    let key: Vec<u8> = (0..16).collect();
+    let cipher = Aes128::from_slice(&key).unwrap();
+    // FIPS-197 appendix C.1, followed by a block of padding
+    let plain = hex::decode("00112233445566778899aabbccddeeff").unwrap();
+    let encrypted = cipher.encrypt(&plain).unwrap();
+    assert_eq!(
+        hex::encode(&encrypted[..16]),
+        "69c4e0d86a7b0430d8cdb78070b4c55a"
+    );
+    assert_eq!(encrypted.len(), 32);
+    assert_eq!(cipher.decrypt(&encrypted).unwrap(), plain);
+    assert!(Aes128::from_slice(&key[1..]).is_err());
+
+    let mut packet = parser::parse_bytes(&hex::decode(LOGIN).unwrap()).unwrap();
+    packet.encrypt = Encrypt::Aes128;
+    let data = packet.encode_with_cipher(Some(&cipher)).unwrap();
+    assert_eq!(data[21], 0x03);
+    assert_eq!(u16::from_be_bytes([data[22], data[23]]), 32);
+    let options = parser::Options::default();
+    let decrypted = parser::parse_bytes_with_cipher(&data, &options, Some(&cipher)).unwrap();
+    assert_eq!(
+        decrypted.body.encode().unwrap(),
+        packet.body.encode().unwrap()
+    );
+
+    let view = view::Packet::parse(&data).unwrap();
+    let body = view
+        .decode_body_with_cipher(&options, Some(&cipher))
+        .unwrap();
+    assert_eq!(body.encode().unwrap(), packet.body.encode().unwrap());
+}
+
+#[cfg(feature = "rsa")]
+#[test]
+fn test_rsa() {
+    use common::frame;
+    use vin::packet::crypto::{Cipher, Rsa};
+
+    let private = rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 512).unwrap();
+    let public = rsa::RsaPublicKey::from(&private);
+    let terminal = Rsa::from_public(public);
+    let platform = Rsa::from_private(private);
+
+    // a heart beat with a body spanning three blocks
+    let body: Vec<u8> = (0..120).collect();
+    let mut packet = parser::parse_bytes(&frame(0x07, &body)).unwrap();
+    packet.encrypt = Encrypt::Rsa;
+    let data = packet.encode_with_cipher(Some(&terminal)).unwrap();
+    assert_eq!(data.len(), 24 + 3 * 64 + 1);
+    assert!(terminal.decrypt(&data[24..data.len() - 1]).is_err());
+
+    let options = parser::Options::default();
+    let decrypted = parser::parse_bytes_with_cipher(&data, &options, Some(&platform)).unwrap();
+    assert_eq!(decrypted.body.encode().unwrap(), body);
+
+    assert!(matches!(
+        parser::parse_bytes_with_cipher(&flag(data, Encrypt::Aes128), &options, Some(&platform)),
+        Err(Error::At { .. })
+    ));
+}
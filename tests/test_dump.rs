@@ -0,0 +1,64 @@
+extern crate vin;
+
+mod common;
+
+use common::{frame, real_time_body, LOGIN};
+use vin::packet::{dump, parser, HEADER_LEN};
+
+#[test]
+fn test_annotate_login() {
+    let data = hex::decode(LOGIN).unwrap();
+    let (spans, packet) = parser::annotate(&data, &parser::Options::default());
+    assert!(packet.is_ok());
+    let vin = &spans[3];
+    assert_eq!((vin.offset, vin.len()), (4, 17));
+    assert_eq!(vin.path, "Header.vin");
+    assert_eq!(vin.value, "\"LZYTBGBW6J1014194\"");
+    let sn = spans.iter().find(|s| s.path == "VehicleLogin.sn").unwrap();
+    assert_eq!((sn.offset, sn.raw.as_slice()), (30, &[0x00, 0xfd][..]));
+    assert_eq!(sn.value, "253");
+    // every byte is covered exactly once
+    let mut end = 0;
+    for span in spans.iter() {
+        assert_eq!(span.offset, end);
+        end = span.end();
+    }
+    assert_eq!(end, data.len());
+}
+
+#[test]
+fn test_annotate_real_time_report() {
+    let data = frame(0x02, &real_time_body());
+    let (spans, _) = parser::annotate(&data, &parser::Options::default());
+    let speed = spans
+        .iter()
+        .find(|s| s.path == "RealTimeReport.items[1].motors[0].speed")
+        .unwrap();
+    assert_eq!(speed.offset, HEADER_LEN + 6 + 21 + 5);
+    assert_eq!(speed.value, "20000");
+    let last = spans.last().unwrap();
+    assert_eq!(last.path, "bcc");
+}
+
+#[test]
+fn test_render_failure() {
+    let mut data = frame(0x02, &real_time_body());
+    // unknown item kind of the motors
+    data[HEADER_LEN + 6 + 21] = 0x7f;
+    let last = data.len() - 1;
+    data[last] = parser::checksum(&data[2..last]);
+    let (text, packet) = dump::dump(&data, &parser::Options::default());
+    assert!(packet.is_err());
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "0000  23 23                    Header.begin                         8995"
+    );
+    let failed = lines
+        .iter()
+        .position(|l| l.contains("RealTimeReport.items[1].kind"))
+        .unwrap();
+    assert!(lines[failed].ends_with("127"));
+    assert!(lines[failed + 1].ends_with("(not decoded)"));
+    assert!(lines.last().unwrap().contains("bcc"));
+}
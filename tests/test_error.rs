@@ -0,0 +1,59 @@
+extern crate vin;
+
+mod common;
+
+use common::frame;
+use vin::packet::{parser, view};
+
+/// Real time report whose fourth item, the motors, ends within the torque of
+/// the second motor.
+fn truncated_body() -> Vec<u8> {
+    let mut body = vec![0x12, 0x0a, 0x1e, 0x14, 0x23, 0x36];
+    body.extend_from_slice(&[0x05, 0x00, 0x07, 0x2b, 0x6e, 0x10, 0x01, 0xc9, 0xc3, 0x80]);
+    body.extend_from_slice(&[0x04, 0x01, 0x03, 0xe8, 0x00, 0x10]);
+    body.extend_from_slice(&[0x04, 0x01, 0x03, 0xe8, 0x00, 0x10]);
+    body.extend_from_slice(&[0x02, 0x02]);
+    body.extend_from_slice(&[
+        0x01, 0x01, 0x50, 0x4e, 0x20, 0x4e, 0x20, 0x50, 0x0e, 0x10, 0x27, 0x10,
+    ]);
+    body.extend_from_slice(&[0x02, 0x01, 0x50, 0x4e, 0x20, 0x4e]);
+    body
+}
+
+#[test]
+fn test_error_offset_and_path() {
+    let data = frame(0x02, &truncated_body());
+    let err = parser::parse_bytes(data.as_slice()).unwrap_err();
+    assert_eq!(err.offset(), Some(71));
+    assert_eq!(err.path(), Some("RealTimeReport.items[3].motors[1].torque"));
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of input at offset 71 while decoding RealTimeReport.items[3].motors[1].torque"
+    );
+}
+
+#[test]
+fn test_reissue_error_path() {
+    let data = frame(0x03, &truncated_body());
+    let err = parser::parse_bytes(data.as_slice()).unwrap_err();
+    assert_eq!(err.offset(), Some(71));
+    assert_eq!(err.path(), Some("ReissueReport.items[3].motors[1].torque"));
+}
+
+#[test]
+fn test_view_error_offset_and_path() {
+    let data = frame(0x02, &truncated_body());
+    let packet = view::Packet::parse(data.as_slice()).unwrap();
+    let report = packet.real_time_report().unwrap();
+    let err = report.items().last().unwrap().unwrap_err();
+    assert_eq!(err.offset(), Some(71));
+    assert_eq!(err.path(), Some("RealTimeReport.items[3].motors[1].torque"));
+}
+
+#[test]
+fn test_header_error_path() {
+    let data = hex::decode("232301fe4c5a5954").unwrap();
+    let err = parser::parse_bytes(data.as_slice()).unwrap_err();
+    assert_eq!(err.offset(), Some(4));
+    assert_eq!(err.path(), Some("Header.vin"));
+}
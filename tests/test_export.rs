@@ -0,0 +1,101 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::{frame, real_time_body};
+use vin::export::{CsvWriter, Exporter, Rows, Table, Value};
+use vin::packet::{parser, Packet};
+
+fn report() -> Packet {
+    parser::parse_bytes(&frame(0x02, &real_time_body())).unwrap()
+}
+
+#[test]
+fn test_rows_of_every_table() {
+    let mut rows = Rows::new();
+    assert!(rows.push_packet(&report()));
+    assert!(!rows.push_packet(&parser::parse_bytes(&frame(0x07, &[])).unwrap()));
+
+    let counts: Vec<usize> = Table::ALL.iter().map(|&t| rows.get(t).len()).collect();
+    assert_eq!(counts, [1, 1, 1, 2, 3, 1]);
+    for table in Table::ALL {
+        for row in rows.get(table) {
+            assert_eq!(row.len(), table.columns().len());
+        }
+    }
+
+    let value = |table: Table, row: usize, name: &str| -> Value {
+        let i = table.columns().iter().position(|c| c.name == name).unwrap();
+        rows.get(table)[row][i].clone()
+    };
+    assert_eq!(value(Table::Vehicles, 0, "mileage"), Value::Float(466.0));
+    assert_eq!(value(Table::Vehicles, 0, "current"), Value::Float(0.0));
+    assert_eq!(value(Table::Vehicles, 0, "latitude"), Value::Float(30.0));
+    // the report has no engine
+    assert_eq!(value(Table::Vehicles, 0, "engine_status"), Value::Null);
+    assert_eq!(value(Table::CellVoltages, 1, "cell"), Value::Int(2));
+    assert_eq!(
+        value(Table::CellVoltages, 1, "voltage"),
+        Value::Float(3.301)
+    );
+    assert_eq!(
+        value(Table::ProbeTemperatures, 2, "temperature"),
+        Value::Float(23.0)
+    );
+    assert_eq!(
+        value(Table::Faults, 0, "kind"),
+        Value::Text("battery".into())
+    );
+    assert_eq!(value(Table::Faults, 0, "code"), Value::Int(42));
+}
+
+#[test]
+fn test_csv_in_batches() {
+    let writer = CsvWriter::new(|_| Ok(Vec::new())).unwrap();
+    let mut exporter = Exporter::with_batch_rows(writer, 5);
+    for _ in 0..3 {
+        exporter.push_packet(&report()).unwrap();
+    }
+    let outputs = exporter.finish().unwrap().into_inner();
+
+    let cells = String::from_utf8(outputs[&Table::CellVoltages].clone()).unwrap();
+    let lines: Vec<&str> = cells.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0], "vin,time,subsys,cell,voltage");
+    assert_eq!(
+        lines[1],
+        "LZYTBGBW6J1014194,2018-10-30T20:35:54+08:00,1,1,3.3"
+    );
+    let vehicles = String::from_utf8(outputs[&Table::Vehicles].clone()).unwrap();
+    let row: Vec<&str> = vehicles.lines().nth(1).unwrap().split(',').collect();
+    assert_eq!(row[2..10], ["1", "3", "1", "0", "466", "360", "0", "80"]);
+    assert_eq!(row[30], "");
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_round_trip() {
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, TimestampSecondType};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use vin::export::parquet::{schema, ParquetWriter};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut exporter = Exporter::new(ParquetWriter::create(dir.path()).unwrap());
+    exporter.push_packet(&report()).unwrap();
+    exporter.finish().unwrap();
+
+    let file = std::fs::File::open(dir.path().join("cell_voltages.parquet")).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap();
+    let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.schema(), schema(Table::CellVoltages));
+    let times = batch.column(1).as_primitive::<TimestampSecondType>();
+    assert_eq!(times.value(0), 1_540_902_954);
+    let voltages = batch.column(4).as_primitive::<Float64Type>();
+    assert_eq!(voltages.values(), &[3.3, 3.301]);
+}
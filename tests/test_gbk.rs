@@ -0,0 +1,193 @@
+extern crate vin;
+
+mod common;
+
+use common::LOGIN;
+use vin::packet::{parser, types};
+use vin::serde::gbk::{self, Charset, GBKString, Lossy, Overflow};
+use vin::serde::Serializer;
+
+fn invalid_vin_login() -> Vec<u8> {
+    let mut data = hex::decode(LOGIN).unwrap();
+    // 0xFF never starts a GB18030 sequence
+    data[4] = 0xff;
+    let last = data.len() - 1;
+    data[last] = parser::checksum(&data[2..last]);
+    data
+}
+
+#[test]
+fn test_invalid_vin_is_an_error() {
+    let data = invalid_vin_login();
+    let err = parser::parse_bytes(data.as_slice()).unwrap_err();
+    assert_eq!(err.path(), Some("Header.vin"));
+    let source = match err {
+        vin::packet::error::Error::Serde(vin::serde::Error::At { source, .. }) => source,
+        err => panic!("unexpected error {:?}", err),
+    };
+    assert!(matches!(
+        *source,
+        vin::serde::Error::Gbk(gbk::Error::Decode {
+            charset: Charset::Gb18030,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_lossy_decoding_marks_damage() {
+    let data = invalid_vin_login();
+    let vin: GBKString<Lossy<types::VinOpts>> = vin::serde::from_bytes(&data[4..21]).unwrap();
+    assert!(vin.is_damaged());
+    assert!(vin.message.ends_with("ZYTBGBW6J1014194"));
+
+    let vin: GBKString<Lossy<types::VinOpts>> =
+        vin::serde::from_bytes(b"LZYTBGBW6J1014194").unwrap();
+    assert!(!vin.is_damaged());
+}
+
+#[test]
+fn test_typed_decode_error() {
+    let err = GBKString::<types::IccidOpts>::decode(&[0xff; 20]).unwrap_err();
+    assert_eq!(
+        err,
+        gbk::Error::Decode {
+            charset: gbk::Charset::Gb18030,
+            bytes: vec![0xff; 20]
+        }
+    );
+
+    let err = GBKString::<types::IccidOpts>::decode(&[0x38; 3]).unwrap_err();
+    assert_eq!(
+        err,
+        gbk::Error::Length {
+            expected: 20,
+            actual: 3
+        }
+    );
+}
+
+struct Name;
+
+impl gbk::Options for Name {
+    const LENGTH: usize = 6;
+    const CHARSET: Charset = Charset::Gbk;
+    const PADDING: u8 = 0x20;
+    const OVERFLOW: Overflow = Overflow::Truncate;
+}
+
+struct Code;
+
+impl gbk::Options for Code {
+    const LENGTH: usize = 4;
+    const CHARSET: Charset = Charset::Ascii;
+}
+
+fn serialize_both<O: gbk::Options>(
+    value: &GBKString<O>,
+) -> (vin::serde::Result<Vec<u8>>, vin::serde::Result<Vec<u8>>) {
+    let mut buff = Vec::new();
+    let direct = Serializer::new(&mut buff)
+        .serialize_gbk_string(value)
+        .map(|_| buff);
+    (direct, vin::serde::to_bytes(value))
+}
+
+#[test]
+fn test_padding_and_truncation() {
+    let name: GBKString<Name> = GBKString::from("北京".to_string());
+    let (direct, serde) = serialize_both(&name);
+    assert_eq!(direct.unwrap(), serde.unwrap());
+
+    let encoded = name.encode().unwrap();
+    assert_eq!(encoded, vec![0xb1, 0xb1, 0xbe, 0xa9, 0x20, 0x20]);
+    assert_eq!(GBKString::<Name>::decode(&encoded).unwrap().message, "北京");
+
+    // truncation keeps whole characters only, padding what is left
+    let name: GBKString<Name> = GBKString::from("北京市X".to_string());
+    assert_eq!(
+        name.encode().unwrap(),
+        vec![0xb1, 0xb1, 0xbe, 0xa9, 0xca, 0xd0]
+    );
+    let name: GBKString<Name> = GBKString::from("北京a市".to_string());
+    assert_eq!(
+        name.encode().unwrap(),
+        vec![0xb1, 0xb1, 0xbe, 0xa9, 0x61, 0x20]
+    );
+}
+
+#[test]
+fn test_overflow_and_charset_errors() {
+    let code: GBKString<Code> = GBKString::from("ABCDE".to_string());
+    let (direct, serde) = serialize_both(&code);
+    assert!(matches!(
+        direct.unwrap_err(),
+        vin::serde::Error::Gbk(gbk::Error::TooLong { len: 5, max: 4, .. })
+    ));
+    assert!(matches!(
+        serde.unwrap_err(),
+        vin::serde::Error::Gbk(gbk::Error::TooLong { len: 5, max: 4, .. })
+    ));
+
+    let code: GBKString<Code> = GBKString::from("北".to_string());
+    assert!(matches!(
+        code.encode().unwrap_err(),
+        gbk::Error::Encode {
+            charset: Charset::Ascii,
+            ..
+        }
+    ));
+    assert!(matches!(
+        GBKString::<Code>::decode(&[0x41, 0xb1, 0xb1, 0x00]).unwrap_err(),
+        gbk::Error::Decode {
+            charset: Charset::Ascii,
+            ..
+        }
+    ));
+    let err = vin::serde::from_bytes::<GBKString<Code>>(&[0x41, 0xb1, 0xb1, 0x00]).unwrap_err();
+    assert!(matches!(
+        err,
+        vin::serde::Error::Gbk(gbk::Error::Decode {
+            charset: Charset::Ascii,
+            ..
+        })
+    ));
+}
+
+struct Starred;
+
+impl gbk::Options for Starred {
+    const LENGTH: usize = 6;
+    const CHARSET: Charset = Charset::Ascii;
+    const PADDING: u8 = b'*';
+}
+
+#[test]
+fn test_spans_follow_the_options() {
+    let mut de = vin::serde::Deserializer::from_slice(b"AB*\xb1**").annotated();
+    let code: vin::serde::Result<GBKString<Starred>> = serde::Deserialize::deserialize(&mut de);
+    assert!(matches!(
+        code.unwrap_err(),
+        vin::serde::Error::Gbk(gbk::Error::Decode {
+            charset: Charset::Ascii,
+            ..
+        })
+    ));
+    let spans = de.take_spans();
+    assert_eq!(spans[0].value, "\"AB*\u{fffd}\"");
+
+    let mut de = vin::serde::Deserializer::from_slice(b"AB****").annotated();
+    let code: GBKString<Starred> = serde::Deserialize::deserialize(&mut de).unwrap();
+    assert_eq!(code.message, "AB");
+    assert_eq!(de.take_spans()[0].value, "\"AB\"");
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_other_formats_see_a_plain_string() {
+    let code: GBKString<Starred> = GBKString::from("AB".to_string());
+    let json = serde_json::to_string(&code).unwrap();
+    assert_eq!(json, "\"AB\"");
+    let code: GBKString<Starred> = serde_json::from_str(&json).unwrap();
+    assert_eq!(code.message, "AB");
+}
@@ -0,0 +1,74 @@
+extern crate vin;
+
+mod common;
+
+use common::LOGIN;
+use vin::packet::body::Body;
+use vin::packet::iccid::{self, Error};
+use vin::packet::parser::{self, Options};
+use vin::packet::{view, Iccid, Strictness};
+
+#[test]
+fn test_structured_accessors() {
+    let iccid = Iccid::new("89860112345678901237").unwrap();
+    assert_eq!(iccid.country_code(), Some("86"));
+    assert_eq!(iccid.issuer(), Some("01"));
+    assert_eq!(iccid.check_digit(), Some('7'));
+
+    let iccid = Iccid::new("8910042348144559361").unwrap();
+    assert_eq!(iccid.country_code(), Some("1"));
+    assert_eq!(iccid.issuer(), Some("00"));
+
+    let iccid = Iccid::new_unchecked("89A6".to_string());
+    assert_eq!(iccid.country_code(), None);
+}
+
+#[test]
+fn test_validation_errors() {
+    assert_eq!(iccid::validate("898601").unwrap_err(), Error::Length(6));
+    assert_eq!(
+        iccid::validate("8986011234567890123F").unwrap_err(),
+        Error::Char {
+            position: 20,
+            ch: 'F'
+        }
+    );
+    assert_eq!(
+        iccid::validate("98860112345678901237").unwrap_err(),
+        Error::Prefix
+    );
+    assert_eq!(
+        iccid::validate("89860402101700179779").unwrap_err(),
+        Error::CheckDigit {
+            expected: '4',
+            actual: '9'
+        }
+    );
+}
+
+#[test]
+fn test_decode_strictness() {
+    let data = hex::decode(LOGIN).unwrap();
+    let options = |iccid| Options {
+        iccid,
+        ..Options::default()
+    };
+
+    let err = parser::parse_bytes_with(&data, &options(Strictness::Reject)).unwrap_err();
+    assert_eq!(err.offset(), Some(32));
+    assert_eq!(err.path(), Some("VehicleLogin.iccid"));
+    let packet = view::Packet::parse(&data).unwrap();
+    assert!(packet
+        .decode_body_with(&options(Strictness::Reject))
+        .is_err());
+
+    let iccid =
+        |strictness| match parser::parse_bytes_with(&data, &options(strictness)).map(|p| p.body) {
+            Ok(Body::VehicleLogin(login)) => login.iccid,
+            body => panic!("expected a login, got {:?}", body),
+        };
+    let warned = iccid(Strictness::Warn);
+    assert_eq!(warned.as_str(), "89860402101700179779");
+    assert!(matches!(warned.issue(), Some(Error::CheckDigit { .. })));
+    assert_eq!(iccid(Strictness::Accept).issue(), None);
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "json")]
+
+extern crate vin;
+
+mod common;
+
+use common::{frame, real_time_body, LOGIN};
+use serde_json::json;
+use vin::packet::{json, parser};
+
+#[test]
+fn test_login_round_trip() {
+    let data = hex::decode(LOGIN).unwrap();
+    let packet = parser::parse_bytes(&data).unwrap();
+    let value = json::to_value(&packet);
+    assert_eq!(value["command"], "vehicle_login");
+    assert_eq!(value["response"], "command");
+    assert_eq!(value["vin"], "LZYTBGBW6J1014194");
+    assert_eq!(value["encrypt"], "none");
+    assert_eq!(value["body"]["at"], "2018-10-30T20:35:54+08:00");
+    assert_eq!(value["body"]["sn"], 253);
+    assert_eq!(value["body"]["iccid"], "89860402101700179779");
+
+    let packet = json::from_str(&json::to_string(&packet)).unwrap();
+    assert_eq!(packet.encode().unwrap(), data);
+}
+
+#[test]
+fn test_vendor_command_round_trip() {
+    let data = frame(0xc1, &[0x0a, 0x0b]);
+    let packet = parser::parse_bytes(&data).unwrap();
+    let value = json::to_value(&packet);
+    assert_eq!(value["command"], 0xc1);
+    assert_eq!(value["body"]["data"], "0a0b");
+
+    let packet = json::from_value(&value).unwrap();
+    assert_eq!(packet.encode().unwrap(), data);
+}
+
+#[test]
+fn test_real_time_report_values() {
+    let data = frame(0x02, &real_time_body());
+    let packet = parser::parse_bytes(&data).unwrap();
+    let value = json::to_value(&packet);
+    let items = &value["body"]["items"];
+    assert_eq!(items[0]["type"], "vehicle");
+    assert_eq!(items[0]["mileage"], 4660);
+    assert_eq!(items[0]["mileage_value"], 466.0);
+    assert_eq!(items[0]["current_value"], 0.0);
+    assert_eq!(items[1]["motors"][0]["speed_value"], 0.0);
+    assert_eq!(items[2]["longitude_value"], 120.286736);
+    assert_eq!(items[2]["latitude_value"], 30.0);
+    assert_eq!(items[3]["max_cell_voltage_value"], 3.302);
+    assert_eq!(items[3]["max_temperature_value"], 25.0);
+    assert_eq!(
+        items[5]["subsystems"][0]["cells_value"],
+        json!([3.3, 3.301])
+    );
+    assert_eq!(items[6]["subsystems"][0]["probes"], json!([65, 64, 63]));
+    assert_eq!(
+        items[7],
+        json!({"type": "custom", "kind": 128, "data": "abcd"})
+    );
+
+    let packet = json::from_value(&value).unwrap();
+    assert_eq!(packet.encode().unwrap(), data);
+}
+
+#[test]
+fn test_markers_and_hemispheres() {
+    let mut body = vec![0x12, 0x0a, 0x1e, 0x14, 0x23, 0x36];
+    body.extend_from_slice(&[0x05, 0x06, 0x07, 0x2b, 0x6e, 0x10, 0xff, 0xff, 0xff, 0xfe]);
+    let packet = parser::parse_bytes(&frame(0x02, &body)).unwrap();
+    let value = json::to_value(&packet);
+    let location = &value["body"]["items"][0];
+    assert_eq!(location["longitude_value"], -120.286736);
+    assert!(location["latitude_value"].is_null());
+}
+
+#[test]
+fn test_fixture_to_frame() {
+    let packet = json::from_str(
+        r#"{
+            "command": "vehicle_logout",
+            "response": "command",
+            "vin": "LZYTBGBW6J1014194",
+            "encrypt": "none",
+            "body": { "at": "2018-10-30T20:35:54", "sn": 253 }
+        }"#,
+    )
+    .unwrap();
+    let data = packet.encode().unwrap();
+    let decoded = parser::parse_bytes(&data).unwrap();
+    assert_eq!(decoded.body_len, 8);
+    assert_eq!(decoded.bcc, 70);
+}
+
+#[test]
+fn test_errors() {
+    let value = json!({
+        "command": "real_time_report",
+        "response": "command",
+        "vin": "LZYTBGBW6J1014194",
+        "encrypt": "none",
+        "body": { "at": "2018-10-30T20:35:54+08:00", "items": [
+            { "type": "engine", "status": 1, "crankshaft_speed": 70000, "fuel_consumption": 0 }
+        ] }
+    });
+    let err = json::from_value(&value).unwrap_err().to_string();
+    assert_eq!(
+        err,
+        "70000 is out of range at body.items[0].crankshaft_speed"
+    );
+
+    let mut broken = value.clone();
+    broken["body"]["at"] = json!("2018-10-30 20:35:54");
+    assert!(json::from_value(&broken).is_err());
+    broken["body"]["at"] = json!("1999-10-30T20:35:54+08:00");
+    assert!(json::from_value(&broken).is_err());
+    broken["body"]["at"] = json!("2018-13-45T99:99:99+08:00");
+    let err = json::from_value(&broken).unwrap_err().to_string();
+    assert_eq!(err, "month 13 is out of range at body.at");
+    broken["body"]["at"] = json!("2019-02-29T00:00:00+08:00");
+    let err = json::from_value(&broken).unwrap_err().to_string();
+    assert_eq!(err, "day 29 is out of range at body.at");
+
+    let mut broken = value;
+    broken["command"] = json!("reboot");
+    let err = json::from_value(&broken).unwrap_err().to_string();
+    assert_eq!(err, "unknown name \"reboot\" at command");
+    assert!(json::from_str("{").is_err());
+}
@@ -0,0 +1,98 @@
+extern crate vin;
+
+mod common;
+
+use common::LOGIN;
+use vin::packet::crypto;
+use vin::packet::error::Error;
+use vin::packet::keys::{KeyProvider, MemoryKeys};
+use vin::packet::{parser, Encrypt};
+
+const VIN: &str = "LZYTBGBW6J1014194";
+
+#[test]
+fn test_missing_key() {
+    let keys = MemoryKeys::new();
+    assert!(keys.is_empty());
+    assert!(keys.cipher(VIN, Encrypt::Aes128).is_none());
+
+    let mut packet = parser::parse_bytes(&hex::decode(LOGIN).unwrap()).unwrap();
+    packet.encrypt = Encrypt::Aes128;
+    assert!(matches!(
+        packet.encode_with_keys(&keys).unwrap_err(),
+        Error::Crypto(crypto::Error::MissingKey(Encrypt::Aes128))
+    ));
+
+    // plain packets never ask for a key
+    let data = hex::decode(LOGIN).unwrap();
+    let options = parser::Options::default();
+    assert!(parser::parse_bytes_with_keys(&data, &options, &keys).is_ok());
+}
+
+#[cfg(feature = "aes")]
+#[test]
+fn test_memory_keys() {
+    use vin::packet::crypto::Aes128;
+    use vin::packet::view;
+
+    let mut keys = MemoryKeys::new();
+    keys.insert(VIN, Aes128::new(&[1; 16]));
+    keys.insert_default(Aes128::new(&[2; 16]));
+    assert_eq!(keys.len(), 2);
+    assert!(keys.cipher(VIN, Encrypt::Rsa).is_none());
+    assert!(!format!("{:?}", keys).contains("cipher"));
+
+    let mut packet = parser::parse_bytes(&hex::decode(LOGIN).unwrap()).unwrap();
+    packet.encrypt = Encrypt::Aes128;
+    let data = packet.encode_with_keys(&keys).unwrap();
+    let options = parser::Options::default();
+    let decoded = parser::parse_bytes_with_keys(&data, &options, &keys).unwrap();
+    assert_eq!(
+        decoded.body.encode().unwrap(),
+        packet.body.encode().unwrap()
+    );
+    let body = view::Packet::parse(&data)
+        .unwrap()
+        .decode_body_with_keys(&options, &keys)
+        .unwrap();
+    assert_eq!(body.encode().unwrap(), packet.body.encode().unwrap());
+
+    // the default key of other vehicles does not fit
+    keys.remove(VIN, Encrypt::Aes128);
+    assert!(parser::parse_bytes_with_keys(&data, &options, &keys).is_err());
+}
+
+#[cfg(all(feature = "keystore", feature = "aes"))]
+#[test]
+fn test_keystore() {
+    use std::io::Write;
+    use vin::packet::keys::FileKeys;
+
+    let keys = MemoryKeys::from_json(
+        r#"{"keys": [{"vin": "LZYTBGBW6J1014194", "encrypt": "aes128",
+            "key": "000102030405060708090a0b0c0d0e0f"}]}"#,
+    )
+    .unwrap();
+    assert!(keys.cipher(VIN, Encrypt::Aes128).is_some());
+    assert!(keys.cipher("LGXCE4CB0H0000001", Encrypt::Aes128).is_none());
+    assert!(MemoryKeys::from_toml("[[keys]]\nencrypt = \"aes128\"\nkey = \"00\"\n").is_err());
+
+    let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+    write!(
+        file,
+        "[[keys]]\nencrypt = \"aes128\"\nkey = \"{}\"\n",
+        "00".repeat(16)
+    )
+    .unwrap();
+    let keys = FileKeys::open(file.path()).unwrap();
+    let cipher = keys.cipher(VIN, Encrypt::Aes128).unwrap();
+    assert_eq!(cipher.kind(), Encrypt::Aes128);
+
+    std::fs::write(file.path(), "keys = []\n").unwrap();
+    keys.reload().unwrap();
+    assert!(keys.cipher(VIN, Encrypt::Aes128).is_none());
+
+    // a broken file keeps the previous keys
+    std::fs::write(file.path(), "keys = ").unwrap();
+    assert!(keys.reload().is_err());
+}
@@ -0,0 +1,183 @@
+#![cfg(feature = "pcap")]
+
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use common::{pcap, tcp_packet, LOGIN};
+use vin::packet::{parser, Command, Response};
+use vin::pcap::{Error, Frames};
+
+const SYN: u8 = 0x02;
+const ACK: u8 = 0x10;
+const PSH_ACK: u8 = 0x18;
+
+fn terminal() -> SocketAddr {
+    "10.0.0.1:50000".parse().unwrap()
+}
+
+fn platform() -> SocketAddr {
+    "10.0.0.2:32960".parse().unwrap()
+}
+
+fn reply() -> Vec<u8> {
+    let login = parser::pares_hex(LOGIN).unwrap();
+    login.reply(Response::Success).unwrap().encode().unwrap()
+}
+
+#[test]
+fn test_pcap_reassembles_segments_out_of_order() {
+    let login = hex::decode(LOGIN).unwrap();
+    let (a, rest) = login.split_at(10);
+    let (b, c) = rest.split_at(20);
+    let seq = 1000;
+    let packets = [
+        (1_000_000, tcp_packet(50000, 32960, seq, SYN, &[])),
+        (1_000_100, tcp_packet(50000, 32960, seq + 1, PSH_ACK, a)),
+        (1_000_200, tcp_packet(50000, 32960, seq + 31, PSH_ACK, c)),
+        (1_000_300, tcp_packet(50000, 32960, seq + 11, PSH_ACK, b)),
+        // a retransmission changes nothing
+        (1_000_400, tcp_packet(50000, 32960, seq + 1, PSH_ACK, a)),
+        (1_500_000, tcp_packet(32960, 50000, 7, PSH_ACK, &reply())),
+    ];
+    let frames: Vec<_> = Frames::new(pcap(&packets))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].at, Duration::from_micros(1_000_300));
+    assert_eq!((frames[0].src, frames[0].dst), (terminal(), platform()));
+    assert_eq!(frames[0].data, login);
+    assert_eq!((frames[1].src, frames[1].dst), (platform(), terminal()));
+    assert_eq!(frames[1].data, reply());
+}
+
+/// Little endian pcapng block of `kind` with `body`, padded to 32 bits.
+fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+    let mut body = body.to_vec();
+    body.resize(body.len().div_ceil(4) * 4, 0);
+    let len = (body.len() + 12) as u32;
+    let mut data = kind.to_le_bytes().to_vec();
+    data.extend_from_slice(&len.to_le_bytes());
+    data.extend_from_slice(&body);
+    data.extend_from_slice(&len.to_le_bytes());
+    data
+}
+
+fn section() -> Vec<u8> {
+    block(
+        0x0a0d_0d0a,
+        &[
+            0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ],
+    )
+}
+
+#[test]
+fn test_pcapng_linux_cooked_packets() {
+    let ip = |packet: Vec<u8>| -> Vec<u8> {
+        let mut cooked = vec![0, 0, 0, 1, 0, 6, 0, 0, 0, 0, 0, 0, 0, 0, 0x08, 0x00];
+        cooked.extend_from_slice(&packet[14..]);
+        cooked
+    };
+    let packet = |nanos: u64, packet: Vec<u8>| -> Vec<u8> {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&((nanos >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(nanos as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&packet);
+        block(6, &body)
+    };
+
+    let mut capture = section();
+    // Linux cooked capture with nanosecond times
+    capture.extend(block(
+        1,
+        &[113, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0],
+    ));
+    let at = 1_540_902_954_123_456_789;
+    let login = hex::decode(LOGIN).unwrap();
+    capture.extend(packet(at, ip(tcp_packet(50000, 32960, 1, ACK, &login))));
+    capture.extend(packet(
+        at + 1,
+        ip(tcp_packet(32960, 50000, 1, ACK, &reply())),
+    ));
+
+    let options = parser::Options::default();
+    let packets: Vec<_> = Frames::new(capture)
+        .unwrap()
+        .packets(options)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(packets.len(), 2);
+    let (time, src, dst, packet) = &packets[0];
+    assert_eq!(*time, Duration::from_nanos(at));
+    assert_eq!((*src, *dst), (terminal(), platform()));
+    assert_eq!(packet.command, Command::VehicleLogin);
+    assert_eq!(packets[1].3.response, Response::Success);
+}
+
+#[test]
+fn test_cut_off_capture_keeps_frames() {
+    let login = hex::decode(LOGIN).unwrap();
+    let mut capture = pcap(&[
+        (0, tcp_packet(50000, 32960, 1, ACK, &login)),
+        (
+            1,
+            tcp_packet(50000, 32960, 1 + login.len() as u32, ACK, &login),
+        ),
+    ]);
+    capture.truncate(capture.len() - 10);
+    let mut frames = Frames::new(capture).unwrap();
+    assert_eq!(frames.next().unwrap().unwrap().data, login);
+    assert!(matches!(frames.next(), Some(Err(Error::Truncated(_)))));
+    assert!(frames.next().is_none());
+
+    assert!(matches!(Frames::new(login), Err(Error::Format)));
+}
+
+#[test]
+fn test_frames_behind_lost_data_keep_their_times() {
+    let login = hex::decode(LOGIN).unwrap();
+    let seq = 1000 + 50;
+    let packets = [
+        (1_000_000, tcp_packet(50000, 32960, 1000, SYN, &[])),
+        // the first 49 bytes were never captured
+        (2_000_000, tcp_packet(50000, 32960, seq, PSH_ACK, &login)),
+        (
+            3_000_000,
+            tcp_packet(50000, 32960, seq + login.len() as u32, PSH_ACK, &login),
+        ),
+    ];
+    let frames: Vec<_> = Frames::new(pcap(&packets))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let times: Vec<_> = frames.iter().map(|frame| frame.at).collect();
+    assert_eq!(
+        times,
+        [
+            Duration::from_micros(2_000_000),
+            Duration::from_micros(3_000_000)
+        ]
+    );
+}
+
+#[test]
+fn test_short_blocks_are_errors() {
+    let mut capture = section();
+    capture.extend(block(1, &[1, 0, 0, 0, 0, 0, 0, 0]));
+    // a simple packet block without room for its length
+    capture.extend(block(3, &[]));
+    let mut frames = Frames::new(capture.clone()).unwrap();
+    assert!(matches!(frames.next(), Some(Err(Error::Block(_)))));
+    assert!(frames.next().is_none());
+
+    // an enhanced packet block cut short
+    let mut capture = capture[..capture.len() - 12].to_vec();
+    capture.extend(block(6, &[0; 8]));
+    let mut frames = Frames::new(capture).unwrap();
+    assert!(matches!(frames.next(), Some(Err(Error::Block(_)))));
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use core::time::Duration;
+
+use common::{frame, real_time_body, LOGIN};
+use vin::packet::body::Body;
+use vin::packet::platform::{Action, Close, Config, PlatformSession, Violation};
+use vin::packet::serial::Issue;
+use vin::packet::{parser, Command, Packet, Response};
+
+const NOW: Duration = Duration::from_secs(1_540_902_954);
+
+fn packet(data: &[u8]) -> Packet {
+    parser::parse_bytes(data).unwrap()
+}
+
+fn login() -> Packet {
+    parser::pares_hex(LOGIN).unwrap()
+}
+
+fn logout(sn: u16) -> Packet {
+    let mut body = vec![0x12, 0x0a, 0x1e, 0x14, 0x30, 0x00];
+    body.extend_from_slice(&sn.to_be_bytes());
+    packet(&frame(0x04, &body))
+}
+
+fn logged_in(config: Config) -> PlatformSession {
+    let mut session = PlatformSession::new(config, NOW);
+    session.handle(login(), NOW);
+    assert!(session.is_logged_in());
+    session
+}
+
+fn sent(actions: &[Action]) -> Vec<(Command, Response)> {
+    actions
+        .iter()
+        .filter_map(|action| match action {
+            Action::Send(p) => Some((p.command, p.response)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn closed(actions: &[Action]) -> Option<&Close> {
+    actions.iter().find_map(|action| match action {
+        Action::Close(close) => Some(close),
+        _ => None,
+    })
+}
+
+#[test]
+fn test_login_is_answered_and_emitted() {
+    let mut session = PlatformSession::new(Config::default(), NOW);
+    let actions = session.handle(login(), NOW);
+    assert!(matches!(&actions[0], Action::Emit(p) if p.command == Command::VehicleLogin));
+    assert_eq!(sent(&actions), [(Command::VehicleLogin, Response::Success)]);
+    match &actions[1] {
+        Action::Send(reply) => {
+            assert_eq!(reply.vin.as_str(), "LZYTBGBW6J1014194");
+            let data = reply.encode().unwrap();
+            assert!(matches!(packet(&data).body, Body::VehicleLogin(ref b) if b.sn == 253));
+        }
+        action => panic!("expected a reply, got {:?}", action),
+    }
+}
+
+#[test]
+fn test_report_before_login_is_rejected() {
+    let mut session = PlatformSession::new(Config::default(), NOW);
+    let actions = session.handle(packet(&frame(0x02, &real_time_body())), NOW);
+    assert_eq!(sent(&actions), [(Command::RealTimeReport, Response::Fail)]);
+    assert_eq!(
+        closed(&actions),
+        Some(&Close::Violation(Violation::NotLoggedIn(
+            Command::RealTimeReport
+        )))
+    );
+    assert!(session.is_closed());
+    assert!(session.handle(login(), NOW).is_empty());
+}
+
+#[test]
+fn test_reports_are_emitted() {
+    let mut session = logged_in(Config::default());
+    let actions = session.handle(packet(&frame(0x02, &real_time_body())), NOW);
+    assert_eq!(actions.len(), 1);
+    assert!(matches!(&actions[0], Action::Emit(p) if p.command == Command::RealTimeReport));
+
+    let mut session = logged_in(Config {
+        respond_reports: true,
+        ..Config::default()
+    });
+    let actions = session.handle(packet(&frame(0x03, &real_time_body())), NOW);
+    assert_eq!(
+        sent(&actions),
+        [(Command::ReissueReport, Response::Success)]
+    );
+}
+
+#[test]
+fn test_vin_must_not_change() {
+    let mut session = logged_in(Config::default());
+    let mut data = frame(0x07, &[]);
+    data[4..21].copy_from_slice(b"LZYTBGBW6J1014195");
+    let last = data.len() - 1;
+    data[last] = parser::checksum(&data[2..last]);
+    let actions = session.handle(packet(&data), NOW);
+    assert_eq!(
+        closed(&actions),
+        Some(&Close::Violation(Violation::VinChanged {
+            expected: "LZYTBGBW6J1014194".into(),
+            actual: "LZYTBGBW6J1014195".into(),
+        }))
+    );
+}
+
+#[test]
+fn test_heartbeats_keep_the_session_alive() {
+    let config = Config::default();
+    let mut session = logged_in(config);
+    let later = NOW + Duration::from_secs(100);
+    let actions = session.handle(packet(&frame(0x07, &[])), later);
+    assert_eq!(sent(&actions), [(Command::HeartBeat, Response::Success)]);
+    assert_eq!(session.deadline(), Some(later + config.idle_timeout));
+
+    assert!(session.poll(NOW + config.idle_timeout).is_empty());
+    let actions = session.poll(later + config.idle_timeout);
+    assert_eq!(closed(&actions), Some(&Close::Idle));
+    assert_eq!(session.deadline(), None);
+}
+
+#[test]
+fn test_time_request_gets_platform_time() {
+    let mut session = logged_in(Config::default());
+    let actions = session.handle(packet(&frame(0x08, &[])), NOW);
+    match &actions[0] {
+        Action::Send(reply) => match &reply.body {
+            Body::Time(raw) => assert_eq!(raw.data, [18, 10, 30, 20, 35, 54]),
+            body => panic!("expected a time, got {:?}", body),
+        },
+        action => panic!("expected a reply, got {:?}", action),
+    }
+}
+
+#[test]
+fn test_logout_closes() {
+    let mut session = logged_in(Config::default());
+    let actions = session.handle(logout(253), NOW);
+    assert!(matches!(&actions[0], Action::Emit(p) if p.command == Command::VehicleLogout));
+    assert_eq!(
+        sent(&actions),
+        [(Command::VehicleLogout, Response::Success)]
+    );
+    assert_eq!(closed(&actions), Some(&Close::Logout));
+
+    let mut session = logged_in(Config::default());
+    let actions = session.handle(logout(252), NOW);
+    assert_eq!(
+        closed(&actions),
+        Some(&Close::Violation(Violation::Serial(Issue::Mismatch {
+            login: Some(253),
+            actual: 252
+        })))
+    );
+}
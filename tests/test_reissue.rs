@@ -0,0 +1,143 @@
+mod common;
+
+use core::time::Duration;
+
+use common::real_time_body;
+use vin::packet::body::Body;
+#[cfg(feature = "std")]
+use vin::packet::reissue::FileStore;
+use vin::packet::reissue::{self, MemoryStore, ReissueStore};
+use vin::packet::terminal::{Action, Config, TerminalSession};
+use vin::packet::{parser, Command, Response, Time};
+
+const NOW: Duration = Duration::from_secs(1_540_902_954);
+const VIN: &str = "LZYTBGBW6J1014194";
+
+fn at(secs: i64) -> Time {
+    Time::from_unix(NOW.as_secs() as i64 + secs)
+}
+
+#[test]
+fn test_replay_is_chronological() {
+    let mut store = MemoryStore::new();
+    // frames of the same time stay in the order they were kept, not by VIN
+    store.push("LZYTBGBW6J1014195", at(20), vec![3]).unwrap();
+    store.push("LZYTBGBW6J1014195", at(10), vec![2]).unwrap();
+    store.push(VIN, at(0), vec![1]).unwrap();
+    store.push(VIN, at(20), vec![4]).unwrap();
+
+    assert_eq!(
+        store.replay(NOW, reissue::RETENTION, 3).unwrap(),
+        [[1], [2], [3]]
+    );
+    assert_eq!(store.replay(NOW, reissue::RETENTION, 3).unwrap(), [[4]]);
+    assert!(store.is_empty());
+}
+
+#[test]
+fn test_replay_drops_expired() {
+    let mut store = MemoryStore::new();
+    let day = 24 * 3600;
+    store.push(VIN, at(-8 * day), vec![1]).unwrap();
+    store.push(VIN, at(-6 * day), vec![2]).unwrap();
+    assert_eq!(store.replay(NOW, reissue::RETENTION, 0).unwrap(), [[2]]);
+
+    // times before 2000 cannot be a cutoff
+    assert_eq!(reissue::cutoff(NOW, NOW), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_file_store_survives_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reissue.bin");
+    let mut store = FileStore::open(&path).unwrap();
+    for n in 0..4 {
+        store.push(VIN, at(10 - n), vec![n as u8]).unwrap();
+    }
+    assert_eq!(store.take(1).unwrap(), [[3]]);
+    drop(store);
+
+    // a crash in the middle of a record
+    let mut data = std::fs::read(&path).unwrap();
+    data.extend_from_slice(&[b'+', 0, 0, 1]);
+    std::fs::write(&path, data).unwrap();
+
+    let mut store = FileStore::open(&path).unwrap();
+    assert_eq!(store.len(), 3);
+    store.push(VIN, at(0), vec![9]).unwrap();
+    drop(store);
+    let mut store = FileStore::open(&path).unwrap();
+    assert_eq!(store.take(10).unwrap(), [[9], [2], [1], [0]]);
+    drop(store);
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_failed_compaction_keeps_frames() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reissue.bin");
+    let mut store = FileStore::open(&path).unwrap();
+    for n in 0..1100 {
+        store.push(VIN, at(-n), vec![n as u8]).unwrap();
+    }
+    // the temporary file cannot be created over a directory
+    let tmp = dir.path().join("reissue.bin.tmp");
+    std::fs::create_dir(&tmp).unwrap();
+    let frames = store.take(1090).unwrap();
+    assert_eq!(frames.len(), 1090);
+    assert_eq!(frames[0], vec![(1099 % 256) as u8]);
+    assert_eq!(store.len(), 10);
+
+    std::fs::remove_dir(&tmp).unwrap();
+    let before = std::fs::metadata(&path).unwrap().len();
+    assert_eq!(store.take(1).unwrap().len(), 1);
+    assert!(std::fs::metadata(&path).unwrap().len() < before);
+    store.push(VIN, at(1), vec![0xff]).unwrap();
+    drop(store);
+    let store = FileStore::open(&path).unwrap();
+    assert_eq!(store.len(), 10);
+}
+
+#[test]
+fn test_terminal_reissues_at_rate() {
+    let config = Config {
+        reissue_rate: 2,
+        ..Config::default()
+    };
+    let mut session = TerminalSession::new(VIN, "89860402101700179779", config);
+    for n in (0..5).rev() {
+        let mut report = match parser::parse_body(Command::ReissueReport, &real_time_body()) {
+            Ok(Body::ReissueReport(report)) => report,
+            body => panic!("expected a report, got {:?}", body),
+        };
+        report.at = at(n);
+        assert!(session.report(report, NOW).is_empty());
+    }
+
+    let times = |actions: Vec<Action>| -> Vec<Time> {
+        actions
+            .into_iter()
+            .map(|action| match action {
+                Action::Send(packet) => match packet.body {
+                    Body::ReissueReport(report) => report.at,
+                    body => panic!("expected a reissue report, got {:?}", body),
+                },
+                action => panic!("expected a packet, got {:?}", action),
+            })
+            .collect()
+    };
+    let login = match session.connected(NOW).remove(0) {
+        Action::Send(packet) => packet,
+        action => panic!("expected a login, got {:?}", action),
+    };
+    let actions = session.handle(login.reply(Response::Success).unwrap(), NOW);
+    assert_eq!(times(actions), [at(0), at(1)]);
+    let next = NOW + reissue::PERIOD;
+    assert_eq!(session.deadline(), Some(next));
+    assert_eq!(times(session.poll(next)), [at(2), at(3)]);
+    assert_eq!(times(session.poll(next + reissue::PERIOD)), [at(4)]);
+    assert_eq!(session.buffered(), 0);
+    assert!(session.deadline() > Some(next + reissue::PERIOD));
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use core::time::Duration;
+
+use common::{frame, real_time_body, LOGIN};
+use vin::packet::platform::{self, Close, Violation};
+use vin::packet::relay::{Action, Config, Relay, Upstream, UpstreamConfig};
+use vin::packet::{parser, Command};
+
+const NOW: Duration = Duration::from_secs(1_540_902_954);
+
+fn config() -> Config {
+    Config {
+        platform_id: "PLATFORM000000001".into(),
+        username: "relay".into(),
+        password: "secret".into(),
+        ..Config::default()
+    }
+}
+
+fn upstream() -> Upstream {
+    let config = UpstreamConfig {
+        username: "relay".into(),
+        password: "secret".into(),
+        ..UpstreamConfig::default()
+    };
+    Upstream::new(config, NOW)
+}
+
+/// Carries `actions` upstream and the answers back until both are done,
+/// returning the commands the upstream emitted and how it closed.
+fn exchange(
+    relay: &mut Relay,
+    upstream: &mut Upstream,
+    mut actions: Vec<Action>,
+) -> (Vec<Command>, Option<Close>) {
+    let mut emitted = Vec::new();
+    let mut closed = None;
+    while !actions.is_empty() {
+        let mut next = Vec::new();
+        for action in actions {
+            let data = match action {
+                Action::Send(data) => data,
+                Action::Disconnect => continue,
+                Action::Store(err) => panic!("{}", err),
+            };
+            for action in upstream.handle(parser::parse_bytes(&data).unwrap(), NOW) {
+                match action {
+                    platform::Action::Send(reply) => {
+                        let reply = parser::parse_bytes(&reply.encode().unwrap()).unwrap();
+                        next.extend(relay.handle(reply, NOW));
+                    }
+                    platform::Action::Emit(packet) => emitted.push(packet.command),
+                    platform::Action::Close(close) => closed = Some(close),
+                }
+            }
+        }
+        actions = next;
+    }
+    (emitted, closed)
+}
+
+#[test]
+fn test_login_and_pass_through() {
+    let mut relay = Relay::new(config()).unwrap();
+    let mut upstream = upstream();
+    let actions = relay.connected(NOW);
+    exchange(&mut relay, &mut upstream, actions);
+    assert!(relay.is_logged_in());
+    assert_eq!(upstream.platform_id(), Some("PLATFORM000000001"));
+
+    let login = hex::decode(LOGIN).unwrap();
+    let actions = relay.forward_frame(login.clone(), NOW);
+    assert_eq!(actions, [Action::Send(login)]);
+    let (emitted, _) = exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(emitted, [Command::VehicleLogin]);
+
+    let packet = parser::parse_bytes(&frame(0x02, &real_time_body())).unwrap();
+    let actions = relay.forward(&packet, NOW).unwrap();
+    let (emitted, _) = exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(emitted, [Command::RealTimeReport]);
+}
+
+#[test]
+fn test_outage_is_reissued() {
+    let mut relay = Relay::new(config()).unwrap();
+    let mut upstream = upstream();
+    let actions = relay.connected(NOW);
+    exchange(&mut relay, &mut upstream, actions);
+    relay.disconnected(NOW);
+
+    assert!(relay
+        .forward_frame(frame(0x02, &real_time_body()), NOW)
+        .is_empty());
+    assert!(relay.forward_frame(frame(0x07, &[]), NOW).is_empty());
+    assert_eq!(relay.buffered(), 2);
+
+    let mut upstream = self::upstream();
+    let actions = relay.connected(NOW);
+    let (emitted, _) = exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(emitted, [Command::ReissueReport]);
+    assert_eq!(relay.buffered(), 0);
+}
+
+#[test]
+fn test_unacknowledged_frames_are_sent_again() {
+    let mut relay = Relay::new(Config {
+        await_acks: true,
+        ..config()
+    })
+    .unwrap();
+    let mut upstream = upstream();
+    let actions = relay.connected(NOW);
+    exchange(&mut relay, &mut upstream, actions);
+
+    let actions = relay.forward_frame(hex::decode(LOGIN).unwrap(), NOW);
+    exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(relay.unacked(), 0);
+
+    // lost on the way
+    relay.forward_frame(frame(0x02, &real_time_body()), NOW);
+    assert_eq!(relay.unacked(), 1);
+    let timeout = Config::default().response_timeout;
+    assert_eq!(relay.poll(NOW + timeout), [Action::Disconnect]);
+    assert_eq!(relay.buffered(), 1);
+
+    let mut upstream = self::upstream();
+    let actions = relay.connected(NOW);
+    let (emitted, _) = exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(emitted, [Command::ReissueReport]);
+    assert_eq!(relay.unacked(), 0);
+}
+
+#[test]
+fn test_refused_login() {
+    let mut relay = Relay::new(Config {
+        password: "wrong".into(),
+        ..config()
+    })
+    .unwrap();
+    let mut upstream = upstream();
+    let actions = relay.connected(NOW);
+    let (_, closed) = exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(
+        closed,
+        Some(Close::Violation(Violation::Credentials("relay".into())))
+    );
+    assert!(!relay.is_logged_in());
+    assert_eq!(
+        relay.deadline(),
+        Some(NOW + Config::default().login_interval)
+    );
+}
+
+#[test]
+fn test_platform_logout() {
+    let mut relay = Relay::new(config()).unwrap();
+    let mut upstream = upstream();
+    let actions = relay.connected(NOW);
+    exchange(&mut relay, &mut upstream, actions);
+
+    let actions = relay.logout(NOW);
+    let (_, closed) = exchange(&mut relay, &mut upstream, actions);
+    assert_eq!(closed, Some(Close::Logout));
+    assert!(upstream.is_closed());
+    assert!(!relay.is_logged_in());
+    assert_eq!(relay.deadline(), None);
+}
+
+#[test]
+fn test_login_fields_must_fit() {
+    let username = Config {
+        username: "relay-user-too-long".into(),
+        ..config()
+    };
+    assert!(Relay::new(username).is_err());
+    let platform_id = Config {
+        platform_id: "PLATFORM0000000001".into(),
+        ..config()
+    };
+    assert!(Relay::new(platform_id).is_err());
+}
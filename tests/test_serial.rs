@@ -0,0 +1,79 @@
+extern crate vin;
+
+use vin::packet::serial::{self, Checker, Issue, LoginSequence};
+use vin::packet::Time;
+
+fn day(day: u8) -> Time {
+    Time {
+        year: 24,
+        month: 5,
+        day,
+        hour: 8,
+        minute: 0,
+        second: 0,
+    }
+}
+
+#[test]
+fn test_login_sequence() {
+    let mut seq = LoginSequence::new();
+    assert_eq!(seq.logout(), None);
+    assert_eq!(seq.next_login(&day(1)), 1);
+    assert_eq!(seq.next_login(&day(1)), 2);
+    assert_eq!(seq.logout(), Some(2));
+    assert_eq!(seq.next_login(&day(2)), 1);
+
+    seq.sn = serial::MAX;
+    assert_eq!(seq.next_login(&day(2)), 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_persistence() {
+    let mut seq = LoginSequence::new();
+    seq.next_login(&day(2));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sequence");
+    assert_eq!(LoginSequence::load(&path).unwrap(), LoginSequence::new());
+    seq.save(&path).unwrap();
+    let mut loaded = LoginSequence::load(&path).unwrap();
+    assert_eq!(loaded, seq);
+    assert_eq!(loaded.next_login(&day(2)), 2);
+}
+
+#[test]
+fn test_checker() {
+    let vin = "LZYTBGBW6J1014194";
+    let mut checker = Checker::new();
+    assert_eq!(checker.login(vin, &day(1), 7), Ok(()));
+    assert_eq!(checker.logout(vin, 7), Ok(()));
+    assert_eq!(checker.login(vin, &day(1), 8), Ok(()));
+    assert_eq!(
+        checker.logout(vin, 7),
+        Err(Issue::Mismatch {
+            login: Some(8),
+            actual: 7
+        })
+    );
+    assert_eq!(checker.login(vin, &day(1), 8), Err(Issue::Reused(8)));
+    assert_eq!(
+        checker.login(vin, &day(1), 3),
+        Err(Issue::OutOfOrder {
+            expected: 9,
+            actual: 3
+        })
+    );
+    // an earlier serial of the day, and rejected ones not advancing
+    assert_eq!(checker.login(vin, &day(1), 7), Err(Issue::Reused(7)));
+    assert_eq!(checker.login(vin, &day(1), 9), Ok(()));
+    assert_eq!(checker.logout(vin, 9), Ok(()));
+    assert_eq!(checker.login(vin, &day(2), 1), Ok(()));
+    assert_eq!(checker.login("LSVAU2180N2183294", &day(2), 1), Ok(()));
+    assert_eq!(
+        checker.logout("LFV2A21K8G4000001", 1),
+        Err(Issue::Mismatch {
+            login: None,
+            actual: 1
+        })
+    );
+}
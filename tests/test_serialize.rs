@@ -1,13 +1,60 @@
 extern crate vin;
 
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
-
-use vin::packet::body::Body;
-use vin::packet::types::Time;
+use vin::packet::body::VehicleLogin;
+use vin::packet::types::SubsysCode;
+use vin::serde::{Deserializer, Serializer};
 
 #[test]
 fn test_vehicle_login() {
     let text = "232301fe4c5a595442474257364a3130313431393401001e120a1e14233600fd383938363034303231303137303031373937373901005c" ;
     vin::packet::parser::pares_hex(text).unwrap();
 }
+
+fn login_body(subsys_num: u8, subsys_len: u8, codes: &[u8]) -> Vec<u8> {
+    let mut body = hex::decode("120a1e1423360001").unwrap();
+    body.extend_from_slice(b"89860402101700179779");
+    body.push(subsys_num);
+    body.push(subsys_len);
+    body.extend_from_slice(codes);
+    body
+}
+
+fn encode(login: &VehicleLogin) -> vin::packet::error::Result<Vec<u8>> {
+    let mut buff = Vec::new();
+    login.serialize(&mut Serializer::new(&mut buff))?;
+    Ok(buff)
+}
+
+#[test]
+fn test_subsystem_codes() {
+    let body = login_body(2, 4, b"AB01CD\0\0");
+    let login = VehicleLogin::deserialize(&mut Deserializer::from_slice(&body)).unwrap();
+    assert_eq!(login.subsys_len, 4);
+    let codes: Vec<&str> = login
+        .subsys_codes
+        .iter()
+        .map(|c| c.message.as_str())
+        .collect();
+    assert_eq!(codes, ["AB01", "CD"]);
+    assert_eq!(encode(&login).unwrap(), body);
+
+    // 200 * 50 used to overflow the u8 length computation
+    let body = login_body(200, 50, &[b'0'; 10000]);
+    let login = VehicleLogin::deserialize(&mut Deserializer::from_slice(&body)).unwrap();
+    assert_eq!(login.subsys_codes.len(), 200);
+    assert_eq!(encode(&login).unwrap(), body);
+}
+
+#[test]
+fn test_subsystem_code_limits() {
+    let body = login_body(1, 51, &[b'0'; 51]);
+    let err = VehicleLogin::deserialize(&mut Deserializer::from_slice(&body)).unwrap_err();
+    assert_eq!(err.path(), Some("subsys_len"));
+
+    let body = login_body(1, 4, b"AB01");
+    let mut login = VehicleLogin::deserialize(&mut Deserializer::from_slice(&body)).unwrap();
+    login
+        .subsys_codes
+        .push(SubsysCode::from("ABCDE".to_string()));
+    assert!(encode(&login).is_err());
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use common::{frame, LOGIN};
+use vin::packet::{parser, Response};
+
+fn reply(stream: &mut TcpStream, data: &[u8]) -> vin::packet::Packet {
+    stream.write_all(data).unwrap();
+    let mut header = [0; 24];
+    stream.read_exact(&mut header).unwrap();
+    let mut rest = vec![0; parser::frame_len(&header).unwrap() - 24];
+    stream.read_exact(&mut rest).unwrap();
+    let mut data = header.to_vec();
+    data.extend_from_slice(&rest);
+    parser::parse_bytes(&data).unwrap()
+}
+
+/// Starts `program` and returns it with the address it listens on and the
+/// lines it writes to stderr.
+fn spawn(program: &str, args: &[&str]) -> (Child, String, Receiver<String>) {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let addr = line.trim().rsplit(' ').next().unwrap().to_string();
+    let (lines, received) = mpsc::channel();
+    thread::spawn(move || {
+        for line in stderr.lines() {
+            let _ = lines.send(line.unwrap_or_default());
+        }
+    });
+    (child, addr, received)
+}
+
+fn commands(child: &mut Child, count: usize) -> Vec<serde_json::Value> {
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut commands = Vec::new();
+    for _ in 0..count {
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+        commands.push(record["packet"]["command"].clone());
+    }
+    commands
+}
+
+#[test]
+fn test_server_answers_and_writes_packets() {
+    let (mut server, addr, _) = spawn(
+        env!("CARGO_BIN_EXE_vin-server"),
+        &["--listen", "127.0.0.1:0"],
+    );
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let login = reply(&mut stream, &hex::decode(LOGIN).unwrap());
+    assert_eq!(login.response, Response::Success);
+    let heartbeat = reply(&mut stream, &frame(0x07, &[]));
+    assert_eq!(heartbeat.response, Response::Success);
+    let logout = reply(
+        &mut stream,
+        &frame(0x04, &[0x12, 0x0a, 0x1e, 0x14, 0x30, 0x00, 0x00, 0xfd]),
+    );
+    assert_eq!(logout.response, Response::Success);
+    assert_eq!(stream.read(&mut [0; 16]).unwrap(), 0);
+
+    let records = commands(&mut server, 2);
+    server.kill().unwrap();
+    server.wait().unwrap();
+    assert_eq!(records, ["vehicle_login", "vehicle_logout"]);
+}
+
+#[test]
+fn test_relay_forwards_to_stand_in() {
+    let credentials = ["--username", "relay", "--password", "secret"];
+    let (mut upstream, upstream_addr, _) = spawn(
+        env!("CARGO_BIN_EXE_vin-relay"),
+        &[&["--stand-in", "--listen", "127.0.0.1:0"], &credentials[..]].concat(),
+    );
+    let (mut relay, addr, log) = spawn(
+        env!("CARGO_BIN_EXE_vin-relay"),
+        &[
+            &["--listen", "127.0.0.1:0", "--upstream", &upstream_addr][..],
+            &["--platform-id", "PLATFORM000000001"],
+            &credentials,
+        ]
+        .concat(),
+    );
+    // frames kept until then are too old to be reissued
+    while log.recv().unwrap() != "upstream: logged in" {}
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let login = reply(&mut stream, &hex::decode(LOGIN).unwrap());
+    assert_eq!(login.response, Response::Success);
+    let logout = reply(
+        &mut stream,
+        &frame(0x04, &[0x12, 0x0a, 0x1e, 0x14, 0x30, 0x00, 0x00, 0xfd]),
+    );
+    assert_eq!(logout.response, Response::Success);
+
+    let forwarded = commands(&mut upstream, 2);
+    relay.kill().unwrap();
+    relay.wait().unwrap();
+    upstream.kill().unwrap();
+    upstream.wait().unwrap();
+    assert_eq!(forwarded, ["vehicle_login", "vehicle_logout"]);
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "std")]
+
+use vin::packet::body::Body;
+use vin::packet::realtime::Item;
+use vin::packet::{parser, Command};
+use vin::sim::{self, Profile, Simulator};
+
+const START: i64 = 1_540_902_954;
+
+fn profile() -> Profile {
+    Profile {
+        duration: 600,
+        ..Profile::city()
+    }
+}
+
+#[test]
+fn test_vins_validate() {
+    let vins = sim::vins(20, 7);
+    assert_eq!(vins.len(), 20);
+    for vin in &vins {
+        assert_eq!(vin.validate(), Ok(()), "{}", vin);
+    }
+}
+
+#[test]
+fn test_frames_decode() {
+    let frames: Vec<_> = Simulator::new(sim::vins(3, 1), profile(), START, 1).collect();
+    assert!(frames.windows(2).all(|w| w[0].at <= w[1].at));
+    for frame in &frames {
+        let data = frame.packet.encode().unwrap();
+        let packet = parser::parse_bytes(&data).unwrap();
+        assert_eq!(packet.command, frame.packet.command);
+        assert_eq!(packet.vin.as_str(), frame.packet.vin.as_str());
+    }
+}
+
+#[test]
+fn test_session_of_one_vehicle() {
+    let vins = sim::vins(1, 2);
+    let frames: Vec<_> = Simulator::new(vins, profile(), START, 2).collect();
+    let first = frames.first().unwrap();
+    let last = frames.last().unwrap();
+    let login_sn = match &first.packet.body {
+        Body::VehicleLogin(login) => login.sn,
+        body => panic!("expected a login, got {:?}", body),
+    };
+    match &last.packet.body {
+        Body::VehicleLogout(logout) => assert_eq!(logout.sn, login_sn),
+        body => panic!("expected a logout, got {:?}", body),
+    }
+    assert_eq!(last.at - first.at, 600);
+
+    let reports: Vec<_> = frames
+        .iter()
+        .filter(|f| f.packet.command == Command::RealTimeReport)
+        .collect();
+    assert_eq!(reports.len(), 60);
+    assert!(reports.windows(2).all(|w| w[1].at - w[0].at == 10));
+    let heartbeats = frames
+        .iter()
+        .filter(|f| f.packet.command == Command::HeartBeat)
+        .count();
+    assert_eq!(heartbeats, 9);
+}
+
+#[test]
+fn test_soc_drains_while_driving() {
+    let frames: Vec<_> = Simulator::new(sim::vins(1, 3), Profile::highway(), START, 3).collect();
+    let vehicles: Vec<_> = frames
+        .iter()
+        .filter_map(|f| match &f.packet.body {
+            Body::RealTimeReport(report) => report.items.iter().find_map(|item| match item {
+                Item::Vehicle(vehicle) => Some(vehicle),
+                _ => None,
+            }),
+            _ => None,
+        })
+        .collect();
+    let (first, last) = (vehicles.first().unwrap(), vehicles.last().unwrap());
+    assert!(last.mileage > first.mileage + 100);
+    assert!(last.soc < first.soc);
+    assert!(vehicles.iter().any(|v| v.speed > 900));
+}
+
+#[test]
+fn test_same_seed_same_traffic() {
+    let encode = |seed| -> Vec<Vec<u8>> {
+        Simulator::new(sim::vins(2, seed), profile(), START, seed)
+            .map(|f| f.packet.encode().unwrap())
+            .collect()
+    };
+    assert_eq!(encode(5), encode(5));
+    assert_ne!(encode(5), encode(6));
+}
+
+#[test]
+fn test_route_without_length() {
+    let parked = Profile {
+        route: vec![(120.0, 30.0), (120.0, 30.0)],
+        duration: 3600,
+        ..Profile::city()
+    };
+    let frames: Vec<_> = Simulator::new(sim::vins(1, 4), parked, START, 4).collect();
+    let location = frames.iter().rev().find_map(|f| match &f.packet.body {
+        Body::RealTimeReport(report) => report.items.iter().find_map(|item| match item {
+            Item::Location(location) => Some(location),
+            _ => None,
+        }),
+        _ => None,
+    });
+    assert_eq!(location.unwrap().longitude, 120_000_000);
+}
+
+#[test]
+fn test_pack_voltage_sums_the_cells() {
+    let frames: Vec<_> = Simulator::new(sim::vins(1, 5), profile(), START, 5).collect();
+    let report = frames
+        .iter()
+        .find_map(|f| match &f.packet.body {
+            Body::RealTimeReport(report) => Some(report),
+            _ => None,
+        })
+        .unwrap();
+    let (mut vehicle, mut subsystem) = (None, None);
+    for item in &report.items {
+        match item {
+            Item::Vehicle(v) => vehicle = Some(v),
+            Item::Voltages(v) => subsystem = Some(&v.subsystems[0]),
+            _ => {}
+        }
+    }
+    let (vehicle, subsystem) = (vehicle.unwrap(), subsystem.unwrap());
+    // cells in mV, the pack in 0.1 V
+    let cells = subsystem.cells.len() as f64;
+    let mean = subsystem.cells.iter().map(|&c| c as f64).sum::<f64>() / cells;
+    let expected = cells * mean / 100.0;
+    for voltage in [vehicle.voltage, subsystem.voltage] {
+        assert!((voltage as f64 - expected).abs() < 1.0, "{}", voltage);
+    }
+}
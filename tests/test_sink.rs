@@ -0,0 +1,61 @@
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::time::Duration;
+
+use common::LOGIN;
+use vin::packet::parser;
+use vin::sink::{JsonLines, Record, RotatingFiles, Sink};
+
+fn write(sink: &mut dyn Sink, count: usize) {
+    let packet = parser::pares_hex(LOGIN).unwrap();
+    for i in 0..count {
+        let record = Record {
+            received: Duration::from_millis(1_540_902_954_000 + i as u64),
+            peer: "127.0.0.1:50212",
+            packet: &packet,
+        };
+        sink.write(&record).unwrap();
+    }
+}
+
+#[test]
+fn test_json_lines() {
+    let mut sink = JsonLines::new(Vec::new());
+    write(&mut sink, 2);
+    let text = String::from_utf8(sink.into_inner()).unwrap();
+    let lines: Vec<serde_json::Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["received_ms"], 1_540_902_954_001u64);
+    assert_eq!(lines[0]["peer"], "127.0.0.1:50212");
+    assert_eq!(lines[0]["packet"]["vin"], "LZYTBGBW6J1014194");
+}
+
+#[test]
+fn test_rotating_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("packets.jsonl");
+    let mut sink = RotatingFiles::open(&path, 1000, 2).unwrap();
+    write(&mut sink, 20);
+
+    let line_len = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .len()
+        + 1;
+    let per_file = 1000 / line_len;
+    let lines = |path| std::fs::read_to_string(path).unwrap().lines().count();
+    assert_eq!(lines(sink.rotated(1)), per_file);
+    assert_eq!(lines(sink.rotated(2)), per_file);
+    assert!(!sink.rotated(3).exists());
+    assert_eq!(lines(path.clone()), (20 - 1) % per_file + 1);
+    for n in 1..=2 {
+        assert!(std::fs::metadata(sink.rotated(n)).unwrap().len() <= 1000);
+    }
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{frame, LOGIN};
+use vin::packet::stream::{Chunk, Framer};
+
+#[test]
+fn test_frames_split_across_reads() {
+    let login = hex::decode(LOGIN).unwrap();
+    let heartbeat = frame(0x07, &[]);
+    let mut data = login.clone();
+    data.extend_from_slice(&heartbeat);
+
+    let mut framer = Framer::new();
+    let mut chunks = Vec::new();
+    for piece in data.chunks(7) {
+        framer.extend(piece);
+        chunks.extend(&mut framer);
+    }
+    assert_eq!(chunks, [Chunk::Frame(login), Chunk::Frame(heartbeat)]);
+    assert!(framer.pending().is_empty());
+}
+
+#[test]
+fn test_garbage_is_skipped() {
+    let heartbeat = frame(0x07, &[]);
+    let mut framer = Framer::new();
+    framer.extend(b"\x00\x01#");
+    assert_eq!(framer.next(), Some(Chunk::Garbage(vec![0, 1])));
+    assert_eq!(framer.next(), None);
+    assert_eq!(framer.pending(), b"#");
+
+    framer.extend(b"x");
+    framer.extend(&heartbeat);
+    let chunks: Vec<Chunk> = framer.by_ref().collect();
+    assert_eq!(
+        chunks,
+        [Chunk::Garbage(b"#x".to_vec()), Chunk::Frame(heartbeat)]
+    );
+}
+
+#[test]
+fn test_incomplete_frame_waits() {
+    let login = hex::decode(LOGIN).unwrap();
+    let mut framer = Framer::new();
+    framer.extend(&login[..30]);
+    assert_eq!(framer.next(), None);
+    assert_eq!(framer.pending().len(), 30);
+    framer.extend(&login[30..]);
+    assert_eq!(framer.next(), Some(Chunk::Frame(login)));
+}
+
+#[test]
+fn test_implausible_headers_are_skipped() {
+    let heartbeat = frame(0x07, &[]);
+    let mut framer = Framer::new();
+    // an unknown command, then a VIN that is not printable
+    framer.extend(b"##\x00#");
+    let mut bad_vin = frame(0x07, &[]);
+    bad_vin[4] = 0x01;
+    framer.extend(&bad_vin);
+    framer.extend(&heartbeat);
+    let chunks: Vec<Chunk> = framer.by_ref().collect();
+    assert_eq!(
+        chunks,
+        [
+            Chunk::Garbage(b"##\x00".to_vec()),
+            Chunk::Garbage(b"#".to_vec()),
+            Chunk::Garbage(bad_vin),
+            Chunk::Frame(heartbeat),
+        ]
+    );
+}
+
+#[test]
+fn test_resync_after_checksum_failure() {
+    let heartbeat = frame(0x07, &[]);
+    // a false frame whose length swallows a real one
+    let mut outer = frame(0x07, &heartbeat);
+    *outer.last_mut().unwrap() ^= 0x01;
+    let mut framer = Framer::new();
+    framer.extend(&outer);
+
+    let chunk = framer.next().unwrap();
+    assert_eq!(chunk, Chunk::Frame(outer.clone()));
+    if let Chunk::Frame(frame) = chunk {
+        framer.resync(frame);
+    }
+    let chunks: Vec<Chunk> = framer.by_ref().collect();
+    assert_eq!(chunks[0], Chunk::Garbage(outer[..24].to_vec()));
+    assert_eq!(chunks[1], Chunk::Frame(heartbeat));
+}
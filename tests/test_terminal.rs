@@ -0,0 +1,204 @@
+mod common;
+
+use core::time::Duration;
+
+use common::real_time_body;
+use vin::packet::body::Body;
+use vin::packet::realtime::RealTimeReport;
+use vin::packet::reissue::{MemoryStore, ReissueStore};
+use vin::packet::terminal::{Action, Config, TerminalSession};
+use vin::packet::{parser, platform, Command, Packet, Response, Time};
+
+const NOW: Duration = Duration::from_secs(1_540_902_954);
+const VIN: &str = "LZYTBGBW6J1014194";
+const ICCID: &str = "89860402101700179779";
+
+fn secs(n: u64) -> Duration {
+    NOW + Duration::from_secs(n)
+}
+
+fn report() -> RealTimeReport {
+    match parser::parse_body(Command::RealTimeReport, &real_time_body()).unwrap() {
+        Body::RealTimeReport(report) => report,
+        body => panic!("expected a report, got {:?}", body),
+    }
+}
+
+fn sent(actions: Vec<Action>) -> Vec<Packet> {
+    actions
+        .into_iter()
+        .map(|action| match action {
+            Action::Send(packet) => packet,
+            action => panic!("expected a packet, got {:?}", action),
+        })
+        .collect()
+}
+
+fn commands(packets: &[Packet]) -> Vec<Command> {
+    packets.iter().map(|p| p.command).collect()
+}
+
+/// Session logged in at `NOW`.
+fn logged_in() -> TerminalSession {
+    let mut session = TerminalSession::new(VIN, ICCID, Config::default());
+    let login = sent(session.connected(NOW)).remove(0);
+    session.handle(login.reply(Response::Success).unwrap(), NOW);
+    assert!(session.is_logged_in());
+    session
+}
+
+#[test]
+fn test_login_retries() {
+    let config = Config::default();
+    let mut session = TerminalSession::new(VIN, ICCID, config);
+    let login = sent(session.connected(NOW));
+    assert_eq!(commands(&login), [Command::VehicleLogin]);
+    assert_eq!(session.deadline(), Some(secs(60)));
+
+    // unanswered
+    assert!(session.poll(secs(60)).is_empty());
+    assert_eq!(session.deadline(), Some(secs(120)));
+    let login = sent(session.poll(secs(120))).remove(0);
+    // refused
+    session.handle(login.reply(Response::Fail).unwrap(), secs(121));
+    let login = sent(session.poll(secs(181)));
+    assert_eq!(commands(&login), [Command::VehicleLogin]);
+    // third failure, wait half an hour
+    assert!(session.poll(secs(241)).is_empty());
+    assert_eq!(session.deadline(), Some(secs(241 + 1800)));
+    assert!(session.poll(secs(2000)).is_empty());
+    let login = sent(session.poll(secs(2041))).remove(0);
+    match login.body {
+        Body::VehicleLogin(body) => {
+            assert_eq!(body.sn, 4);
+            assert_eq!(body.iccid.as_str(), ICCID);
+        }
+        body => panic!("expected a login, got {:?}", body),
+    }
+    assert_eq!(session.sequence().sn, 4);
+}
+
+#[test]
+fn test_heartbeats() {
+    let mut session = logged_in();
+    assert_eq!(session.deadline(), Some(secs(30)));
+    assert_eq!(sent(session.report(report(), secs(20))).len(), 1);
+    assert!(session.poll(secs(30)).is_empty());
+
+    let heartbeat = sent(session.poll(secs(50))).remove(0);
+    assert_eq!(heartbeat.command, Command::HeartBeat);
+    session.handle(heartbeat.reply(Response::Success).unwrap(), secs(51));
+    assert_eq!(session.deadline(), Some(secs(80)));
+
+    // unanswered heart beats drop the connection
+    sent(session.poll(secs(80)));
+    sent(session.poll(secs(110)));
+    assert_eq!(session.deadline(), Some(secs(140)));
+    let actions = session.poll(secs(140));
+    assert!(matches!(actions[..], [Action::Disconnect]));
+    assert!(!session.is_logged_in());
+}
+
+#[test]
+fn test_reports_are_reissued_after_login() {
+    let mut session = logged_in();
+    session.disconnected();
+    assert!(session.report(report(), secs(10)).is_empty());
+    assert!(session.report(report(), secs(20)).is_empty());
+    assert_eq!(session.buffered(), 2);
+
+    let login = sent(session.connected(secs(25))).remove(0);
+    assert!(session.report(report(), secs(30)).is_empty());
+    let reissued = sent(session.handle(login.reply(Response::Success).unwrap(), secs(31)));
+    assert_eq!(commands(&reissued), [Command::ReissueReport; 3]);
+    assert_eq!(session.buffered(), 0);
+    let sent = sent(session.report(report(), secs(40)));
+    assert_eq!(commands(&sent), [Command::RealTimeReport]);
+}
+
+#[test]
+fn test_buffer_drops_oldest() {
+    let config = Config {
+        buffer_capacity: 2,
+        ..Config::default()
+    };
+    let mut session = TerminalSession::new(VIN, ICCID, config);
+    for _ in 0..5 {
+        session.report(report(), NOW);
+    }
+    assert_eq!(session.buffered(), 2);
+}
+
+/// Carries `actions` of the terminal to the platform and its answers back
+/// until both are done, returning the commands the platform emitted and
+/// whether the terminal disconnected.
+fn exchange(
+    terminal: &mut TerminalSession,
+    platform: &mut platform::PlatformSession,
+    mut actions: Vec<Action>,
+    now: Duration,
+) -> (Vec<Command>, bool) {
+    let mut emitted = Vec::new();
+    let mut disconnected = false;
+    while !actions.is_empty() {
+        let mut next = Vec::new();
+        for action in actions {
+            let packet = match action {
+                Action::Send(packet) => parser::parse_bytes(&packet.encode().unwrap()).unwrap(),
+                Action::Disconnect => {
+                    disconnected = true;
+                    continue;
+                }
+                Action::Store(err) => panic!("{}", err),
+                Action::Drop(err) => panic!("{}", err),
+            };
+            for action in platform.handle(packet, now) {
+                match action {
+                    platform::Action::Send(reply) => {
+                        let reply = parser::parse_bytes(&reply.encode().unwrap()).unwrap();
+                        next.extend(terminal.handle(reply, now));
+                    }
+                    platform::Action::Emit(packet) => emitted.push(packet.command),
+                    platform::Action::Close(_) => {}
+                }
+            }
+        }
+        actions = next;
+    }
+    (emitted, disconnected)
+}
+
+#[test]
+fn test_session_with_platform() {
+    let mut terminal = TerminalSession::new(VIN, ICCID, Config::default());
+    let mut platform = platform::PlatformSession::new(platform::Config::default(), NOW);
+
+    let mut actions = terminal.report(report(), NOW);
+    actions.extend(terminal.connected(secs(1)));
+    let (emitted, _) = exchange(&mut terminal, &mut platform, actions, secs(1));
+    assert_eq!(emitted, [Command::VehicleLogin, Command::ReissueReport]);
+
+    let actions = terminal.report(report(), secs(10));
+    let (emitted, _) = exchange(&mut terminal, &mut platform, actions, secs(10));
+    assert_eq!(emitted, [Command::RealTimeReport]);
+
+    let actions = terminal.logout(secs(20));
+    let (emitted, disconnected) = exchange(&mut terminal, &mut platform, actions, secs(20));
+    assert_eq!(emitted, [Command::VehicleLogout]);
+    assert!(disconnected);
+    assert!(platform.is_closed());
+}
+
+#[test]
+fn test_undecodable_reissues_are_reported() {
+    let mut store = MemoryStore::new();
+    store
+        .push(VIN, Time::from_unix(NOW.as_secs() as i64), vec![0x23, 0x23])
+        .unwrap();
+    let mut session =
+        TerminalSession::new(VIN, ICCID, Config::default()).with_store(Box::new(store));
+    let login = sent(session.connected(NOW)).remove(0);
+    let actions = session.handle(login.reply(Response::Success).unwrap(), NOW);
+    assert!(matches!(actions[..], [Action::Drop(_)]));
+    assert_eq!(session.buffered(), 0);
+}
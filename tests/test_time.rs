@@ -0,0 +1,95 @@
+extern crate vin;
+
+use vin::packet::time::Error;
+use vin::packet::Time;
+
+fn time(year: u8, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Time {
+    Time {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    }
+}
+
+#[test]
+fn test_validation() {
+    assert!(time(24, 2, 29, 23, 59, 59).is_valid());
+    assert_eq!(time(18, 10, 30, 20, 35, 54).full_year(), 2018);
+    assert_eq!(
+        time(23, 2, 29, 0, 0, 0).validate().unwrap_err(),
+        Error::Range {
+            field: "day",
+            value: 29
+        }
+    );
+    assert_eq!(
+        time(23, 13, 1, 0, 0, 0).validate().unwrap_err(),
+        Error::Range {
+            field: "month",
+            value: 13
+        }
+    );
+    assert!(!time(23, 4, 31, 0, 0, 0).is_valid());
+    assert!(!time(23, 4, 30, 24, 0, 0).is_valid());
+    assert!(!time(100, 1, 1, 0, 0, 0).is_valid());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_now() {
+    assert!(Time::now().is_valid());
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_chrono() {
+    use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+    use std::convert::TryFrom;
+
+    let t = time(18, 10, 30, 20, 35, 54);
+    let dt = DateTime::<FixedOffset>::try_from(t).unwrap();
+    assert_eq!(dt.to_rfc3339(), "2018-10-30T20:35:54+08:00");
+    assert_eq!(Time::try_from(dt.with_timezone(&Utc)).unwrap(), t);
+    let naive = NaiveDateTime::try_from(t).unwrap();
+    assert_eq!(Time::try_from(naive).unwrap(), t);
+
+    assert!(NaiveDateTime::try_from(time(23, 2, 29, 0, 0, 0)).is_err());
+    let old = DateTime::parse_from_rfc3339("1999-12-31T15:59:59Z").unwrap();
+    assert_eq!(Time::try_from(old).unwrap_err(), Error::Year(1999));
+    let new_year = DateTime::parse_from_rfc3339("1999-12-31T16:00:00Z").unwrap();
+    assert_eq!(Time::try_from(new_year).unwrap(), time(0, 1, 1, 0, 0, 0));
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn test_time() {
+    use std::convert::TryFrom;
+    use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+    let t = time(18, 10, 30, 20, 35, 54);
+    let dt = OffsetDateTime::try_from(t).unwrap();
+    assert_eq!(dt.offset(), UtcOffset::from_hms(8, 0, 0).unwrap());
+    assert_eq!(dt.unix_timestamp(), 1_540_902_954);
+    assert_eq!(Time::try_from(dt.to_offset(UtcOffset::UTC)).unwrap(), t);
+    let primitive = PrimitiveDateTime::try_from(t).unwrap();
+    assert_eq!(Time::try_from(primitive).unwrap(), t);
+
+    assert!(PrimitiveDateTime::try_from(time(23, 2, 29, 0, 0, 0)).is_err());
+}
+
+#[test]
+fn test_unix_seconds() {
+    // 2018-10-30T12:35:54Z
+    let at = time(18, 10, 30, 20, 35, 54);
+    assert_eq!(at.to_unix(), 1_540_902_954);
+    assert_eq!(Time::from_unix(1_540_902_954), at);
+    let leap = time(24, 2, 29, 0, 0, 0);
+    assert_eq!(Time::from_unix(leap.to_unix()), leap);
+    assert_eq!(
+        Time::from_unix(leap.to_unix() - 1),
+        time(24, 2, 28, 23, 59, 59)
+    );
+}
@@ -0,0 +1,113 @@
+extern crate vin;
+
+mod common;
+
+use common::{frame, LOGIN};
+use vin::packet::realtime::Item;
+use vin::packet::{parser, view, Command};
+
+fn real_time_body() -> Vec<u8> {
+    let mut body = vec![0x12, 0x0a, 0x1e, 0x14, 0x23, 0x36];
+    // location
+    body.extend_from_slice(&[0x05, 0x00, 0x07, 0x2b, 0x6e, 0x10, 0x01, 0xc9, 0xc3, 0x80]);
+    // voltages: one subsystem, three cells in this frame
+    body.extend_from_slice(&[
+        0x08, 0x01, 0x01, 0x0e, 0x10, 0x27, 0x10, 0x00, 0x60, 0x00, 0x01,
+    ]);
+    body.extend_from_slice(&[0x03, 0x0c, 0xe4, 0x0c, 0xe5, 0x0c, 0xe6]);
+    // engine
+    body.extend_from_slice(&[0x04, 0x02, 0xff, 0xff, 0xff, 0xff]);
+    body
+}
+
+#[test]
+fn test_view_borrows_vin() {
+    let data = hex::decode(LOGIN).unwrap();
+    let packet = view::Packet::parse(data.as_slice()).unwrap();
+    assert_eq!(packet.command, Command::VehicleLogin);
+    assert_eq!(packet.vin, "LZYTBGBW6J1014194");
+    assert!(data.as_ptr_range().contains(&packet.vin.as_ptr()));
+    assert_eq!(packet.body.len(), 30);
+}
+
+/// Heartbeat whose VIN field holds `vin`.
+fn heartbeat_with_vin(vin: &[u8; 17]) -> Vec<u8> {
+    let mut data = frame(0x07, &[]);
+    data[4..21].copy_from_slice(vin);
+    let last = data.len() - 1;
+    data[last] = parser::checksum(&data[2..last]);
+    data
+}
+
+#[test]
+fn test_view_decodes_vin_like_parser() {
+    // cut at the first NUL
+    let data = heartbeat_with_vin(b"LZYTBGBW\0J1014194");
+    let view = view::Packet::parse(&data).unwrap();
+    let packet = parser::parse_bytes(&data).unwrap();
+    assert_eq!(view.vin, packet.vin.as_str());
+    assert_eq!(view.vin, "LZYTBGBW");
+
+    // GB18030 rather than UTF-8
+    let data = heartbeat_with_vin(b"LZYTBGBW6J10141\xb1\xb1");
+    let view = view::Packet::parse(&data);
+    let packet = parser::parse_bytes(&data);
+    if cfg!(feature = "gbk") {
+        assert_eq!(view.unwrap().vin, packet.unwrap().vin.as_str());
+    } else {
+        assert_eq!(
+            view.unwrap_err().to_string(),
+            packet.unwrap_err().to_string()
+        );
+    }
+
+    // 0xFF never starts a GB18030 sequence
+    let data = heartbeat_with_vin(b"LZYTBGBW6J101419\xff");
+    let view = view::Packet::parse(&data).unwrap_err();
+    let packet = parser::parse_bytes(&data).unwrap_err();
+    assert_eq!(view.path(), Some("Header.vin"));
+    assert_eq!(view.to_string(), packet.to_string());
+}
+
+#[test]
+fn test_view_real_time_report() {
+    let data = frame(0x02, &real_time_body());
+    let packet = view::Packet::parse(data.as_slice()).unwrap();
+    let report = packet.real_time_report().unwrap();
+    assert_eq!(report.at.year, 0x12);
+
+    let items = report.items().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(items.len(), 3);
+    match &items[1] {
+        view::Item::Voltages(voltages) => {
+            let subsystem = voltages.subsystems().next().unwrap();
+            assert_eq!(subsystem.cell_count, 0x60);
+            assert_eq!(subsystem.len(), 3);
+            assert_eq!(subsystem.cell(1), Some(0x0ce5));
+            assert_eq!(subsystem.cell(3), None);
+            assert_eq!(
+                subsystem.cells().collect::<Vec<_>>(),
+                vec![0x0ce4, 0x0ce5, 0x0ce6]
+            );
+        }
+        item => panic!("unexpected item {:?}", item),
+    }
+    match &items[2] {
+        view::Item::Decoded(Item::Engine(engine)) => assert_eq!(engine.status, 0x02),
+        item => panic!("unexpected item {:?}", item),
+    }
+}
+
+#[test]
+fn test_parse_real_time_report() {
+    let data = frame(0x02, &real_time_body());
+    let packet = parser::parse_bytes(data.as_slice()).unwrap();
+    assert_eq!(packet.command, Command::RealTimeReport);
+}
+
+#[test]
+fn test_checksum_mismatch() {
+    let mut data = hex::decode(LOGIN).unwrap();
+    *data.last_mut().unwrap() ^= 0xff;
+    assert!(view::Packet::parse(data.as_slice()).is_err());
+}
@@ -0,0 +1,78 @@
+extern crate vin;
+
+mod common;
+
+use common::frame;
+use vin::packet::parser::{self, Options};
+use vin::packet::vin::{self as vin_number, Error};
+use vin::packet::{view, Strictness, Vin};
+
+#[test]
+fn test_structured_accessors() {
+    let vin = Vin::new("LZYTBGBW6J1014194").unwrap();
+    assert_eq!(vin.wmi(), Some("LZY"));
+    assert_eq!(vin.vds(), Some("TBGBW6"));
+    assert_eq!(vin.vis(), Some("J1014194"));
+    assert_eq!(vin.check_digit(), Some('6'));
+    assert_eq!(vin.model_year_code(), Some('J'));
+    assert_eq!(vin.model_year(), Some(2018));
+    assert_eq!(vin.plant_code(), Some('1'));
+    assert_eq!(vin.serial(), Some("014194"));
+}
+
+#[test]
+fn test_validation_errors() {
+    assert_eq!(Vin::new("LZYTBGBW6J101419").unwrap_err(), Error::Length(16));
+    assert_eq!(
+        vin_number::validate("LZYTBGBW6J10I4194").unwrap_err(),
+        Error::Char {
+            position: 13,
+            ch: 'I'
+        }
+    );
+    assert_eq!(
+        vin_number::validate("LZYTBGBW6U1014194").unwrap_err(),
+        Error::ModelYear('U')
+    );
+    assert_eq!(
+        vin_number::validate("LZYTBGBW7J1014194").unwrap_err(),
+        Error::CheckDigit {
+            expected: '6',
+            actual: '7'
+        }
+    );
+    assert_eq!(vin_number::compute_check_digit("1M8GDM9A_KP042788"), None);
+    assert_eq!(
+        vin_number::compute_check_digit("1M8GDM9AXKP042788"),
+        Some('X')
+    );
+}
+
+fn bad_vin_heartbeat() -> Vec<u8> {
+    let mut data = frame(0x07, &[]);
+    data[12] = b'7';
+    let last = data.len() - 1;
+    data[last] = parser::checksum(&data[2..last]);
+    data
+}
+
+#[test]
+fn test_decode_strictness() {
+    let data = bad_vin_heartbeat();
+    let options = |vin| Options {
+        vin,
+        ..Options::default()
+    };
+
+    let err = parser::parse_bytes_with(&data, &options(Strictness::Reject)).unwrap_err();
+    assert_eq!(err.path(), Some("Header.vin"));
+    assert!(view::Packet::parse_with(&data, &options(Strictness::Reject)).is_err());
+
+    let packet = parser::parse_bytes_with(&data, &options(Strictness::Warn)).unwrap();
+    assert!(matches!(packet.vin.issue(), Some(Error::CheckDigit { .. })));
+    let packet = view::Packet::parse_with(&data, &options(Strictness::Warn)).unwrap();
+    assert!(packet.vin_issue.is_some());
+
+    let packet = parser::parse_bytes_with(&data, &options(Strictness::Accept)).unwrap();
+    assert_eq!(packet.vin.issue(), None);
+}